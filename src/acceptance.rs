@@ -0,0 +1,119 @@
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    config::{Config, Source, Tags},
+    scan::ScanEntry,
+};
+
+/// A reviewed decision to accept the risk of a vulnerability until it expires. Unlike excluding
+/// paths from scans, accepted findings are still exported, labeled `accepted="true"`, so audits
+/// can check that every acceptance is justified, approved and not expired.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Acceptance {
+    /// Id of the vulnerability or any of its aliases, e.g. `CVE-2023-4039`.
+    pub cve: String,
+    #[serde(default)]
+    pub scope: AcceptanceScope,
+    pub justification: String,
+    /// Who reviewed and approved the acceptance.
+    pub approver: String,
+    /// Last day the acceptance applies.
+    pub expires: NaiveDate,
+}
+
+/// Where an acceptance applies, everywhere if empty.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AcceptanceScope {
+    /// Name of the vulnerable package.
+    pub package: Option<String>,
+    /// Prefix of the source, e.g. an image name like `ghcr.io/famedly/app` or a path.
+    pub source: Option<String>,
+    /// Tags the source needs to have.
+    #[serde(default)]
+    pub tags: Tags,
+}
+
+impl Acceptance {
+    /// Acceptances without justification or approver are invalid and never apply.
+    pub fn is_valid(&self) -> bool {
+        !self.justification.trim().is_empty() && !self.approver.trim().is_empty()
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expires < today
+    }
+
+    /// Whether the finding of the source is accepted today.
+    pub fn applies(
+        &self,
+        source: &Source,
+        tags: Option<&Tags>,
+        entry: &ScanEntry,
+        today: NaiveDate,
+    ) -> bool {
+        let scope = &self.scope;
+        self.is_valid()
+            && !self.is_expired(today)
+            && (entry.vulnerability.id == self.cve
+                || entry
+                    .related_vulnerabilities
+                    .iter()
+                    .any(|related| related.id == self.cve))
+            && scope
+                .package
+                .as_ref()
+                .is_none_or(|package| *package == entry.artifact.name)
+            && scope
+                .source
+                .as_ref()
+                .is_none_or(|prefix| source.name().starts_with(prefix.as_str()))
+            && scope
+                .tags
+                .iter()
+                .all(|(key, value)| tags.and_then(|tags| tags.get(key)) == Some(value))
+    }
+}
+
+/// The acceptances of the configured file. Invalid acceptances are kept, so they show up in
+/// the metrics, but are logged.
+pub fn load_acceptances(config: &Config) -> Vec<Acceptance> {
+    let Some(path) = &config.acceptances else {
+        return Vec::new();
+    };
+    let acceptances: Vec<Acceptance> = match std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| Ok(serde_yaml::from_str(&content)?))
+    {
+        Ok(acceptances) => acceptances,
+        Err(e) => {
+            warn!("Failed to load acceptances from {}: {e:?}", path.display());
+            return Vec::new();
+        }
+    };
+    for acceptance in &acceptances {
+        if !acceptance.is_valid() {
+            warn!(
+                "Acceptance of {} has no justification or approver and doesn't apply",
+                acceptance.cve
+            );
+        }
+    }
+    acceptances
+}
+
+/// Whether any of the acceptances applies to the finding today.
+pub fn is_accepted(
+    acceptances: &[Acceptance],
+    source: &Source,
+    tags: Option<&Tags>,
+    entry: &ScanEntry,
+) -> bool {
+    let today = Utc::now().date_naive();
+    acceptances
+        .iter()
+        .any(|acceptance| acceptance.applies(source, tags, entry, today))
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    config::{Config, Source},
+    error::SsceError,
+    fs::{create_parent, write_atomic},
+    policy::Violation,
+    proxy::Integration,
+    report::Report,
+    secret::Secret,
+};
+
+/// Verdicts of the images against the policy, for admission controllers like OPA Gatekeeper or
+/// docker authorization plugins to gate deployments on.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct AdmissionConfig {
+    /// File the verdicts are written to.
+    pub path: Option<PathBuf>,
+    /// URL the verdicts are sent to with a PUT request, e.g. the data API of OPA at
+    /// `http://opa:8181/v1/data/ssce/images`.
+    pub url: Option<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImageVerdict {
+    pub verdict: Verdict,
+    /// Reference of the image on this host.
+    pub image: String,
+    /// Number of findings violating the policy.
+    pub violations: usize,
+    /// Start of the run which scanned the image.
+    pub scanned: DateTime<Utc>,
+    pub run_id: String,
+}
+
+/// Verdicts by image digest. Images without a repository digest, e.g. built locally, are listed
+/// by their image id.
+pub fn verdicts(report: &Report, violations: &[Violation]) -> BTreeMap<String, ImageVerdict> {
+    let mut counts: HashMap<&Source, usize> = HashMap::new();
+    for violation in violations {
+        *counts.entry(&violation.source).or_default() += 1;
+    }
+    report
+        .sources
+        .iter()
+        .filter_map(|source| {
+            let Source::DockerImage { name, id, digest } = &source.source else {
+                return None;
+            };
+            let key = digest
+                .as_deref()
+                .map_or(id.as_str(), |digest| {
+                    digest.rsplit_once('@').map_or(digest, |(_, digest)| digest)
+                })
+                .to_owned();
+            let violations = counts.get(&source.source).copied().unwrap_or_default();
+            Some((
+                key,
+                ImageVerdict {
+                    verdict: if violations == 0 {
+                        Verdict::Pass
+                    } else {
+                        Verdict::Fail
+                    },
+                    image: name.clone(),
+                    violations,
+                    scanned: report.started,
+                    run_id: report.run_id.clone(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Write the verdicts of the images to the configured file and URL. Without a policy, all
+/// images pass.
+pub async fn write_verdicts(
+    config: &Config,
+    report: &Report,
+    violations: &[Violation],
+) -> Result<()> {
+    let Some(admission) = &config.admission else {
+        return Ok(());
+    };
+    let verdicts = verdicts(report, violations);
+    debug!(images = verdicts.len(), "writing admission verdicts");
+
+    if let Some(path) = &admission.path {
+        write_file(path, &verdicts).map_err(|error| SsceError::Export {
+            output: path.display().to_string(),
+            error,
+        })?;
+    }
+    if let Some(url) = &admission.url {
+        let client = config.proxy.client(Integration::Admission)?;
+        put(&client, url, &admission.headers, &verdicts)
+            .await
+            .map_err(|error| SsceError::Export {
+                output: url.clone(),
+                error,
+            })?;
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, verdicts: &BTreeMap<String, ImageVerdict>) -> Result<()> {
+    create_parent(path)?;
+    write_atomic(path, serde_json::to_vec(verdicts)?)
+}
+
+async fn put(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &BTreeMap<String, Secret>,
+    verdicts: &BTreeMap<String, ImageVerdict>,
+) -> Result<()> {
+    let mut request = client.put(url).json(verdicts);
+    for (name, value) in headers {
+        request = request.header(name, value.expose());
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::Result;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::{
+    config::default_true,
+    metrics::SourceLabels,
+    notify::hostname,
+    policy::{PolicyUpdate, Violation},
+    schema::duration_schema,
+    secret::Secret,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct AlertmanagerConfig {
+    /// Base URL of Alertmanager, e.g. `http://alertmanager:9093`.
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    /// Labels added to every alert, e.g. to route them to a receiver.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Annotations added to every alert, e.g. a runbook URL.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    /// Resolve the alerts of violations which are gone, instead of letting them expire.
+    #[serde(default = "default_true")]
+    pub resolve_on_fix: bool,
+    /// How long an alert keeps firing unless the next run refreshes it. Should be longer than
+    /// the interval between runs.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_expiry")]
+    pub expiry: Duration,
+}
+
+fn default_expiry() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// Post an alert for every active violation to Alertmanager, and resolve the alerts of
+/// resolved violations.
+#[tracing::instrument(skip_all, fields(url = config.url))]
+pub async fn send_alerts(
+    client: &reqwest::Client,
+    config: &AlertmanagerConfig,
+    update: &PolicyUpdate,
+) -> Result<()> {
+    let now = Utc::now();
+    let expires = now + chrono::Duration::from_std(config.expiry)?;
+    let mut alerts: Vec<Value> = update
+        .active
+        .iter()
+        .map(|violation| alert(config, violation, now, expires))
+        .collect();
+    if config.resolve_on_fix {
+        alerts.extend(
+            update
+                .resolved
+                .iter()
+                .map(|violation| alert(config, violation, now, now)),
+        );
+    }
+    if alerts.is_empty() {
+        return Ok(());
+    }
+
+    debug!(alerts = alerts.len(), "posting alerts");
+    let mut request = client
+        .post(format!(
+            "{}/api/v2/alerts",
+            config.url.trim_end_matches('/')
+        ))
+        .json(&alerts);
+    for (name, value) in &config.headers {
+        request = request.header(name, value.expose());
+    }
+    request.send().await?.error_for_status()?;
+    Ok(())
+}
+
+/// An alert in the format of the Alertmanager API. The labels identify the finding, so the
+/// alerts of different runs for the same violation are deduplicated by Alertmanager.
+fn alert(
+    config: &AlertmanagerConfig,
+    violation: &Violation,
+    starts: chrono::DateTime<Utc>,
+    ends: chrono::DateTime<Utc>,
+) -> Value {
+    // Tags are sanitized and prefixed like in the metrics, as Alertmanager rejects label names
+    // with dots.
+    let mut labels: BTreeMap<String, String> =
+        SourceLabels::new(&violation.source, Some(&violation.tags))
+            .tags
+            .into_iter()
+            .collect();
+    labels.extend([
+        ("alertname".into(), "VulnerabilityPolicyViolation".into()),
+        ("instance".into(), hostname()),
+        ("source".into(), violation.source.name()),
+        ("cve".into(), violation.id.clone()),
+        ("package".into(), violation.package.clone()),
+        ("version".into(), violation.version.clone()),
+        (
+            "severity".into(),
+            violation.severity.as_str().to_lowercase(),
+        ),
+    ]);
+    labels.extend(config.labels.clone());
+
+    let mut annotations = BTreeMap::from([(
+        "summary".to_owned(),
+        format!(
+            "{} {} in {} {} of {}",
+            violation.severity,
+            violation.id,
+            violation.package,
+            violation.version,
+            violation.source
+        ),
+    )]);
+    if !violation.fixed_versions.is_empty() {
+        annotations.insert("fixed_versions".into(), violation.fixed_versions.join(", "));
+    }
+    if let Some(url) = violation.urls.first() {
+        annotations.insert("url".into(), url.clone());
+    }
+    annotations.extend(config.annotations.clone());
+
+    json!({
+        "labels": labels,
+        "annotations": annotations,
+        "startsAt": starts,
+        "endsAt": ends,
+    })
+}
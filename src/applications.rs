@@ -0,0 +1,115 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config::{merge_tags, Config, Source, Tags};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ApplicationDiscoveryConfig {
+    /// Directories searched for application dependency trees.
+    #[serde(default = "default_paths")]
+    pub paths: Vec<PathBuf>,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    [
+        "/home",
+        "/root",
+        "/opt",
+        "/srv",
+        "/var/lib",
+        "/usr/lib/node_modules",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect()
+}
+
+fn default_max_depth() -> usize {
+    8
+}
+
+/// Find python virtualenvs, user-level pip installs and node_modules directories which aren't
+/// covered by the configured host directories, and return each as its own source.
+pub fn discover_applications(config: &Config) -> HashMap<Source, Tags> {
+    let Some(discovery) = &config.application_discovery else {
+        return HashMap::new();
+    };
+
+    let mut sources = HashMap::new();
+    for root in &discovery.paths {
+        let mut entries = WalkDir::new(root)
+            .max_depth(discovery.max_depth)
+            .into_iter();
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let Some(kind) = application_kind(entry.path()) else {
+                continue;
+            };
+            // Nested dependency trees are part of the one we just found.
+            entries.skip_current_dir();
+
+            if covered(config, entry.path()) {
+                debug!(path = ?entry.path(), "application is covered by a host directory scan");
+                continue;
+            }
+
+            let mut tags = config.tags.clone();
+            merge_tags(
+                &mut tags,
+                Tags::from([("application".to_owned(), kind.to_owned())]),
+            );
+            sources.insert(
+                Source::HostDirectory {
+                    path: entry.into_path(),
+                },
+                tags,
+            );
+        }
+    }
+
+    sources
+}
+
+fn application_kind(path: &Path) -> Option<&'static str> {
+    if path.file_name()? == "node_modules" {
+        Some("node_modules")
+    } else if path.join("pyvenv.cfg").is_file() {
+        Some("virtualenv")
+    } else if path.file_name()? == "site-packages"
+        && path.to_string_lossy().contains("/.local/lib/")
+    {
+        Some("pip_user")
+    } else {
+        None
+    }
+}
+
+/// Whether a path is inside a configured host directory and not excluded from its scan.
+/// Excludes are relative to the scanned directory.
+fn covered(config: &Config, path: &Path) -> bool {
+    config.directories.iter().any(|directory| {
+        path.starts_with(&directory.path)
+            && !config.excludes.iter().any(|exclude| {
+                path.starts_with(
+                    directory
+                        .path
+                        .join(exclude.strip_prefix("/").unwrap_or(exclude)),
+                )
+            })
+    })
+}
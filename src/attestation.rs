@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, path::PathBuf, process::Stdio};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    report::{Report, SourceReport},
+};
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PREDICATE_TYPE: &str = "https://cosign.sigstore.dev/attestation/vuln/v1";
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// In-toto attestations of the scan results of images, e.g. for admission controllers which
+/// require a recent scan without critical findings.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct AttestationConfig {
+    /// Directory the attestations are written to, one per image.
+    pub path: PathBuf,
+    /// Command which signs the attestation. It gets the DSSE pre-authentication encoding of
+    /// the statement on stdin and prints the base64 encoded signature, e.g.
+    /// `cosign sign-blob --key cosign.key -`. Without it, the statements are written unsigned.
+    pub sign_command: Option<SignCommand>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct SignCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Identifies the key in the signature envelope.
+    #[serde(default)]
+    pub keyid: String,
+}
+
+/// Write an attestation of the scan result of every image in the report.
+pub async fn write_attestations(config: &Config, report: &Report) -> Result<()> {
+    let Some(attestation) = &config.attestations else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&attestation.path)?;
+
+    for source in &report.sources {
+        let Some(statement) = statement(report, source) else {
+            continue;
+        };
+        let result = async {
+            let contents = match &attestation.sign_command {
+                Some(sign_command) => {
+                    serde_json::to_vec(&envelope(sign_command, &statement).await?)?
+                }
+                None => serde_json::to_vec(&statement)?,
+            };
+            let path = attestation
+                .path
+                .join(format!("{}.intoto.json", source.source.slug()));
+            debug!(?path, "writing attestation");
+            write_atomic(&path, contents)
+        };
+        if let Err(e) = result.await {
+            warn!("Failed to write attestation for {}: {e:?}", source.source);
+        }
+    }
+    Ok(())
+}
+
+fn statement(report: &Report, source: &SourceReport) -> Option<Value> {
+    let Source::DockerImage { name, id, digest } = &source.source else {
+        return None;
+    };
+    let scan_tool = source.scan_tool.as_ref()?;
+    // The repository digest identifies the image in registries, the image id only locally.
+    let sha256 = digest
+        .as_deref()
+        .and_then(|digest| digest.rsplit_once('@'))
+        .map_or(id.as_str(), |(_, digest)| digest)
+        .trim_start_matches("sha256:");
+
+    let mut by_severity: BTreeMap<String, usize> = BTreeMap::new();
+    for entry in &source.findings {
+        *by_severity
+            .entry(entry.vulnerability.severity.as_str().to_lowercase())
+            .or_default() += 1;
+    }
+
+    Some(json!({
+        "_type": STATEMENT_TYPE,
+        "subject": [{
+            "name": name,
+            "digest": { "sha256": sha256 },
+        }],
+        "predicateType": PREDICATE_TYPE,
+        "predicate": {
+            "invocation": {
+                "parameters": [],
+                "uri": "",
+                "event_id": report.run_id,
+                "builder.id": format!(
+                    "software_supply_chain_exporter@{}",
+                    env!("CARGO_PKG_VERSION")
+                ),
+            },
+            "scanner": {
+                "uri": format!("pkg:github/anchore/{}@v{}", scan_tool.name, scan_tool.version),
+                "version": scan_tool.version,
+                "db": {
+                    "uri": "",
+                    "version": report.grype_db_built.map(|built| built.to_rfc3339()),
+                },
+                "result": {
+                    "findings": source.findings.len(),
+                    "findings_by_severity": by_severity,
+                },
+            },
+            "metadata": {
+                "scanStartedOn": report.started.to_rfc3339(),
+                "scanFinishedOn": Utc::now().to_rfc3339(),
+            },
+        },
+    }))
+}
+
+/// Sign the statement as a DSSE envelope.
+async fn envelope(sign_command: &SignCommand, statement: &Value) -> Result<Value> {
+    let payload = serde_json::to_vec(statement)?;
+    let mut pae = format!(
+        "DSSEv1 {} {PAYLOAD_TYPE} {} ",
+        PAYLOAD_TYPE.len(),
+        payload.len()
+    )
+    .into_bytes();
+    pae.extend_from_slice(&payload);
+
+    let mut child = Command::new(&sign_command.command)
+        .args(&sign_command.args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(&pae).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        bail!("{} exited with {}", sign_command.command, output.status);
+    }
+    let signature = String::from_utf8(output.stdout)?.trim().to_owned();
+    STANDARD
+        .decode(&signature)
+        .context("The signature is not base64 encoded")?;
+
+    Ok(json!({
+        "payloadType": PAYLOAD_TYPE,
+        "payload": STANDARD.encode(&payload),
+        "signatures": [{
+            "keyid": sign_command.keyid,
+            "sig": signature,
+        }],
+    }))
+}
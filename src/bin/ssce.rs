@@ -1,38 +1,245 @@
-use anyhow::Result;
+use std::{future::Future, path::Path, process::ExitCode, sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use chrono::Local;
 use clap::Parser;
 use software_supply_chain_exporter::{
-    config::{Cli, Config, Source},
-    docker::get_docker_images,
-    metrics::export_metrics,
-    sbom::{clean, create_sboms},
-    scan::scan,
+    config::{Cli, Command, Config, ConfigCommand},
+    digest::send_digest,
+    error::SsceError,
+    hashes::find_hash,
+    query::{find, Query},
+    recording::Recording,
+    run::{run_scan, warm_start},
+    sbom::clean,
+    schedule::Scheduler,
+    schema::config_schema,
+    validate::validate_sbom,
+    webhook::listen,
 };
-use tracing::info;
+use tokio::sync::Notify;
+use tracing::{error, info, warn};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     tracing_subscriber::fmt::init();
 
-    let cli = Cli::parse();
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(SsceError::exit_code_of(&e))
+        }
+    }
+}
+
+async fn run() -> Result<()> {
+    let mut cli = Cli::parse();
+
+    match cli.command.take().unwrap_or_default() {
+        Command::Scan => {
+            let config = load_config(&cli)?;
+            until_shutdown(async {
+                run_scan(&config, &mut Scheduler::default()).await?;
+                Ok(())
+            })
+            .await
+        }
+        Command::Daemon => until_shutdown(run_daemon(&load_config(&cli)?)).await,
+        Command::Clean { dry_run } => run_clean(&load_config(&cli)?, dry_run).await,
+        Command::FindHash { sha256 } => run_find_hash(&load_config(&cli)?, &sha256),
+        Command::Find {
+            package,
+            version,
+            cve,
+        } => run_find(
+            &load_config(&cli)?,
+            &Query {
+                package,
+                version,
+                cve,
+            },
+        ),
+        Command::Config {
+            command: ConfigCommand::Show,
+        } => {
+            print!("{}", serde_yaml::to_string(&load_config(&cli)?)?);
+            Ok(())
+        }
+        // These commands don't need a config. The schema is needed to write a valid config in
+        // the first place.
+        Command::Config {
+            command: ConfigCommand::Schema,
+        } => {
+            println!("{}", serde_json::to_string_pretty(&config_schema())?);
+            Ok(())
+        }
+        Command::Validate { sbom } => run_validate(&sbom),
+    }
+}
 
+fn load_config(cli: &Cli) -> Result<Config> {
     info!("Reading config");
-    let config: Config = serde_yaml::from_str(&std::fs::read_to_string(cli.config)?)?;
+    let mut config = Config::load(&cli.config, cli.profile.as_deref())?;
+    config.recording = match (&cli.record, &cli.replay) {
+        (Some(dir), _) => Some(Recording::Record(dir.clone())),
+        (_, Some(dir)) => Some(Recording::Replay(dir.clone())),
+        _ => None,
+    };
+    Ok(config)
+}
+
+/// Run until completion or until a shutdown signal is received. On shutdown, the run is
+/// dropped, which kills running scanner processes. Completed sources are kept in the
+/// checkpoint, so the next run resumes from there.
+async fn until_shutdown(run: impl Future<Output = Result<()>>) -> Result<()> {
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?
+            .recv()
+            .await;
+        Ok::<_, std::io::Error>(())
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<std::io::Result<()>>();
+
+    tokio::select! {
+        res = run => res,
+        res = tokio::signal::ctrl_c() => {
+            warn!("Interrupted, shutting down");
+            Ok(res?)
+        }
+        res = terminate => {
+            warn!("Terminated, shutting down");
+            Ok(res?)
+        }
+    }
+}
 
-    info!("Fetching docker images that are used in containers from docker");
-    let mut sources = get_docker_images().await?;
-    sources.push(Source::HostDirectory { path: "/".into() });
+async fn run_daemon(config: &Config) -> Result<()> {
+    let pushed = Arc::new(Notify::new());
+    if config.webhook.is_some() {
+        let config = config.clone();
+        let pushed = pushed.clone();
+        tokio::spawn(async move {
+            if let Err(e) = listen(&config, pushed).await {
+                error!("Registry webhook listener failed: {e:?}");
+            }
+        });
+    }
 
-    info!("Start generating SBOMs");
-    let sboms = create_sboms(&config, &sources).await?;
+    let mut scheduler = match Scheduler::load(config) {
+        Some((mut scheduler, previous)) => {
+            if let Err(e) = warm_start(config, &mut scheduler, previous).await {
+                error!("Exporting the previous results failed: {e:?}");
+            }
+            scheduler
+        }
+        None => Scheduler::default(),
+    };
+    loop {
+        let now = Local::now();
+        let wait = config.schedule.until_scan_allowed(now);
+        if !wait.is_zero() {
+            let reason = if config.schedule.in_blackout(now) {
+                "In a blackout"
+            } else {
+                "Outside of the scan windows"
+            };
+            info!(
+                "{reason}, keeping the last results for {}",
+                humantime::format_duration(Duration::from_secs(wait.as_secs()))
+            );
+            // Pushed images are scanned once scanning is allowed again.
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+        match run_scan(config, &mut scheduler).await {
+            Ok(report) => {
+                if let Err(e) = scheduler.save(config, &report) {
+                    error!("Saving the results failed: {e:?}");
+                }
+                if let Err(e) = send_digest(config, &report).await {
+                    error!("Sending the digest failed: {e:?}");
+                }
+            }
+            Err(e) => {
+                SsceError::count_of(&e);
+                error!("Scan run failed: {e:?}");
+            }
+        }
+        // Pushed images are scanned right away instead of at the next interval.
+        tokio::select! {
+            _ = tokio::time::sleep(config.schedule.interval) => {}
+            _ = pushed.notified() => info!("Scanning images pushed to registries"),
+        }
+    }
+}
 
-    info!("Compare generated SBOMs against vulnerability databases");
-    let scans = scan(&sboms).await?;
+fn run_validate(path: &Path) -> Result<()> {
+    let sbom: serde_json::Value = serde_json::from_reader(std::fs::File::open(path)?)?;
+    let validation = validate_sbom(&sbom);
+    println!(
+        "{}: {} with {} packages",
+        path.display(),
+        validation.format,
+        validation.packages
+    );
+    for error in &validation.errors {
+        println!("error: {error}");
+    }
+    for warning in &validation.warnings {
+        println!("warning: {warning}");
+    }
+    println!(
+        "{} errors, {} warnings",
+        validation.errors.len(),
+        validation.warnings.len()
+    );
+    if !validation.errors.is_empty() {
+        bail!("{} is not a valid SBOM", path.display());
+    }
+    Ok(())
+}
 
-    info!("Format SBOM and vulnerability data as metrics");
-    export_metrics(&config, sboms, scans)?;
+fn run_find(config: &Config, query: &Query) -> Result<()> {
+    let matches = find(config, query)?;
+    for found in &matches {
+        match &found.cve {
+            Some(cve) => println!(
+                "{}: {} {} ({cve})",
+                found.location, found.package, found.version
+            ),
+            None => println!("{}: {} {}", found.location, found.package, found.version),
+        }
+    }
+    if matches.is_empty() {
+        bail!("No matches");
+    }
+    Ok(())
+}
 
-    info!("Clean up old cache files");
-    clean(&config).await?;
+fn run_find_hash(config: &Config, sha256: &str) -> Result<()> {
+    let matches = find_hash(config, sha256)?;
+    for found in &matches {
+        println!("{}: {}", found.location, found.path);
+    }
+    if matches.is_empty() {
+        bail!("{sha256} not found");
+    }
+    Ok(())
+}
 
+async fn run_clean(config: &Config, dry_run: bool) -> Result<()> {
+    let removed = clean(config, dry_run).await?;
+    let action = if dry_run { "Would remove" } else { "Removed" };
+    for (path, size) in &removed {
+        println!("{action} {} ({size} bytes)", path.to_string_lossy());
+    }
+    println!(
+        "{action} {} files, reclaiming {} bytes",
+        removed.len(),
+        removed.iter().map(|(_, size)| size).sum::<u64>()
+    );
     Ok(())
 }
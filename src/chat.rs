@@ -0,0 +1,72 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    notify::{Event, Notification},
+    secret::Secret,
+    severity::Severity,
+};
+
+/// An incoming webhook of a Slack or Mattermost channel. Several webhooks can be combined to
+/// route notifications, e.g. critical violations to an on-call channel and digests to the
+/// team's channel.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ChatConfig {
+    /// The webhook URL, which contains the token to post with.
+    pub url: Secret,
+    /// Channel to post to instead of the webhook's default channel, if the webhook allows it.
+    pub channel: Option<String>,
+    /// Name to post as instead of the webhook's default name, if the webhook allows it.
+    pub username: Option<String>,
+    /// Notifications sent to this channel, all if empty.
+    #[serde(default)]
+    pub events: Vec<Event>,
+    /// Lowest severity of the policy violations sent to this channel.
+    pub min_severity: Option<Severity>,
+}
+
+impl ChatConfig {
+    pub fn accepts(&self, event: Event) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+
+    /// Whether a policy violation of this severity is sent to this channel.
+    pub fn accepts_severity(&self, severity: Severity) -> bool {
+        self.min_severity.is_none_or(|min| severity >= min)
+    }
+}
+
+/// Post the notification to the channel. Both Slack and Mattermost take a markdown `text`,
+/// they only differ in the markup for bold text.
+pub async fn post(
+    client: &reqwest::Client,
+    config: &ChatConfig,
+    bold: &str,
+    notification: &Notification,
+) -> Result<()> {
+    let mut message = Map::new();
+    message.insert(
+        "text".into(),
+        format!(
+            "{bold}{}{bold}\n{}",
+            notification.subject, notification.text
+        )
+        .into(),
+    );
+    if let Some(channel) = &config.channel {
+        message.insert("channel".into(), channel.clone().into());
+    }
+    if let Some(username) = &config.username {
+        message.insert("username".into(), username.clone().into());
+    }
+    client
+        .post(config.url.expose())
+        .json(&Value::Object(message))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    scan::Scan,
+};
+
+/// Whether a checkpoint was opened by this process before. Only the first run of a process
+/// resumes, as a checkpoint left by an earlier run of the same daemon is from a run that failed,
+/// not one that was interrupted.
+static OPENED: AtomicBool = AtomicBool::new(false);
+
+/// Results of the sources completed so far in the current run, stored as one file per source
+/// and stage. If a run gets interrupted, the next one resumes from it instead of scanning those
+/// sources again.
+pub struct Checkpoint {
+    path: PathBuf,
+}
+
+impl Checkpoint {
+    /// Open the checkpoint of a previous run, or start a new one if there is none, it's older
+    /// than the cache duration or it was left by an earlier run of this process.
+    pub fn open(config: &Config) -> Result<Self> {
+        let path = config.checkpoint_path();
+        let started_path = path.join("started");
+
+        let first_run = !OPENED.swap(true, Ordering::Relaxed);
+        let started = std::fs::read_to_string(&started_path)
+            .ok()
+            .filter(|_| first_run)
+            .and_then(|started| DateTime::parse_from_rfc3339(started.trim()).ok());
+        match started {
+            Some(started)
+                if (Utc::now() - started.to_utc()).to_std().unwrap_or_default()
+                    < config.cache_duration =>
+            {
+                info!("Resuming from checkpoint of run started at {started}");
+            }
+            _ => {
+                if path.exists() {
+                    std::fs::remove_dir_all(&path)?;
+                }
+                std::fs::create_dir_all(&path)?;
+                write_atomic(&started_path, Utc::now().to_rfc3339())?;
+            }
+        }
+
+        Ok(Self { path })
+    }
+
+    pub fn sbom(&self, source: &Source) -> Option<Value> {
+        self.read(source, "sbom")
+    }
+
+    pub fn scan(&self, source: &Source) -> Option<Scan> {
+        self.read(source, "scan")
+    }
+
+    pub fn add_sbom(&self, source: &Source, sbom: &Value) -> Result<()> {
+        self.write(source, "sbom", sbom)
+    }
+
+    pub fn add_scan(&self, source: &Source, scan: &Scan) -> Result<()> {
+        self.write(source, "scan", scan)
+    }
+
+    /// Remove the checkpoint after the run completed.
+    pub fn finish(self) -> Result<()> {
+        Ok(std::fs::remove_dir_all(self.path)?)
+    }
+
+    fn file(&self, source: &Source, stage: &str) -> PathBuf {
+        self.path.join(format!("{}.{stage}.json", source.slug()))
+    }
+
+    fn read<T: DeserializeOwned>(&self, source: &Source, stage: &str) -> Option<T> {
+        let file = File::open(self.file(source, stage)).ok()?;
+        debug!(%source, stage, "using result from checkpoint");
+        serde_json::from_reader(file).ok()
+    }
+
+    fn write<T: Serialize>(&self, source: &Source, stage: &str, value: &T) -> Result<()> {
+        write_atomic(&self.file(source, stage), serde_json::to_vec(value)?)
+    }
+}
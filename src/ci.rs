@@ -0,0 +1,73 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    config::{Config, Tags},
+    schema::duration_schema,
+};
+
+/// Syft catalogers for CI workspaces: the language catalogers, for dependency manifests, lock
+/// files and installed language packages, and those for built artifacts. Unlike the
+/// `directory` tag, the `language` tag leaves out the catalogers for OS packages and the binary
+/// classifiers, which would only find the build server's own packages and tools, covered by
+/// the host directory scan.
+pub const CI_CATALOGERS: &str = "language,java-archive-cataloger,go-module-binary-cataloger,\
+cargo-auditable-binary-cataloger,dotnet-portable-executable-cataloger";
+
+/// Syft excludes for files in CI workspaces which never contain dependency information, but
+/// take long to walk.
+pub const CI_EXCLUDES: &[&str] = &[
+    "./**/.git/**",
+    "./**/.cache/**",
+    "./**/.gradle/caches/**",
+    "./**/.m2/repository/**/*.sha1",
+    "./**/target/*/incremental/**",
+    "./**/target/*/build/**",
+    "./**/target/*/deps/*.d",
+    "./**/__pycache__/**",
+    "./**/*.log",
+    "./**/*.o",
+];
+
+/// A CI workspace or artifact store, scanned with the CI preset.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct CiWorkspace {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub tags: Tags,
+    /// How long the SBOM of the workspace is reused, shorter than for images as workspaces
+    /// change with every build.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_cache_duration")]
+    pub cache_duration: Duration,
+}
+
+fn default_cache_duration() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+/// The cached SBOM of a CI workspace, if it is younger than the workspace's cache duration.
+pub fn cached_sbom(config: &Config, path: &Path, sbom_path: &Path) -> Option<Value> {
+    let cache_duration = config
+        .ci_workspaces
+        .iter()
+        .find(|workspace| workspace.path == path)
+        .map_or_else(default_cache_duration, |workspace| workspace.cache_duration);
+    let modified = std::fs::metadata(sbom_path).ok()?.modified().ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    if age >= cache_duration {
+        debug!("cached sbom of ci workspace is outdated");
+        return None;
+    }
+    serde_json::from_reader(std::fs::File::open(sbom_path).ok()?).ok()
+}
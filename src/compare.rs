@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    finding::{from_grype, from_trivy, Finding, TrivyReport},
+    progress::Progress,
+    proxy::Integration,
+    recording::output,
+    scan::Scan,
+    workspace::Workspace,
+};
+
+/// Scan all SBOMs with trivy in addition to grype to compare their results, e.g. to quantify
+/// false positives before switching scanners.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ScannerComparison {
+    /// The trivy executable.
+    #[serde(default = "default_trivy")]
+    pub trivy: String,
+}
+
+fn default_trivy() -> String {
+    "trivy".into()
+}
+
+/// Number of findings of a source by the scanners that found them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Agreement {
+    pub both: usize,
+    pub grype_only: usize,
+    pub trivy_only: usize,
+}
+
+/// Scan the SBOMs with trivy and compare the findings to the grype scans. Only the sources
+/// scanned in the run are passed, the scheduler keeps the comparisons of the others. Sources
+/// trivy fails to scan are left out.
+pub async fn compare_scanners(
+    config: &Config,
+    sboms: &HashMap<Source, Value>,
+    scans: &HashMap<Source, Scan>,
+) -> HashMap<Source, Agreement> {
+    let Some(comparison) = &config.scanner_comparison else {
+        return HashMap::new();
+    };
+
+    let mut agreements = HashMap::new();
+    let progress = Progress::new("Scanning SBOMs with trivy", scans.len());
+    for (source, scan) in scans {
+        progress.start(source);
+        let Some(sbom) = sboms.get(source) else {
+            progress.inc();
+            continue;
+        };
+        match trivy_scan(config, comparison, source, sbom).await {
+            Ok(trivy) => {
+                agreements.insert(source.clone(), agreement(&from_grype(source, scan), &trivy));
+            }
+            Err(e) => warn!("Failed to scan {source} with trivy: {e:?}"),
+        }
+        progress.inc();
+    }
+    agreements
+}
+
+#[tracing::instrument(skip(config, comparison, sbom))]
+async fn trivy_scan(
+    config: &Config,
+    comparison: &ScannerComparison,
+    source: &Source,
+    sbom: &Value,
+) -> Result<Vec<Finding>> {
+    let workspace = Workspace::new(config, source, "trivy")?;
+    let sbom_path = workspace.path().join("sbom.spdx.json");
+    std::fs::write(&sbom_path, serde_json::to_vec(sbom)?)?;
+
+    debug!("running trivy");
+    let mut command = Command::new(&comparison.trivy);
+    command
+        .arg("sbom")
+        .arg("--quiet")
+        .arg("--format")
+        .arg("json")
+        .arg("--cache-dir")
+        .arg(config.base_path.join("trivy"))
+        .arg(&sbom_path)
+        .kill_on_drop(true);
+    workspace.apply(&mut command);
+    config.proxy.get(Integration::Trivy).apply(&mut command);
+    let key = format!("trivy/{}", source.slug());
+    let output = workspace
+        .limit(output(config.recording.as_ref(), &key, &mut command))
+        .await?;
+    if !output.status.success() {
+        bail!(
+            "trivy failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let report: TrivyReport = serde_json::from_slice(&output.stdout)?;
+    Ok(from_trivy(source, &report))
+}
+
+/// Findings are the same if they refer to the same vulnerability, by any alias, in the same
+/// package version.
+fn agreement(grype: &[Finding], trivy: &[Finding]) -> Agreement {
+    let key = |finding: &Finding| {
+        (
+            finding.canonical_id().to_owned(),
+            finding.package.name.clone(),
+            finding.package.version.clone(),
+        )
+    };
+    let grype = grype.iter().map(key).collect::<HashSet<_>>();
+    let trivy = trivy.iter().map(key).collect::<HashSet<_>>();
+    Agreement {
+        both: grype.intersection(&trivy).count(),
+        grype_only: grype.difference(&trivy).count(),
+        trivy_only: trivy.difference(&grype).count(),
+    }
+}
@@ -1,28 +1,437 @@
-use std::{fmt::Display, path::PathBuf, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "docker")]
 use bollard::service::ContainerSummary;
-use clap::Parser;
-use serde::Deserialize;
+use clap::{Parser, Subcommand};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
 
-#[derive(Deserialize, Clone, Debug)]
+#[cfg(feature = "kubernetes")]
+use crate::kubernetes::KubernetesConfig;
+#[cfg(feature = "notifiers")]
+use crate::tickets::TicketConfig;
+use crate::{
+    admission::AdmissionConfig,
+    applications::ApplicationDiscoveryConfig,
+    attestation::AttestationConfig,
+    ci::CiWorkspace,
+    compare::ScannerComparison,
+    cvss::CvssEnvironment,
+    digest::DigestConfig,
+    discovery::DiscoveryCommand,
+    docker::PlatformOverride,
+    exploits::ExploitsConfig,
+    github::GithubConfig,
+    groups::SourceGroup,
+    grype_db::GrypeDbConfig,
+    hooks::Hook,
+    index::DirectoryIndexConfig,
+    java::JavaArchiveConfig,
+    limits::SbomLimits,
+    load::LoadGuardConfig,
+    lxd::LxdConfig,
+    malicious::MaliciousPackagesConfig,
+    metrics::UnversionedPackages,
+    notify::NotifierConfig,
+    origins::PackageOriginConfig,
+    policy::PolicyConfig,
+    preflight::PreflightConfig,
+    proxy::ProxyConfig,
+    query::VersionConstraint,
+    recording::Recording,
+    redeploy::FixedInNewerTag,
+    runtimes::RuntimeEol,
+    schedule::ScheduleConfig,
+    schema::{duration_schema, validate},
+    shared_cache::SharedCacheConfig,
+    sink::{MetricsOutput, SinkConfig},
+    webhook::WebhookConfig,
+    workspace::WorkspaceConfig,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
     pub base_path: PathBuf,
     pub metrics_path: Option<PathBuf>,
+    /// Additional metrics files with a subset of the metric families each, e.g. a small file
+    /// for node_exporter and the full metrics for a dedicated, less frequent scrape job.
+    #[serde(default)]
+    pub metrics_outputs: Vec<MetricsOutput>,
+    /// Further destinations for the metrics and the report of every run, like a Pushgateway.
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    #[schemars(schema_with = "duration_schema")]
     #[serde(with = "humantime_serde")]
     pub cache_duration: Duration,
     pub excludes: Vec<PathBuf>,
     pub generate_sboms: bool,
+    /// Export the per-package `sbom` metric family.
+    #[serde(default = "default_true")]
+    pub sbom_metrics: bool,
+    /// Restrict the per-package `sbom` metric family to these package names.
+    pub sbom_metrics_allowlist: Option<Vec<String>>,
+    /// How packages without a version, e.g. found by binary catalogers, are exported.
+    #[serde(default)]
+    pub unversioned_packages: UnversionedPackages,
+    /// Add the attack vector, attack complexity, privileges required, user interaction and
+    /// scope of the CVSS vector as labels to the `vulnerability_scans` metric family.
+    #[serde(default)]
+    pub cvss_vector_labels: bool,
+    /// Add a `likely_used` label to the `vulnerability_scans` metric family, hinting whether a
+    /// language package is installed where it is loaded from or only declared, e.g. as
+    /// development dependency.
+    #[serde(default)]
+    pub reachability_hints: bool,
+    /// Only export vulnerabilities with an available fix as metrics. The report files still
+    /// contain all findings.
+    #[serde(default)]
+    pub only_fixed: bool,
+    /// CVSS environmental metrics by source tags. The first matching entry applies to a source,
+    /// and the resulting score is exported as `vulnerability_cvss_environmental_score`.
+    #[serde(default)]
+    pub cvss_environments: Vec<CvssEnvironment>,
+    /// Add the date of the run as `scan_date` label to the `vulnerability_scans` metric
+    /// family. This creates new series every day, the `ssce_run_info` metric and the run
+    /// report can be used for correlation instead.
+    #[serde(default = "default_true")]
+    pub scan_date_label: bool,
+    /// Additional syft formats every SBOM is converted into, e.g. `cyclonedx-json`.
+    #[serde(default)]
+    pub sbom_outputs: Vec<String>,
+    /// Number of scan results to keep per source under `base_path/history/`.
+    pub history_retention: Option<usize>,
+    /// Host directories to scan, defaults to the root directory.
+    #[serde(default = "default_directories")]
+    pub directories: Vec<PathSource>,
+    /// Reuse the SBOMs of host directories in which no package related file changed.
+    pub directory_index: Option<DirectoryIndexConfig>,
+    /// CI workspaces and artifact stores, scanned only for dependency manifests and built
+    /// artifacts.
+    #[serde(default)]
+    pub ci_workspaces: Vec<CiWorkspace>,
+    /// Tags attached to all sources.
+    #[serde(default)]
+    pub tags: Tags,
+    /// Docker container labels that are attached to the container's image as tags.
+    #[serde(default)]
+    pub docker_labels: Vec<String>,
+    /// Attach pod, namespace and owner of containers started by the kubelet as tags.
+    #[cfg(feature = "kubernetes")]
+    pub kubernetes: Option<KubernetesConfig>,
+    /// Scan the root file systems of running LXD containers.
+    pub lxd: Option<LxdConfig>,
+    /// VM disk images (qcow2 or raw) to mount read-only and scan.
+    #[serde(default)]
+    pub disk_images: Vec<PathSource>,
+    /// Inventory the nix store instead of letting syft catalog `/nix/store`. Defaults to
+    /// enabled on NixOS.
+    pub nix: Option<bool>,
+    /// Inspection of Java archives, e.g. of libraries shaded into fat JARs.
+    pub java_archives: Option<JavaArchiveConfig>,
+    /// Discover virtualenvs, user pip installs and node_modules outside the host directories.
+    pub application_discovery: Option<ApplicationDiscoveryConfig>,
+    /// Write a GitLab dependency scanning report to this path.
+    pub gitlab_report: Option<PathBuf>,
+    /// Submit dependencies of images built from GitHub repositories to their dependency graph.
+    pub github: Option<GithubConfig>,
+    /// Scan intervals in daemon mode.
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// Platforms to use for images instead of the host's platform.
+    #[serde(default)]
+    pub platforms: Vec<PlatformOverride>,
+    /// Docker socket or named pipe, defaults to the platform's default location.
+    pub docker_socket: Option<String>,
+    /// Write a property list summary to this path, for MDM tooling.
+    pub plist_summary: Option<PathBuf>,
+    /// Directory of SPDX or CycloneDX JSON files provided by vendors, which are scanned like
+    /// the generated SBOMs.
+    pub sbom_inbox: Option<PathBuf>,
+    /// Maximum number of docker API requests per second, unlimited by default.
+    pub docker_api_rate_limit: Option<f64>,
+    /// SBOM cache shared between hosts, keyed by image digest.
+    pub shared_cache: Option<SharedCacheConfig>,
+    /// Annotate or suppress findings which are fixed in a newer tag of the same image running
+    /// on this host.
+    pub fixed_in_newer_tag: Option<FixedInNewerTag>,
+    /// External commands printing additional sources as JSON.
+    #[serde(default)]
+    pub discovery_commands: Vec<DiscoveryCommand>,
+    /// Path of the JSON report of each run, defaults to `report.json` in the base path.
+    pub report_path: Option<PathBuf>,
+    /// Number of images in each ranking of the worst offenders in the report, 0 to leave the
+    /// rankings out.
+    #[serde(default = "default_worst_offenders")]
+    pub worst_offenders: usize,
+    /// Include the paths of the files each package was found in in the report, e.g. the JAR
+    /// of a vulnerable library inside an image.
+    #[serde(default)]
+    pub report_package_paths: bool,
+    /// Include the supplier, originator and download location of packages from their SPDX
+    /// entries in the report, and flag packages downloaded from unexpected hosts.
+    pub package_origins: Option<PackageOriginConfig>,
+    /// Check language packages against known malicious packages and flag packages named like
+    /// internal ones, exported as `suspicious_package` metric.
+    pub malicious_packages: Option<MaliciousPackagesConfig>,
+    /// Include the SHA-256 digests of the files syft catalogs in the report, so `ssce
+    /// find-hash` finds binaries of every source of the last run.
+    #[serde(default)]
+    pub binary_hashes: bool,
+    /// Wait before generating an SBOM while the host is saturated, and defer the source to
+    /// the next run if the load doesn't drop, exported as `scans_deferred_total`.
+    pub load_guard: Option<LoadGuardConfig>,
+    /// Path of the vulnerability details sidecar file, defaults to `cve_details.json` in the
+    /// base path.
+    pub cve_details_path: Option<PathBuf>,
+    /// In-toto attestations of the scan results of images.
+    pub attestations: Option<AttestationConfig>,
+    /// Directory for OWASP Dependency-Check XML reports, one per source.
+    pub dependency_check_path: Option<PathBuf>,
+    /// Directory for CycloneDX vulnerability disclosure reports, one per source.
+    pub vdr_path: Option<PathBuf>,
+    #[serde(default)]
+    pub grype_db: GrypeDbConfig,
+    /// Location and size limit of temporary files of the scanners.
+    #[serde(default)]
+    pub workspace: WorkspaceConfig,
+    /// Free space required before a run starts.
+    #[serde(default)]
+    pub preflight: PreflightConfig,
+    /// Look up public exploit code for findings in ExploitDB and Metasploit.
+    pub exploits: Option<ExploitsConfig>,
+    /// End of life dates of language runtime release cycles as `YYYY-MM-DD` by
+    /// `runtime:cycle`, e.g. `python:3.9`, in addition to the built-in dates.
+    #[serde(default)]
+    pub runtime_eol: RuntimeEol,
+    /// Export BIOS, CPU microcode, NIC and storage firmware versions of the host.
+    #[serde(default)]
+    pub firmware_inventory: bool,
+    /// Also scan dangling images, e.g. previous builds on hosts acting as builders, and export
+    /// the size of the build cache. Dangling images get the tag `origin: build_cache` to keep
+    /// them out of dashboards of the running software.
+    #[serde(default)]
+    pub build_cache: bool,
+    /// Export the names of the containers using each image as `image_containers` metric.
+    #[serde(default)]
+    pub container_names: bool,
+    /// Export the binaries of enabled systemd services and flag those not installed by a
+    /// package manager.
+    #[serde(default)]
+    pub systemd_services: bool,
+    /// Also scan with trivy and export how much the scanners agree.
+    pub scanner_comparison: Option<ScannerComparison>,
+    /// Limits for the size of SBOMs.
+    #[serde(default)]
+    pub sbom_limits: SbomLimits,
+    /// Destinations for notifications like the digest.
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+    /// Periodic summary of what changed, sent to the notifiers in daemon mode.
+    pub digest: Option<DigestConfig>,
+    /// Findings which are sent to the notifiers as soon as they are found.
+    pub policy: Option<PolicyConfig>,
+    /// Issue trackers in which an issue is opened for every new policy violation.
+    #[cfg(feature = "notifiers")]
+    #[serde(default)]
+    pub tickets: Vec<TicketConfig>,
+    /// YAML file of reviewed acceptances of vulnerabilities, with justification, approver and
+    /// expiry. Accepted findings are labeled `accepted="true"` and aren't policy violations.
+    pub acceptances: Option<PathBuf>,
+    /// Groups of sources, e.g. all images of a product, with rollup metrics per group.
+    #[serde(default)]
+    pub groups: Vec<SourceGroup>,
+    /// Write pass or fail verdicts of the images against the policy for admission controllers.
+    pub admission: Option<AdmissionConfig>,
+    /// Listen for push events of registries like Harbor and Docker Hub in daemon mode, and scan
+    /// pushed images right away. They get the tag `origin: registry`.
+    pub webhook: Option<WebhookConfig>,
+    /// Proxies of the scanners and HTTP clients, instead of the proxy environment variables.
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    /// Commands run after each run.
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    /// Recording or replay of scanner outputs and docker responses, set from the command line.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub recording: Option<Recording>,
+}
+
+/// User-defined key/value pairs attached to a source and exported as metric labels.
+pub type Tags = BTreeMap<String, String>;
+
+/// Merge tags into an existing set. Conflicting values are joined with a comma, as multiple
+/// containers with different labels can share the same image.
+pub fn merge_tags(tags: &mut Tags, other: Tags) {
+    for (key, value) in other {
+        tags.entry(key)
+            .and_modify(|existing| {
+                if !existing.split(',').any(|v| v == value) {
+                    existing.push(',');
+                    existing.push_str(&value);
+                }
+            })
+            .or_insert(value);
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct PathSource {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub tags: Tags,
+}
+
+fn default_directories() -> Vec<PathSource> {
+    vec![PathSource {
+        path: "/".into(),
+        tags: Tags::new(),
+    }]
+}
+
+fn load_layered(path: &Path, loaded: &mut Vec<PathBuf>) -> Result<Value> {
+    loaded.push(path.to_owned());
+    let mut config: Value = serde_yaml::from_str(
+        &std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?,
+    )
+    .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+    let includes: Vec<PathBuf> = match config
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove("include"))
+    {
+        Some(includes) => serde_yaml::from_value(includes)?,
+        None => Vec::new(),
+    };
+
+    let base = path.parent().unwrap_or(Path::new("."));
+    for include in includes {
+        let include = base.join(include);
+        let files = if include.is_dir() {
+            let mut files = std::fs::read_dir(&include)?
+                .filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "yaml" || extension == "yml")
+                })
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        } else {
+            vec![include]
+        };
+        for file in files {
+            merge(&mut config, load_layered(&file, loaded)?);
+        }
+    }
+
+    Ok(config)
+}
+
+fn merge(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Mapping(base), Value::Mapping(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Sequence(base), Value::Sequence(layer)) => base.extend(layer),
+        (base, layer) => *base = layer,
+    }
+}
+
+pub(crate) fn default_true() -> bool {
+    true
+}
+
+fn default_worst_offenders() -> usize {
+    10
 }
 
 impl Config {
+    /// Read the config file and all files it includes. Included files are layered on top of the
+    /// including file: mappings are merged, lists are appended and other values are replaced.
+    /// Includes can be files or directories, of which all YAML files are included in
+    /// alphabetical order, and are relative to the including file.
+    ///
+    /// With a profile, the values of `profiles.<profile>` are applied on top, replacing the
+    /// values of the base config instead of appending to lists.
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Self> {
+        let mut files = Vec::new();
+        let mut config = load_layered(path, &mut files)?;
+        let profiles = config
+            .as_mapping_mut()
+            .and_then(|mapping| mapping.remove("profiles"));
+
+        if let Some(profile) = profile {
+            let profile_config = profiles
+                .as_ref()
+                .and_then(|profiles| profiles.get(profile))
+                .with_context(|| format!("Profile {profile} is not defined in the config"))?;
+            if let (Some(config), Some(profile_config)) =
+                (config.as_mapping_mut(), profile_config.as_mapping())
+            {
+                for (key, value) in profile_config {
+                    config.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        let problems = validate(&serde_json::to_value(&config)?, &files);
+        if !problems.is_empty() {
+            let problems = problems
+                .iter()
+                .map(|problem| format!("  {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            bail!("Invalid config:\n{problems}");
+        }
+
+        Ok(serde_yaml::from_value(config)?)
+    }
+
     pub fn sbom_path(&self, source: &Source) -> Option<PathBuf> {
         match source {
-            Source::DockerImage { name: _, id } => {
-                Some(self.base_path.join(format!("sbom/docker/{id}.json")))
-            }
-            Source::HostDirectory { path: _ } => None,
+            Source::DockerImage { .. } => Some(
+                self.base_path
+                    .join(format!("sbom/docker/{}.json", source.cache_key())),
+            ),
+            Source::CiWorkspace { path: _ } => Some(
+                self.base_path
+                    .join(format!("sbom/ci/{}.json", source.slug())),
+            ),
+            Source::HostDirectory { path: _ }
+            | Source::DiskImage { path: _ }
+            | Source::VendorSbom { path: _ } => None,
         }
     }
+    pub fn sbom_output_path(&self, source: &Source, format: &str) -> PathBuf {
+        let extension = match format {
+            "spdx-json" => "spdx.json",
+            "cyclonedx-json" => "cdx.json",
+            "cyclonedx-xml" => "cdx.xml",
+            "syft-json" => "syft.json",
+            "syft-table" | "syft-text" => "txt",
+            other => other,
+        };
+        self.base_path
+            .join(format!("sbom/export/{}.{extension}", source.slug()))
+    }
     pub fn metrics_path(&self) -> PathBuf {
         if let Some(metrics_path) = self.metrics_path.as_deref() {
             metrics_path.into()
@@ -30,6 +439,74 @@ impl Config {
             self.base_path.join("metrics/metrics.prom")
         }
     }
+    /// Configured host directories, CI workspaces and disk images as sources, with the global
+    /// tags applied.
+    pub fn directory_sources(&self) -> HashMap<Source, Tags> {
+        self.directories
+            .iter()
+            .map(|directory| {
+                let mut tags = self.tags.clone();
+                merge_tags(&mut tags, directory.tags.clone());
+                (
+                    Source::HostDirectory {
+                        path: directory.path.clone(),
+                    },
+                    tags,
+                )
+            })
+            .chain(self.ci_workspaces.iter().map(|workspace| {
+                let mut tags = self.tags.clone();
+                merge_tags(&mut tags, workspace.tags.clone());
+                (
+                    Source::CiWorkspace {
+                        path: workspace.path.clone(),
+                    },
+                    tags,
+                )
+            }))
+            .chain(self.disk_images.iter().map(|image| {
+                let mut tags = self.tags.clone();
+                merge_tags(&mut tags, image.tags.clone());
+                (
+                    Source::DiskImage {
+                        path: image.path.clone(),
+                    },
+                    tags,
+                )
+            }))
+            .collect()
+    }
+    pub fn scheduler_state_path(&self) -> PathBuf {
+        self.base_path.join("scheduler_state.json")
+    }
+    pub fn report_path(&self) -> PathBuf {
+        self.report_path
+            .clone()
+            .unwrap_or_else(|| self.base_path.join("report.json"))
+    }
+    pub fn cve_details_path(&self) -> PathBuf {
+        self.cve_details_path
+            .clone()
+            .unwrap_or_else(|| self.base_path.join("cve_details.json"))
+    }
+    pub fn workspace_path(&self) -> PathBuf {
+        self.workspace
+            .path
+            .clone()
+            .unwrap_or_else(|| self.base_path.join("tmp"))
+    }
+    pub fn checkpoint_path(&self) -> PathBuf {
+        self.base_path.join("checkpoint")
+    }
+    pub fn mount_path(&self) -> PathBuf {
+        self.base_path.join("mnt")
+    }
+    pub fn history_path(&self) -> PathBuf {
+        self.base_path.join("history")
+    }
+    pub fn registry_images_path(&self) -> PathBuf {
+        self.base_path.join("registry_images.json")
+    }
 }
 
 #[derive(Parser)]
@@ -43,19 +520,157 @@ pub struct Cli {
     /// Path to the config file
     #[arg(short, long, default_value = "config.yaml")]
     pub config: PathBuf,
+    /// Name of a profile from the config file to apply
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// Save all scanner outputs and docker responses of the run to this directory
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Rerun the pipeline offline from the outputs and responses saved with `--record`. Cached
+    /// SBOMs are still used, so replay with an empty base path to replay the whole run
+    #[arg(long, value_name = "DIR")]
+    pub replay: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Default)]
+pub enum Command {
+    /// Generate SBOMs, scan them for vulnerabilities and export metrics (default)
+    #[default]
+    Scan,
+    /// Keep running and re-scan sources on the configured schedule
+    Daemon,
+    /// Remove cache files which haven't been used within the cache duration
+    Clean {
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Check an SPDX or CycloneDX JSON SBOM for missing required fields and fields the
+    /// exporter needs, like versions and package URLs
+    Validate {
+        /// Path of the SBOM
+        sbom: PathBuf,
+    },
+    /// Find files by their SHA-256 digest in the last run report and the cached SBOMs, e.g. to
+    /// check whether a binary known from an incident is present
+    FindHash {
+        /// SHA-256 digest in hex, optionally prefixed with `sha256:`
+        sha256: String,
+    },
+    /// Find sources containing a package or vulnerability in the report of the last run
+    Find {
+        /// Name of the package, e.g. `openssl`
+        #[arg(long, required_unless_present = "cve")]
+        package: Option<String>,
+        /// Version or version constraint of the package, e.g. `<3.0.13` or `>=1.2,<1.4`
+        #[arg(long)]
+        version: Option<VersionConstraint>,
+        /// Id of the vulnerability, also matching its aliases, e.g. `CVE-2024-3094`
+        #[arg(long)]
+        cve: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration after applying includes and the profile, with
+    /// secrets redacted
+    Show,
+    /// Print the JSON Schema of the config file, e.g. for editor integration
+    Schema,
+}
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum Source {
-    DockerImage { name: String, id: String },
-    HostDirectory { path: PathBuf },
+    /// A container image by the reference of its containers, usually including the tag, and
+    /// the local image id. The repository digest identifies the image across hosts and is
+    /// used for caching where available.
+    DockerImage {
+        name: String,
+        id: String,
+        #[serde(default)]
+        digest: Option<String>,
+    },
+    HostDirectory {
+        path: PathBuf,
+    },
+    DiskImage {
+        path: PathBuf,
+    },
+    VendorSbom {
+        path: PathBuf,
+    },
+    CiWorkspace {
+        path: PathBuf,
+    },
 }
 
+impl Source {
+    /// Image name or path of the source.
+    pub fn name(&self) -> String {
+        match self {
+            Source::DockerImage { name, .. } => name.clone(),
+            Source::HostDirectory { path }
+            | Source::DiskImage { path }
+            | Source::CiWorkspace { path }
+            | Source::VendorSbom { path } => path.to_string_lossy().into_owned(),
+        }
+    }
+
+    /// File system safe identifier, used for per-source directories.
+    pub fn slug(&self) -> String {
+        match self {
+            Source::DockerImage { id, .. } => id.clone(),
+            Source::HostDirectory { path } => format!("host_{}", path_slug(path)),
+            Source::DiskImage { path } => format!("disk_{}", path_slug(path)),
+            Source::VendorSbom { path } => format!("vendor_{}", path_slug(path)),
+            Source::CiWorkspace { path } => format!("ci_{}", path_slug(path)),
+        }
+    }
+
+    /// Key for cached results of the source. For images this is the repository digest, which
+    /// stays the same when the image is pulled again or on other hosts.
+    pub fn cache_key(&self) -> String {
+        match self {
+            Source::DockerImage {
+                digest: Some(digest),
+                ..
+            } => digest
+                .rsplit_once('@')
+                .map_or(digest.as_str(), |(_, digest)| digest)
+                .replace(':', "_"),
+            _ => self.slug(),
+        }
+    }
+}
+
+/// Replace separators and other characters not allowed in file names on all platforms.
+fn path_slug(path: &Path) -> String {
+    path.to_string_lossy()
+        .trim_start_matches(['/', '\\'])
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(feature = "docker")]
 impl From<ContainerSummary> for Source {
     fn from(value: ContainerSummary) -> Self {
         Self::DockerImage {
             name: value.image.unwrap_or_default(),
             id: value.image_id.unwrap_or_default(),
+            digest: None,
         }
     }
 }
@@ -63,10 +678,19 @@ impl From<ContainerSummary> for Source {
 impl Display for Source {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Source::DockerImage { name, id } => write!(f, "OCI image {name} ({id})"),
+            Source::DockerImage { name, id, .. } => write!(f, "OCI image {name} ({id})"),
             Source::HostDirectory { path } => {
                 write!(f, "Host directory {}", path.to_string_lossy())
             }
+            Source::DiskImage { path } => {
+                write!(f, "Disk image {}", path.to_string_lossy())
+            }
+            Source::VendorSbom { path } => {
+                write!(f, "Vendor SBOM {}", path.to_string_lossy())
+            }
+            Source::CiWorkspace { path } => {
+                write!(f, "CI workspace {}", path.to_string_lossy())
+            }
         }
     }
 }
@@ -0,0 +1,66 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::debug;
+
+use crate::{
+    config::{Config, Source},
+    fs::{create_parent, write_atomic},
+    scan::{Cvss, Scan},
+    severity::Severity,
+};
+
+#[derive(Serialize, Debug)]
+pub struct CveDetails {
+    pub severity: Severity,
+    pub description: String,
+    pub urls: Vec<String>,
+    pub cvss: Vec<Cvss>,
+    pub affected: Vec<AffectedPackage>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AffectedPackage {
+    pub source: String,
+    pub name: String,
+    pub version: String,
+    pub fixed_versions: Vec<String>,
+}
+
+/// Write descriptions, references and affected packages of all findings keyed by vulnerability
+/// id. Metrics only carry the id, dashboards can look up the details here.
+pub fn write_cve_details(config: &Config, scans: &HashMap<Source, Scan>) -> Result<()> {
+    let mut details: BTreeMap<&str, CveDetails> = BTreeMap::new();
+    for (source, scan) in scans {
+        for entry in &scan.matches {
+            let vulnerability = &entry.vulnerability;
+            details
+                .entry(&vulnerability.id)
+                .or_insert_with(|| CveDetails {
+                    severity: vulnerability.severity,
+                    description: vulnerability.description.clone(),
+                    urls: vulnerability.urls.clone(),
+                    cvss: vulnerability.cvss.clone(),
+                    affected: Vec::new(),
+                })
+                .affected
+                .push(AffectedPackage {
+                    source: source.to_string(),
+                    name: entry.artifact.name.clone(),
+                    version: entry.artifact.version.clone(),
+                    fixed_versions: vulnerability.fix.versions.clone(),
+                });
+        }
+    }
+    for cve in details.values_mut() {
+        cve.affected.sort();
+        cve.affected.dedup();
+    }
+
+    let path = config.cve_details_path();
+    debug!(?path, "writing vulnerability details");
+    create_parent(&path)?;
+    write_atomic(&path, serde_json::to_vec_pretty(&details)?)?;
+    Ok(())
+}
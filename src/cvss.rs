@@ -0,0 +1,150 @@
+//! CVSS v3 environmental scores, which adjust the base score of a vulnerability to the
+//! environment a source runs in.
+
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Tags;
+
+/// Environmental metrics for the sources with all of the given tags, e.g. `MAV: A` for
+/// services only reachable from the internal network.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct CvssEnvironment {
+    /// Tags a source needs to have for the metrics to apply, all sources if empty.
+    #[serde(default)]
+    pub tags: Tags,
+    /// Environmental metrics by their abbreviation in the vector, e.g. `CR`, `MAV` or `MPR`.
+    pub metrics: BTreeMap<String, String>,
+}
+
+impl CvssEnvironment {
+    /// The metrics of the first environment matching the tags of a source.
+    pub fn find<'a>(
+        environments: &'a [CvssEnvironment],
+        tags: Option<&Tags>,
+    ) -> Option<&'a BTreeMap<String, String>> {
+        environments
+            .iter()
+            .find(|environment| {
+                environment
+                    .tags
+                    .iter()
+                    .all(|(key, value)| tags.and_then(|tags| tags.get(key)) == Some(value))
+            })
+            .map(|environment| &environment.metrics)
+    }
+}
+
+/// The environmental score of a CVSS v3 vector with the environmental metrics applied. Metrics
+/// which aren't set default to the base metrics. Returns `None` for other CVSS versions.
+pub fn environmental_score(vector: &str, environment: &BTreeMap<String, String>) -> Option<f64> {
+    let mut parts = vector.split('/');
+    let version = parts.next()?.strip_prefix("CVSS:")?;
+    if !version.starts_with('3') {
+        return None;
+    }
+    let base = parts
+        .filter_map(|part| part.split_once(':'))
+        .collect::<HashMap<_, _>>();
+    // The modified metric if set and not `X`, the base metric otherwise.
+    let metric = |name: &str| {
+        environment
+            .get(&format!("M{name}"))
+            .map(String::as_str)
+            .filter(|value| *value != "X")
+            .or_else(|| base.get(name).copied())
+    };
+    let requirement = |name: &str| -> f64 {
+        match environment.get(name).map(String::as_str) {
+            Some("H") => 1.5,
+            Some("L") => 0.5,
+            _ => 1.0,
+        }
+    };
+    let impact = |name: &str| match metric(name) {
+        Some("H") => Some(0.56),
+        Some("L") => Some(0.22),
+        Some("N") => Some(0.0),
+        _ => None,
+    };
+
+    let scope_changed = metric("S")? == "C";
+    let attack_vector: f64 = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let attack_complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let privileges_required = match (metric("PR")?, scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let user_interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+
+    let impact_subscore = (1.0
+        - (1.0 - requirement("CR") * impact("C")?)
+            * (1.0 - requirement("IR") * impact("I")?)
+            * (1.0 - requirement("AR") * impact("A")?))
+    .min(0.915);
+    let modified_impact = if scope_changed {
+        // CVSS 3.1 changed the impact formula for changed scope.
+        let (factor, exponent) = if version == "3.0" {
+            (1.0, 15)
+        } else {
+            (0.9731, 13)
+        };
+        7.52 * (impact_subscore - 0.029) - 3.25 * (impact_subscore * factor - 0.02).powi(exponent)
+    } else {
+        6.42 * impact_subscore
+    };
+    let modified_exploitability =
+        8.22 * attack_vector * attack_complexity * privileges_required * user_interaction;
+
+    if modified_impact <= 0.0 {
+        return Some(0.0);
+    }
+    let score = if scope_changed {
+        roundup((1.08 * (modified_impact + modified_exploitability)).min(10.0))
+    } else {
+        roundup((modified_impact + modified_exploitability).min(10.0))
+    };
+    Some(roundup(score))
+}
+
+/// Qualitative severity of a CVSS v3 score.
+pub fn severity(score: f64) -> &'static str {
+    match score {
+        score if score >= 9.0 => "Critical",
+        score if score >= 7.0 => "High",
+        score if score >= 4.0 => "Medium",
+        score if score > 0.0 => "Low",
+        _ => "None",
+    }
+}
+
+/// Round up to one decimal, avoiding floating point artifacts as specified in CVSS 3.1.
+fn roundup(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use tracing::debug;
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    scan::{Cvss, Scan, ScanEntry},
+};
+
+const NAMESPACE: &str = "https://jeremylong.github.io/DependencyCheck/dependency-check.2.5.xsd";
+
+/// Write the findings of each source as OWASP Dependency-Check XML report, for tooling which
+/// only ingests that format.
+pub fn write_dependency_check_reports(
+    config: &Config,
+    scans: &HashMap<Source, Scan>,
+) -> Result<()> {
+    let Some(dir) = &config.dependency_check_path else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(dir)?;
+
+    for (source, scan) in scans {
+        let path = dir.join(format!("{}.dependency-check-report.xml", source.slug()));
+        debug!(?path, "writing dependency-check report");
+        write_atomic(&path, report(source, scan))?;
+    }
+    Ok(())
+}
+
+fn report(source: &Source, scan: &Scan) -> String {
+    // Dependency-Check lists vulnerabilities per dependency.
+    let mut dependencies: BTreeMap<(&str, &str), Vec<&ScanEntry>> = BTreeMap::new();
+    for entry in &scan.matches {
+        dependencies
+            .entry((&entry.artifact.name, &entry.artifact.version))
+            .or_default()
+            .push(entry);
+    }
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<analysis xmlns=\"{NAMESPACE}\">");
+    let _ = writeln!(
+        xml,
+        "  <scanInfo><engineVersion>{}</engineVersion></scanInfo>",
+        escape(env!("CARGO_PKG_VERSION"))
+    );
+    let _ = writeln!(
+        xml,
+        "  <projectInfo><name>{}</name><reportDate>{}</reportDate>\
+         <credits>software_supply_chain_exporter using {} {}</credits></projectInfo>",
+        escape(&source.to_string()),
+        Utc::now().to_rfc3339(),
+        escape(&scan.descriptor.name),
+        escape(&scan.descriptor.version),
+    );
+    xml.push_str("  <dependencies>\n");
+    for ((name, version), entries) in dependencies {
+        let artifact = &entries[0].artifact;
+        let file_path = artifact
+            .locations
+            .first()
+            .map(|location| location.path.as_str())
+            .unwrap_or_default();
+        xml.push_str("    <dependency isVirtual=\"true\">\n");
+        let _ = writeln!(
+            xml,
+            "      <fileName>{}:{}</fileName>",
+            escape(name),
+            escape(version)
+        );
+        let _ = writeln!(xml, "      <filePath>{}</filePath>", escape(file_path));
+        if let Some(purl) = artifact.purl.as_deref().filter(|purl| !purl.is_empty()) {
+            let _ = writeln!(
+                xml,
+                "      <identifiers><package><id>{}</id></package></identifiers>",
+                escape(purl)
+            );
+        }
+        xml.push_str("      <vulnerabilities>\n");
+        for entry in entries {
+            vulnerability(&mut xml, entry);
+        }
+        xml.push_str("      </vulnerabilities>\n");
+        xml.push_str("    </dependency>\n");
+    }
+    xml.push_str("  </dependencies>\n");
+    xml.push_str("</analysis>\n");
+    xml
+}
+
+fn vulnerability(xml: &mut String, entry: &ScanEntry) {
+    let vulnerability = &entry.vulnerability;
+    let source = match vulnerability.id.split('-').next() {
+        Some("CVE") => "NVD",
+        Some("GHSA") => "OSSINDEX",
+        _ => "RETIREJS",
+    };
+    let _ = writeln!(xml, "        <vulnerability source=\"{source}\">");
+    let _ = writeln!(xml, "          <name>{}</name>", escape(&vulnerability.id));
+    let _ = writeln!(
+        xml,
+        "          <severity>{}</severity>",
+        escape(&vulnerability.severity.as_str().to_uppercase())
+    );
+    for cvss in &vulnerability.cvss {
+        if cvss.version.starts_with('3') {
+            cvss_v3(xml, cvss);
+        } else if cvss.version.starts_with('2') {
+            cvss_v2(xml, cvss);
+        }
+    }
+    let _ = writeln!(
+        xml,
+        "          <description>{}</description>",
+        escape(&vulnerability.description)
+    );
+    xml.push_str("          <references>\n");
+    for url in &vulnerability.urls {
+        let _ = writeln!(
+            xml,
+            "            <reference><source>{}</source><url>{}</url><name>{}</name></reference>",
+            source,
+            escape(url),
+            escape(url)
+        );
+    }
+    xml.push_str("          </references>\n");
+    let _ = writeln!(
+        xml,
+        "          <vulnerableSoftware><software>{}:{}</software></vulnerableSoftware>",
+        escape(&entry.artifact.name),
+        escape(&entry.artifact.version)
+    );
+    xml.push_str("        </vulnerability>\n");
+}
+
+fn cvss_v3(xml: &mut String, cvss: &Cvss) {
+    let impact = [("H", "HIGH"), ("L", "LOW"), ("N", "NONE")];
+    let score = cvss.metrics.base_score;
+    let severity = if score >= 9.into() {
+        "CRITICAL"
+    } else if score >= 7.into() {
+        "HIGH"
+    } else if score >= 4.into() {
+        "MEDIUM"
+    } else if score > 0.into() {
+        "LOW"
+    } else {
+        "NONE"
+    };
+    let _ = writeln!(
+        xml,
+        "          <cvssV3><attackVector>{}</attackVector><attackComplexity>{}</attackComplexity>\
+         <privilegesRequired>{}</privilegesRequired><userInteraction>{}</userInteraction>\
+         <scope>{}</scope><confidentialityImpact>{}</confidentialityImpact>\
+         <integrityImpact>{}</integrityImpact><availabilityImpact>{}</availabilityImpact>\
+         <baseScore>{score}</baseScore><baseSeverity>{severity}</baseSeverity>\
+         <version>{}</version></cvssV3>",
+        metric(
+            cvss,
+            "AV",
+            &[
+                ("N", "NETWORK"),
+                ("A", "ADJACENT_NETWORK"),
+                ("L", "LOCAL"),
+                ("P", "PHYSICAL")
+            ]
+        ),
+        metric(cvss, "AC", &[("L", "LOW"), ("H", "HIGH")]),
+        metric(cvss, "PR", &impact),
+        metric(cvss, "UI", &[("N", "NONE"), ("R", "REQUIRED")]),
+        metric(cvss, "S", &[("U", "UNCHANGED"), ("C", "CHANGED")]),
+        metric(cvss, "C", &impact),
+        metric(cvss, "I", &impact),
+        metric(cvss, "A", &impact),
+        escape(&cvss.version),
+    );
+}
+
+fn cvss_v2(xml: &mut String, cvss: &Cvss) {
+    let impact = [("N", "NONE"), ("P", "PARTIAL"), ("C", "COMPLETE")];
+    let score = cvss.metrics.base_score;
+    let severity = if score >= 7.into() {
+        "HIGH"
+    } else if score >= 4.into() {
+        "MEDIUM"
+    } else {
+        "LOW"
+    };
+    let _ = writeln!(
+        xml,
+        "          <cvssV2><score>{score}</score><accessVector>{}</accessVector>\
+         <accessComplexity>{}</accessComplexity><authenticationr>{}</authenticationr>\
+         <confidentialImpact>{}</confidentialImpact><integrityImpact>{}</integrityImpact>\
+         <availabilityImpact>{}</availabilityImpact><severity>{severity}</severity></cvssV2>",
+        metric(
+            cvss,
+            "AV",
+            &[("N", "NETWORK"), ("A", "ADJACENT_NETWORK"), ("L", "LOCAL")]
+        ),
+        metric(cvss, "AC", &[("L", "LOW"), ("M", "MEDIUM"), ("H", "HIGH")]),
+        metric(
+            cvss,
+            "Au",
+            &[("N", "NONE"), ("S", "SINGLE"), ("M", "MULTIPLE")]
+        ),
+        metric(cvss, "C", &impact),
+        metric(cvss, "I", &impact),
+        metric(cvss, "A", &impact),
+    );
+}
+
+/// Long name of a metric of the CVSS vector, e.g. `NETWORK` for `AV:N`.
+fn metric(cvss: &Cvss, name: &str, values: &[(&str, &'static str)]) -> &'static str {
+    let value = cvss.vector_metric(name).unwrap_or_default();
+    values
+        .iter()
+        .find(|(short, _)| *short == value)
+        .map_or("", |(_, long)| *long)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
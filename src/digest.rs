@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, info};
+
+use crate::{
+    config::Config,
+    fs::write_atomic,
+    notify::{hostname, notify, Event, Notification},
+    report::Report,
+    schema::duration_schema,
+    severity::Severity,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct DigestConfig {
+    /// How often the daemon sends a summary of what changed.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_interval")]
+    pub interval: Duration,
+}
+
+fn default_interval() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// State at the time of the last digest, which the next digest is compared against.
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    sent: DateTime<Utc>,
+    /// Findings per source as `(id, package, severity)`.
+    sources: BTreeMap<String, BTreeSet<(String, String, Severity)>>,
+    failing: BTreeSet<String>,
+}
+
+impl Snapshot {
+    fn new(report: &Report) -> Self {
+        Self {
+            sent: report.finished,
+            sources: report
+                .sources
+                .iter()
+                .map(|source| {
+                    let findings = source
+                        .findings
+                        .iter()
+                        .map(|entry| {
+                            (
+                                entry.vulnerability.id.clone(),
+                                entry.artifact.name.clone(),
+                                entry.vulnerability.severity,
+                            )
+                        })
+                        .collect();
+                    (source.source.to_string(), findings)
+                })
+                .collect(),
+            failing: report.failed.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+/// Send a summary of the changes since the last digest to the notifiers, once the digest
+/// interval has passed. The first call only records the current state.
+pub async fn send_digest(config: &Config, report: &Report) -> Result<()> {
+    let Some(digest) = &config.digest else {
+        return Ok(());
+    };
+
+    let path = config.base_path.join("digest.json");
+    let current = Snapshot::new(report);
+    let previous: Option<Snapshot> = std::fs::read(&path)
+        .ok()
+        .and_then(|previous| serde_json::from_slice(&previous).ok());
+
+    if let Some(previous) = &previous {
+        let since = (report.finished - previous.sent)
+            .to_std()
+            .unwrap_or_default();
+        if since < digest.interval {
+            debug!("digest not due yet");
+            return Ok(());
+        }
+        notify(config, &changes(previous, &current)).await;
+    } else {
+        info!("Recording the initial state for the digest");
+    }
+
+    write_atomic(&path, serde_json::to_vec(&current)?)?;
+    Ok(())
+}
+
+fn changes(previous: &Snapshot, current: &Snapshot) -> Notification {
+    let new_sources = keys_missing(&current.sources, &previous.sources);
+    let removed_sources = keys_missing(&previous.sources, &current.sources);
+
+    let mut new_findings: BTreeMap<Severity, usize> = BTreeMap::new();
+    let mut fixed_findings: BTreeMap<Severity, usize> = BTreeMap::new();
+    let empty = BTreeSet::new();
+    for (source, findings) in &current.sources {
+        let before = previous.sources.get(source).unwrap_or(&empty);
+        for (_, _, severity) in findings.difference(before) {
+            *new_findings.entry(*severity).or_default() += 1;
+        }
+    }
+    for (source, findings) in &previous.sources {
+        // Findings of removed sources aren't fixed, the source is just gone.
+        let Some(after) = current.sources.get(source) else {
+            continue;
+        };
+        for (_, _, severity) in findings.difference(after) {
+            *fixed_findings.entry(*severity).or_default() += 1;
+        }
+    }
+
+    let mut text = format!(
+        "Changes since {}:\n",
+        previous.sent.format("%Y-%m-%d %H:%M UTC")
+    );
+    text += &list("New sources", &new_sources);
+    text += &list("Removed sources", &removed_sources);
+    text += &format!("New vulnerabilities: {}\n", counts(&new_findings));
+    text += &format!("Fixed vulnerabilities: {}\n", counts(&fixed_findings));
+    text += &list(
+        "Failing sources",
+        &current.failing.iter().cloned().collect::<Vec<_>>(),
+    );
+
+    Notification {
+        event: Event::Digest,
+        subject: format!("ssce digest for {}", hostname()),
+        text,
+        data: json!({
+            "since": previous.sent,
+            "new_sources": new_sources,
+            "removed_sources": removed_sources,
+            "new_vulnerabilities": new_findings,
+            "fixed_vulnerabilities": fixed_findings,
+            "failing_sources": current.failing,
+        }),
+    }
+}
+
+fn keys_missing<V>(from: &BTreeMap<String, V>, other: &BTreeMap<String, V>) -> Vec<String> {
+    from.keys()
+        .filter(|key| !other.contains_key(*key))
+        .cloned()
+        .collect()
+}
+
+fn list(title: &str, items: &[String]) -> String {
+    let mut text = format!("{title}: {}\n", items.len());
+    for item in items {
+        text += &format!("  - {item}\n");
+    }
+    text
+}
+
+fn counts(by_severity: &BTreeMap<Severity, usize>) -> String {
+    if by_severity.is_empty() {
+        return "none".into();
+    }
+    // Most severe first.
+    by_severity
+        .iter()
+        .rev()
+        .map(|(severity, count)| format!("{count} {severity}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::config::{merge_tags, Config, Source, Tags};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct DiscoveryCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Tags attached to all sources discovered by this command.
+    #[serde(default)]
+    pub tags: Tags,
+}
+
+/// A source as printed by a discovery command, e.g.
+/// `{"type": "host_directory", "path": "/srv/app", "tags": {"owner": "team-a"}}`.
+#[derive(Deserialize)]
+struct DiscoveredSource {
+    #[serde(flatten)]
+    source: Source,
+    #[serde(default)]
+    tags: Tags,
+}
+
+/// Run the configured discovery commands, which print a JSON list of additional sources, e.g.
+/// from an inventory system. Failing commands are logged and skipped.
+pub async fn run_discovery_commands(config: &Config) -> HashMap<Source, Tags> {
+    let mut sources: HashMap<Source, Tags> = HashMap::new();
+    for discovery in &config.discovery_commands {
+        match discover(discovery).await {
+            Ok(discovered) => {
+                for discovered in discovered {
+                    let mut tags = config.tags.clone();
+                    merge_tags(&mut tags, discovery.tags.clone());
+                    merge_tags(&mut tags, discovered.tags);
+                    merge_tags(sources.entry(discovered.source).or_default(), tags);
+                }
+            }
+            Err(e) => warn!("Discovery command {} failed: {e:?}", discovery.command),
+        }
+    }
+    sources
+}
+
+#[tracing::instrument]
+async fn discover(discovery: &DiscoveryCommand) -> Result<Vec<DiscoveredSource>> {
+    debug!("running discovery command");
+    let output = Command::new(&discovery.command)
+        .args(&discovery.args)
+        .kill_on_drop(true)
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!(
+            "exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
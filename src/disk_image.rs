@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::config::{Config, Source};
+
+/// A VM disk image (qcow2 or raw) mounted read-only using libguestfs, so its file system can be
+/// scanned like a host directory.
+pub struct DiskImageMount {
+    pub path: PathBuf,
+}
+
+impl DiskImageMount {
+    #[tracing::instrument(skip(config))]
+    pub async fn mount(config: &Config, image: &Path) -> Result<Self> {
+        let path = config.mount_path().join(
+            Source::DiskImage {
+                path: image.to_owned(),
+            }
+            .slug(),
+        );
+        if path
+            .read_dir()
+            .is_ok_and(|mut entries| entries.next().is_some())
+        {
+            debug!(?path, "unmounting leftover mount of an interrupted run");
+            Command::new("guestunmount").arg(&path).status().await?;
+        }
+        std::fs::create_dir_all(&path)?;
+
+        debug!(?path, "mounting disk image");
+        // `-i` inspects the image to find the operating system and mounts its file systems
+        // the same way the guest would.
+        let status = Command::new("guestmount")
+            .arg("--ro")
+            .arg("-i")
+            .arg("-a")
+            .arg(image)
+            .arg(&path)
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("guestmount exited with {status}");
+        }
+
+        Ok(Self { path })
+    }
+
+    #[tracing::instrument(skip(self), fields(path = ?self.path))]
+    pub async fn unmount(self) -> Result<()> {
+        debug!("unmounting disk image");
+        let status = Command::new("guestunmount")
+            .arg(&self.path)
+            .status()
+            .await?;
+        if !status.success() {
+            bail!("guestunmount exited with {status}");
+        }
+        std::fs::remove_dir(&self.path)?;
+        Ok(())
+    }
+}
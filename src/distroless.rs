@@ -0,0 +1,45 @@
+//! Heuristics for images without a package manager, like distroless and scratch images. Their
+//! SBOMs only contain what syft's binary and language catalogers find, as there is no package
+//! database. Such images are scanned once more with only the binary catalogers, and as an empty
+//! SBOM can't be told apart from a failed scan, it is flagged.
+
+use std::collections::BTreeMap;
+
+use crate::{config::Source, sbom::Sbom};
+
+/// Syft catalogers run on their own for images without a package database: those identifying
+/// executables, like Go and Rust binaries or interpreters by their version strings.
+pub const BINARY_CATALOGERS: &str = "binary";
+
+/// Package types of package managers with a database in the image.
+const OS_PACKAGE_TYPES: [&str; 5] = ["deb", "rpm", "apk", "alpm", "nix"];
+
+/// Number of packages in the SBOM by package URL type, e.g. `deb` or `golang`. The image
+/// itself, which syft lists with the `oci` type, isn't counted.
+pub fn packages_by_type(sbom: &Sbom) -> BTreeMap<String, usize> {
+    let mut types = BTreeMap::new();
+    for package in &sbom.packages {
+        let package_type = package.purl_type().unwrap_or("unknown");
+        if package_type == "oci" {
+            continue;
+        }
+        *types.entry(package_type.to_owned()).or_default() += 1;
+    }
+    types
+}
+
+/// Why the SBOM of an image is possibly incomplete: `empty` if nothing was cataloged at all,
+/// `no_os_packages` if the image has no package database, like distroless images.
+pub fn incomplete_reason(source: &Source, sbom: &Sbom) -> Option<&'static str> {
+    if !matches!(source, Source::DockerImage { .. }) {
+        return None;
+    }
+    let types = packages_by_type(sbom);
+    if types.is_empty() {
+        Some("empty")
+    } else if !OS_PACKAGE_TYPES.iter().any(|os| types.contains_key(*os)) {
+        Some("no_os_packages")
+    } else {
+        None
+    }
+}
@@ -1,27 +1,537 @@
 use std::collections::HashMap;
+#[cfg(feature = "docker")]
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use anyhow::Result;
-use bollard::{container::ListContainersOptions, Docker};
-use itertools::Itertools;
+#[cfg(feature = "docker")]
+use bollard::{
+    container::ListContainersOptions,
+    image::ListImagesOptions,
+    service::{BuildCache, ContainerInspectResponse, ContainerSummary, ImageSummary},
+    Docker,
+};
+#[cfg(feature = "docker")]
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "docker")]
+use tokio::{
+    sync::{Mutex, OnceCell},
+    time::Instant,
+};
+#[cfg(feature = "docker")]
+use tracing::{debug, warn};
 
-use crate::config::Source;
+#[cfg(feature = "kubernetes")]
+use crate::kubernetes::Kubernetes;
+#[cfg(feature = "docker")]
+use crate::{
+    config::merge_tags,
+    proxy::Integration,
+    recording::{response, Recording},
+};
+use crate::{
+    config::{Config, Source, Tags},
+    report::BuildCacheUsage,
+};
 
-pub async fn get_docker_images() -> Result<Vec<Source>> {
-    let docker = Docker::connect_with_socket_defaults()?;
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct PlatformOverride {
+    /// Prefix of the image name.
+    pub image: String,
+    /// Platform in `os/arch[/variant]` form, e.g. `linux/arm/v7`.
+    pub platform: String,
+}
+
+/// Platform of the host in the `os/arch[/variant]` form used by OCI image indexes. The OS is
+/// always `linux`, as Docker Desktop on macOS and Windows runs linux images in a VM.
+pub fn host_platform() -> String {
+    let arch = match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "x86" => "386",
+        "aarch64" => "arm64",
+        "arm" if cfg!(target_feature = "v7") => "arm/v7",
+        "arm" => "arm/v6",
+        "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+        "mips64" if cfg!(target_endian = "little") => "mips64le",
+        "loongarch64" => "loong64",
+        other => other,
+    };
+    format!("linux/{arch}")
+}
+
+/// Platform to use for an image, either configured explicitly or the host's platform.
+pub fn platform(config: &Config, image: &str) -> String {
+    config
+        .platforms
+        .iter()
+        .find(|platform| image.starts_with(&platform.image))
+        .map(|platform| platform.platform.clone())
+        .unwrap_or_else(host_platform)
+}
+
+/// Sockets of docker compatible runtimes on developer machines, relative to the home directory.
+#[cfg(feature = "docker")]
+const USER_SOCKETS: [&str; 3] = [
+    ".docker/run/docker.sock",
+    ".colima/default/docker.sock",
+    ".rd/docker.sock",
+];
+
+/// Connect to the configured docker socket, or the platform default (`/var/run/docker.sock` or
+/// the `//./pipe/docker_engine` named pipe on Windows). If the default socket doesn't exist,
+/// the sockets of Docker Desktop, Colima and Rancher Desktop in the user's home are tried.
+#[cfg(feature = "docker")]
+pub fn connect(config: &Config) -> Result<Docker> {
+    if let Some(socket) = &config.docker_socket {
+        return Ok(Docker::connect_with_socket(
+            socket,
+            120,
+            bollard::API_DEFAULT_VERSION,
+        )?);
+    }
+
+    if cfg!(unix) && !Path::new("/var/run/docker.sock").exists() {
+        let user_socket = std::env::var_os("HOME").and_then(|home| {
+            USER_SOCKETS
+                .iter()
+                .map(|socket| Path::new(&home).join(socket))
+                .find(|socket| socket.exists())
+        });
+        if let Some(socket) = user_socket {
+            return Ok(Docker::connect_with_socket(
+                &socket.to_string_lossy(),
+                120,
+                bollard::API_DEFAULT_VERSION,
+            )?);
+        }
+    }
+
+    Ok(Docker::connect_with_socket_defaults()?)
+}
+
+/// Docker API client shared by all modules during a run. Requests are rate limited, and the
+/// container and image lists are fetched once per run instead of inspecting every image. Responses are
+/// recorded or replayed with `--record` and `--replay`.
+#[cfg(feature = "docker")]
+pub struct DockerClient {
+    docker: Docker,
+    min_interval: Option<Duration>,
+    last_request: Mutex<Option<Instant>>,
+    containers: OnceCell<Vec<ContainerSummary>>,
+    images: OnceCell<HashMap<String, ImageSummary>>,
+    recording: Option<Recording>,
+    short_lived_skipped: AtomicUsize,
+}
+
+#[cfg(feature = "docker")]
+impl DockerClient {
+    pub fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            docker: connect(config)?,
+            min_interval: config
+                .docker_api_rate_limit
+                .filter(|limit| *limit > 0.0)
+                .map(|limit| Duration::from_secs_f64(1.0 / limit)),
+            last_request: Mutex::new(None),
+            containers: OnceCell::new(),
+            images: OnceCell::new(),
+            recording: config.recording.clone(),
+            short_lived_skipped: AtomicUsize::new(0),
+        })
+    }
 
-    let filters: HashMap<String, Vec<String>> = HashMap::new();
+    /// Wait until the next request is allowed by the rate limit.
+    async fn throttle(&self) {
+        let Some(min_interval) = self.min_interval else {
+            return;
+        };
+        let mut last_request = self.last_request.lock().await;
+        if let Some(last_request) = *last_request {
+            tokio::time::sleep_until(last_request + min_interval).await;
+        }
+        *last_request = Some(Instant::now());
+    }
 
-    let options = Some(ListContainersOptions {
-        all: true,
-        filters,
-        ..Default::default()
-    });
+    /// All containers, including stopped ones.
+    pub async fn containers(&self) -> Result<&[ContainerSummary]> {
+        self.containers
+            .get_or_try_init(|| {
+                response(self.recording.as_ref(), "docker/containers", async {
+                    self.throttle().await;
+                    Ok(self
+                        .docker
+                        .list_containers(Some(ListContainersOptions::<String> {
+                            all: true,
+                            ..Default::default()
+                        }))
+                        .await?)
+                })
+            })
+            .await
+            .map(Vec::as_slice)
+    }
+
+    /// All images present in the daemon by id.
+    pub async fn images(&self) -> Result<&HashMap<String, ImageSummary>> {
+        self.images
+            .get_or_try_init(|| async {
+                let images = response(self.recording.as_ref(), "docker/images", async {
+                    self.throttle().await;
+                    Ok(self
+                        .docker
+                        .list_images(Some(ListImagesOptions::<String> {
+                            all: true,
+                            ..Default::default()
+                        }))
+                        .await?)
+                })
+                .await?;
+                Ok(images
+                    .into_iter()
+                    .map(|image| (image.id.clone(), image))
+                    .collect())
+            })
+            .await
+    }
+
+    pub async fn image(&self, id: &str) -> Result<Option<&ImageSummary>> {
+        Ok(self.images().await?.get(id))
+    }
+
+    /// Records of the build cache of BuildKit.
+    pub async fn build_cache(&self) -> Result<Vec<BuildCache>> {
+        response(self.recording.as_ref(), "docker/build-cache", async {
+            self.throttle().await;
+            Ok(self.docker.df().await?.build_cache.unwrap_or_default())
+        })
+        .await
+    }
+
+    /// Number of containers skipped for living shorter than `schedule.min_container_age`.
+    pub fn short_lived_skipped(&self) -> usize {
+        self.short_lived_skipped.load(Ordering::Relaxed)
+    }
+
+    pub async fn inspect_container(&self, id: &str) -> Result<ContainerInspectResponse> {
+        let key = format!("docker/container-{id}");
+        response(self.recording.as_ref(), &key, async {
+            self.throttle().await;
+            Ok(self.docker.inspect_container(id, None).await?)
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "docker")]
+pub async fn get_docker_images(
+    config: &Config,
+    docker: &DockerClient,
+) -> Result<HashMap<Source, Tags>> {
+    #[cfg(feature = "kubernetes")]
+    let mut kubernetes = config
+        .kubernetes
+        .as_ref()
+        .map(|kubernetes| Kubernetes::new(kubernetes, config.proxy.get(Integration::Kubernetes)))
+        .transpose()?;
+
+    // Containers are grouped by image digest, so tags pointing at the same image are only
+    // scanned once. The tags of all containers using an image are merged.
+    let mut images: HashMap<String, (String, Option<String>, Tags)> = HashMap::new();
+    // Containers whose image is gone are scanned through their root file system instead.
+    let mut rootfs: HashMap<Source, Tags> = HashMap::new();
+    for container in docker.containers().await? {
+        if is_short_lived(config, docker, container).await {
+            debug!(container = container.id, "skipping short-lived container");
+            docker.short_lived_skipped.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        let labels = container.labels.clone().unwrap_or_default();
+        let mut tags = config.tags.clone();
+        merge_tags(
+            &mut tags,
+            config
+                .docker_labels
+                .iter()
+                .filter_map(|label| Some((label.clone(), labels.get(label)?.clone())))
+                .collect(),
+        );
+        #[cfg(feature = "kubernetes")]
+        if let Some(kubernetes) = kubernetes.as_mut() {
+            merge_tags(&mut tags, kubernetes.tags(&labels).await);
+        }
+
+        let name = container.image.clone().unwrap_or_default();
+        let id = container.image_id.clone().unwrap_or_default();
+        let Some(image) = docker.image(&id).await? else {
+            let container_id = container.id.clone().unwrap_or_default();
+            match merged_dir(docker, &container_id).await {
+                Some(path) => {
+                    debug!(
+                        container_id,
+                        ?path,
+                        "scanning root file system of container"
+                    );
+                    tags.insert("image".into(), name);
+                    merge_tags(
+                        rootfs.entry(Source::HostDirectory { path }).or_default(),
+                        tags,
+                    );
+                }
+                None => warn!(
+                    "Image {name} of container {container_id} is gone and its root file system \
+                     isn't accessible"
+                ),
+            }
+            continue;
+        };
+        // Containers whose tag was moved to a newer image only reference the image id.
+        let name = if name.starts_with("sha256:") {
+            image.repo_tags.first().cloned().unwrap_or(name)
+        } else {
+            name
+        };
+
+        let (image_name, _, image_tags) = images
+            .entry(id)
+            .or_insert_with(|| (name.clone(), repo_digest(image, &name), Tags::new()));
+        if name < *image_name {
+            *image_name = name;
+        }
+        merge_tags(image_tags, tags);
+    }
+
+    Ok(images
+        .into_iter()
+        .map(|(id, (name, digest, tags))| (Source::DockerImage { name, id, digest }, tags))
+        .chain(rootfs)
+        .collect())
+}
 
-    Ok(docker
-        .list_containers(options)
+/// Whether the container lived shorter than `schedule.min_container_age`: running containers
+/// by the time since they were created, stopped ones by the time between their start and end,
+/// as stopped containers of CI jobs get old without having lived long.
+#[cfg(feature = "docker")]
+async fn is_short_lived(
+    config: &Config,
+    docker: &DockerClient,
+    container: &ContainerSummary,
+) -> bool {
+    let min_age = config.schedule.min_container_age.as_secs() as i64;
+    if min_age == 0 {
+        return false;
+    }
+    if container.state.as_deref() == Some("running") {
+        return Utc::now().timestamp() - container.created.unwrap_or_default() < min_age;
+    }
+    let Some(id) = &container.id else {
+        return false;
+    };
+    let state = match docker.inspect_container(id).await {
+        Ok(inspected) => inspected.state.unwrap_or_default(),
+        Err(e) => {
+            debug!(container = id, "Failed to inspect container: {e:?}");
+            return false;
+        }
+    };
+    let time = |time: Option<String>| DateTime::parse_from_rfc3339(&time?).ok();
+    match (time(state.started_at), time(state.finished_at)) {
+        (Some(started), Some(finished)) => (finished - started).num_seconds() < min_age,
+        _ => false,
+    }
+}
+
+/// Dangling images, which have no tag, no container and no child image, e.g. previous builds
+/// kept by hosts acting as builders. They get the tag `origin: build_cache`, so they can be
+/// told apart from the images of running containers.
+#[cfg(feature = "docker")]
+pub async fn get_dangling_images(
+    config: &Config,
+    docker: &DockerClient,
+) -> Result<HashMap<Source, Tags>> {
+    if !config.build_cache {
+        return Ok(HashMap::new());
+    }
+    let used: HashSet<&str> = docker
+        .containers()
         .await?
         .iter()
-        .map(|v| (*v).clone().into())
-        .unique()
+        .filter_map(|container| container.image_id.as_deref())
+        .collect();
+    let images = docker.images().await?;
+    let parents: HashSet<&str> = images
+        .values()
+        .map(|image| image.parent_id.as_str())
+        .collect();
+    Ok(images
+        .values()
+        .filter(|image| {
+            !used.contains(image.id.as_str())
+                && !parents.contains(image.id.as_str())
+                && image.repo_tags.iter().all(|tag| tag == "<none>:<none>")
+        })
+        .map(|image| {
+            let mut tags = config.tags.clone();
+            tags.insert("origin".into(), "build_cache".into());
+            // Like `docker images`, the repository is known only for pulled images.
+            let digest = image.repo_digests.first().cloned();
+            let name = digest
+                .as_deref()
+                .and_then(|digest| digest.split_once('@'))
+                .map_or("<none>", |(repository, _)| repository)
+                .to_owned();
+            let source = Source::DockerImage {
+                name,
+                id: image.id.clone(),
+                digest,
+            };
+            (source, tags)
+        })
         .collect())
 }
+
+/// Number and size of the records of the build cache. Failures are only logged, e.g. with
+/// runtimes which don't report the build cache.
+#[cfg(feature = "docker")]
+pub async fn build_cache_usage(config: &Config, docker: &DockerClient) -> Option<BuildCacheUsage> {
+    if !config.build_cache {
+        return None;
+    }
+    let records = docker
+        .build_cache()
+        .await
+        .inspect_err(|e| warn!("Failed to get the build cache usage: {e:?}"))
+        .ok()?;
+    let size = |record: &BuildCache| record.size.unwrap_or_default().max(0) as u64;
+    Some(BuildCacheUsage {
+        records: records.len(),
+        bytes: records.iter().map(size).sum(),
+        reclaimable_bytes: records
+            .iter()
+            .filter(|record| record.in_use != Some(true) && record.shared != Some(true))
+            .map(size)
+            .sum(),
+    })
+}
+
+/// Names of the containers using each image, by image id.
+#[cfg(feature = "docker")]
+pub async fn image_containers(
+    config: &Config,
+    docker: &DockerClient,
+) -> Result<HashMap<String, Vec<String>>> {
+    let mut containers: HashMap<String, Vec<String>> = HashMap::new();
+    if !config.container_names {
+        return Ok(containers);
+    }
+    for container in docker.containers().await? {
+        // Docker reports names with a leading slash.
+        let Some(name) = container.names.iter().flatten().next() else {
+            continue;
+        };
+        containers
+            .entry(container.image_id.clone().unwrap_or_default())
+            .or_default()
+            .push(name.trim_start_matches('/').to_owned());
+    }
+    for names in containers.values_mut() {
+        names.sort();
+    }
+    Ok(containers)
+}
+
+/// The repository digest of an image, preferring the repository of the given reference, e.g.
+/// `nginx@sha256:…` for `nginx:1.25`. Images which were built locally and never pushed or
+/// pulled have none.
+#[cfg(feature = "docker")]
+fn repo_digest(image: &ImageSummary, name: &str) -> Option<String> {
+    let repository = name.split('@').next().unwrap_or(name);
+    // The tag is after the last colon, unless that colon belongs to a registry port.
+    let repository = repository
+        .rsplit_once(':')
+        .filter(|(_, tag)| !tag.contains('/'))
+        .map_or(repository, |(repository, _)| repository);
+    image
+        .repo_digests
+        .iter()
+        .find(|digest| digest.split('@').next() == Some(repository))
+        .or(image.repo_digests.first())
+        .cloned()
+}
+
+/// The merged overlay file system of a running container, as seen from the host.
+#[cfg(feature = "docker")]
+async fn merged_dir(docker: &DockerClient, container_id: &str) -> Option<PathBuf> {
+    let container = docker.inspect_container(container_id).await.ok()?;
+    let graph_driver = container.graph_driver?;
+    if graph_driver.name != "overlay2" && graph_driver.name != "overlay" {
+        return None;
+    }
+    let path = PathBuf::from(graph_driver.data.get("MergedDir")?);
+    path.exists().then_some(path)
+}
+
+/// Without the `docker` feature there is no daemon to talk to: no containers are discovered and
+/// no image metadata is known, so only configured directories and archives are scanned.
+#[cfg(not(feature = "docker"))]
+pub struct DockerClient;
+
+/// The fields of docker's image summary used outside of this module.
+#[cfg(not(feature = "docker"))]
+pub struct ImageSummary {
+    pub size: i64,
+    pub labels: HashMap<String, String>,
+}
+
+#[cfg(not(feature = "docker"))]
+impl DockerClient {
+    pub fn new(_config: &Config) -> Result<Self> {
+        Ok(Self)
+    }
+
+    pub async fn image(&self, _id: &str) -> Result<Option<&ImageSummary>> {
+        Ok(None)
+    }
+
+    pub fn short_lived_skipped(&self) -> usize {
+        0
+    }
+}
+
+#[cfg(not(feature = "docker"))]
+pub async fn get_docker_images(
+    _config: &Config,
+    _docker: &DockerClient,
+) -> Result<HashMap<Source, Tags>> {
+    Ok(HashMap::new())
+}
+
+#[cfg(not(feature = "docker"))]
+pub async fn get_dangling_images(
+    _config: &Config,
+    _docker: &DockerClient,
+) -> Result<HashMap<Source, Tags>> {
+    Ok(HashMap::new())
+}
+
+#[cfg(not(feature = "docker"))]
+pub async fn build_cache_usage(
+    _config: &Config,
+    _docker: &DockerClient,
+) -> Option<BuildCacheUsage> {
+    None
+}
+
+#[cfg(not(feature = "docker"))]
+pub async fn image_containers(
+    _config: &Config,
+    _docker: &DockerClient,
+) -> Result<HashMap<String, Vec<String>>> {
+    Ok(HashMap::new())
+}
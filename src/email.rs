@@ -0,0 +1,187 @@
+use std::process::Stdio;
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+use crate::{
+    config::Config,
+    notify::{hostname, Event, Notification, NotifierConfig},
+    report::Report,
+    smtp::{self, SmtpConfig},
+};
+
+/// Mail delivery to an SMTP server, or through the local MTA, e.g. postfix or an SMTP relay
+/// client like msmtp, which provide a sendmail compatible command.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub from: String,
+    pub to: Vec<String>,
+    /// The SMTP server delivering the mail. Without it, mail is handed to `sendmail`.
+    pub smtp: Option<SmtpConfig>,
+    /// The sendmail compatible command delivering the mail if no SMTP server is configured.
+    #[serde(default = "default_sendmail")]
+    pub sendmail: String,
+    /// Also mail the findings of every run as CSV attachment, not only the digest and policy
+    /// violations.
+    #[serde(default)]
+    pub every_run: bool,
+}
+
+fn default_sendmail() -> String {
+    "/usr/sbin/sendmail".into()
+}
+
+/// A file attached to a mail.
+pub struct Attachment {
+    pub name: String,
+    pub content_type: &'static str,
+    pub content: Vec<u8>,
+}
+
+/// Mail the findings of the run as CSV to all email notifiers with `every_run`.
+pub async fn mail_reports(config: &Config, report: &Report) {
+    let mut attachment = None;
+    for notifier in &config.notifiers {
+        let NotifierConfig::Email(email) = notifier else {
+            continue;
+        };
+        if !email.every_run {
+            continue;
+        }
+        let attachment = attachment.get_or_insert_with(|| Attachment {
+            name: format!("ssce-{}.csv", report.run_id),
+            content_type: "text/csv",
+            content: findings_csv(report).into_bytes(),
+        });
+        let notification = Notification {
+            event: Event::Report,
+            subject: format!("ssce report for {}", hostname()),
+            text: format!(
+                "Run {} found {} vulnerabilities in {} packages of {} sources.\n",
+                report.run_id,
+                report.summary.findings,
+                report.summary.packages,
+                report.summary.sources
+            ),
+            data: serde_json::to_value(&report.summary).unwrap_or_default(),
+        };
+        if let Err(e) = send_email(email, &notification, Some(attachment)).await {
+            warn!("Failed to mail report: {e:?}");
+        }
+    }
+}
+
+#[tracing::instrument(skip_all, fields(to = ?config.to))]
+pub async fn send_email(
+    config: &EmailConfig,
+    notification: &Notification,
+    attachment: Option<&Attachment>,
+) -> Result<()> {
+    debug!("sending mail");
+    let message = message(config, notification, attachment);
+    if let Some(smtp) = &config.smtp {
+        return smtp::send(smtp, &config.from, &config.to, &message).await;
+    }
+    let mut child = Command::new(&config.sendmail)
+        .arg("-i")
+        .arg("-f")
+        .arg(&config.from)
+        .arg("--")
+        .args(&config.to)
+        .stdin(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(message.as_bytes()).await?;
+    }
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!("{} exited with {status}", config.sendmail);
+    }
+    Ok(())
+}
+
+/// A MIME message with the text of the notification and the attachment, if any.
+fn message(
+    config: &EmailConfig,
+    notification: &Notification,
+    attachment: Option<&Attachment>,
+) -> String {
+    let mut message = format!(
+        "From: {}\r\nTo: {}\r\nSubject: =?UTF-8?B?{}?=\r\nDate: {}\r\nMIME-Version: 1.0\r\n",
+        config.from,
+        config.to.join(", "),
+        STANDARD.encode(&notification.subject),
+        Utc::now().to_rfc2822(),
+    );
+    let body = format!(
+        "Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}\r\n",
+        wrap(&STANDARD.encode(&notification.text))
+    );
+    let Some(attachment) = attachment else {
+        return message + &body;
+    };
+
+    let boundary = format!(
+        "ssce-{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    message += &format!("Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\r\n");
+    message += &format!("--{boundary}\r\n{body}");
+    message += &format!(
+        "--{boundary}\r\nContent-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: \
+         base64\r\nContent-Disposition: attachment; filename=\"{}\"\r\n\r\n{}\r\n",
+        attachment.content_type,
+        attachment.name,
+        wrap(&STANDARD.encode(&attachment.content))
+    );
+    message + &format!("--{boundary}--\r\n")
+}
+
+/// Wrap base64 into lines of 76 characters, the maximum for MIME.
+fn wrap(encoded: &str) -> String {
+    encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// The findings of all sources as CSV, one line per finding.
+pub fn findings_csv(report: &Report) -> String {
+    let mut csv =
+        String::from("source,package,version,vulnerability,severity,fix_state,fixed_versions\n");
+    for source in &report.sources {
+        for entry in &source.findings {
+            let fields = [
+                source.source.to_string(),
+                entry.artifact.name.clone(),
+                entry.artifact.version.clone(),
+                entry.vulnerability.id.clone(),
+                entry.vulnerability.severity.to_string(),
+                entry.vulnerability.fix.state.to_string(),
+                entry.vulnerability.fix.versions.join(" "),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| quote(field)).collect();
+            csv += &fields.join(",");
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
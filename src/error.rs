@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+
+use tracing::warn;
+
+use crate::config::Source;
+
+/// Kinds of errors, as exported in the `kind` label of `errors_total`.
+const KINDS: [&str; 4] = ["discovery", "sbom", "scan", "export"];
+
+/// Errors since the start of the process by kind.
+static ERRORS: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+
+/// Errors of the stages of a run, with the context needed to tell them apart in logs, metrics
+/// and exit codes. The underlying errors are `anyhow` errors, which keep their own context.
+/// Failing SBOMs and scans don't fail the run, they are logged and counted instead.
+pub enum SsceError {
+    /// Sources couldn't be discovered, e.g. because the docker daemon isn't reachable.
+    Discovery(anyhow::Error),
+    /// No SBOM could be created or loaded for a source.
+    Sbom {
+        source: Source,
+        error: anyhow::Error,
+    },
+    /// An SBOM couldn't be scanned for vulnerabilities.
+    Scan {
+        source: Source,
+        error: anyhow::Error,
+    },
+    /// Results couldn't be written to an output, like the metrics file.
+    Export {
+        output: String,
+        error: anyhow::Error,
+    },
+}
+
+impl SsceError {
+    fn index(&self) -> usize {
+        match self {
+            Self::Discovery(_) => 0,
+            Self::Sbom { .. } => 1,
+            Self::Scan { .. } => 2,
+            Self::Export { .. } => 3,
+        }
+    }
+
+    /// Short name of the stage that failed, as in the `kind` label of `errors_total`.
+    pub fn kind(&self) -> &'static str {
+        KINDS[self.index()]
+    }
+
+    /// Count the error in `errors_total`.
+    pub fn count(&self) {
+        ERRORS[self.index()].fetch_add(1, Relaxed);
+    }
+
+    /// Count the error of a failed run, if it's one of a stage.
+    pub fn count_of(error: &anyhow::Error) {
+        if let Some(error) = error.downcast_ref::<Self>() {
+            error.count();
+        }
+    }
+
+    /// Log and count an error which doesn't fail the run.
+    pub fn log(self) {
+        self.count();
+        warn!("{self:?}");
+    }
+
+    /// Exit code of the process when a run fails with this error. SBOMs and scans which fail
+    /// don't fail the run.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Discovery(_) => 2,
+            Self::Export { .. } => 5,
+            Self::Sbom { .. } | Self::Scan { .. } => 1,
+        }
+    }
+
+    /// Exit code for any error of a run, 1 for errors outside of the stages.
+    pub fn exit_code_of(error: &anyhow::Error) -> u8 {
+        error
+            .downcast_ref::<Self>()
+            .map_or(1, |error| error.exit_code())
+    }
+}
+
+/// Errors since the start of the process by kind, including kinds without errors.
+pub fn errors_total() -> impl Iterator<Item = (&'static str, u64)> {
+    KINDS
+        .into_iter()
+        .zip(ERRORS.iter().map(|errors| errors.load(Relaxed)))
+}
+
+impl fmt::Display for SsceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Discovery(_) => write!(f, "Discovering sources failed"),
+            Self::Sbom { source, .. } => write!(f, "Creating the SBOM of {source} failed"),
+            Self::Scan { source, .. } => write!(f, "Scanning {source} failed"),
+            Self::Export { output, .. } => write!(f, "Writing to {output} failed"),
+        }
+    }
+}
+
+/// Like `anyhow`, the debug output is meant for logs and includes the underlying error.
+impl fmt::Debug for SsceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Discovery(error)
+            | Self::Sbom { error, .. }
+            | Self::Scan { error, .. }
+            | Self::Export { error, .. } => write!(f, "{self}: {error:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SsceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Discovery(error)
+            | Self::Sbom { error, .. }
+            | Self::Scan { error, .. }
+            | Self::Export { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    proxy::Integration,
+    scan::Scan,
+    schema::duration_schema,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ExploitsConfig {
+    #[serde(default = "default_exploitdb_url")]
+    pub exploitdb_url: String,
+    #[serde(default = "default_metasploit_url")]
+    pub metasploit_url: String,
+    /// How long the downloaded exploit indexes are used before they are refreshed.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_refresh_after")]
+    pub refresh_after: Duration,
+}
+
+fn default_exploitdb_url() -> String {
+    "https://gitlab.com/exploit-database/exploitdb/-/raw/main/files_exploits.csv".into()
+}
+
+fn default_metasploit_url() -> String {
+    "https://raw.githubusercontent.com/rapid7/metasploit-framework/master/db/modules_metadata_base.json"
+        .into()
+}
+
+fn default_refresh_after() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// Vulnerability ids with public exploit code, mapped to the databases referencing them.
+pub type Exploits = HashMap<String, Vec<String>>;
+
+/// Load the ExploitDB and Metasploit indexes from the local cache, downloading them when
+/// they're missing or outdated. An index that can't be downloaded is skipped, an outdated
+/// cached copy is used if there is one.
+pub async fn load_exploits(config: &Config) -> Exploits {
+    let Some(exploits_config) = &config.exploits else {
+        return Exploits::new();
+    };
+
+    let client = match config.proxy.client(Integration::Exploits) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create the HTTP client for the exploit indexes: {e:?}");
+            return Exploits::new();
+        }
+    };
+    let mut exploits = Exploits::new();
+    for (name, url) in [
+        ("exploitdb", &exploits_config.exploitdb_url),
+        ("metasploit", &exploits_config.metasploit_url),
+    ] {
+        let path = config.base_path.join("exploits").join(name);
+        match cached_download(&client, &path, url, exploits_config.refresh_after).await {
+            Ok(index) => {
+                for id in vulnerability_ids(&index) {
+                    exploits.entry(id).or_default().push(name.into());
+                }
+            }
+            Err(e) => warn!("Failed to load the {name} exploit index: {e:?}"),
+        }
+    }
+    exploits
+}
+
+async fn cached_download(
+    client: &reqwest::Client,
+    path: &Path,
+    url: &str,
+    refresh_after: Duration,
+) -> Result<String> {
+    let age = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if age.is_some_and(|age| age < refresh_after) {
+        return Ok(std::fs::read_to_string(path)?);
+    }
+
+    debug!(url, "downloading exploit index");
+    let download = async {
+        let response = client.get(url).send().await?.error_for_status()?;
+        anyhow::Ok(response.text().await?)
+    };
+    match download.await {
+        Ok(index) => {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            write_atomic(path, &index)?;
+            Ok(index)
+        }
+        Err(e) if age.is_some() => {
+            warn!("Failed to refresh {url}, using the cached copy: {e:?}");
+            Ok(std::fs::read_to_string(path)?)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// All CVE ids mentioned in an index, independent of its format.
+fn vulnerability_ids(index: &str) -> HashSet<String> {
+    index
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+        .filter(|token| token.starts_with("CVE-"))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Note which findings have public exploit code, by their canonical id.
+pub fn enrich_exploits(scans: &mut HashMap<Source, Scan>, exploits: &Exploits) {
+    for scan in scans.values_mut() {
+        for entry in &mut scan.matches {
+            entry.exploits = exploits
+                .get(entry.canonical_id())
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Scanner independent model of vulnerability findings. Every scanner backend maps its results
+//! into [`Finding`]s, so comparisons and persistence don't depend on a scanner's output format.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Source,
+    scan::{Cvss, CvssMetrics, FixState, Scan, ScanEntry},
+    severity::Severity,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Id of the vulnerability in the database that reported it, e.g. a CVE or GHSA id.
+    pub id: String,
+    /// Other ids of the same vulnerability.
+    pub aliases: Vec<String>,
+    pub severity: Severity,
+    pub cvss: Vec<Cvss>,
+    pub fix: FixInfo,
+    pub package: PackageRef,
+    pub source: Source,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixInfo {
+    pub state: FixState,
+    pub versions: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageRef {
+    pub name: String,
+    pub version: String,
+    /// Package type in syft's spelling, e.g. `deb` or `rust-crate`.
+    pub package_type: String,
+    pub purl: Option<String>,
+    pub locations: Vec<String>,
+}
+
+impl Finding {
+    pub fn from_grype(source: &Source, entry: &ScanEntry) -> Self {
+        Self {
+            id: entry.vulnerability.id.clone(),
+            aliases: entry
+                .related_vulnerabilities
+                .iter()
+                .map(|related| related.id.clone())
+                .filter(|id| *id != entry.vulnerability.id)
+                .collect(),
+            severity: entry.vulnerability.severity,
+            cvss: entry.vulnerability.cvss.clone(),
+            fix: FixInfo {
+                state: entry.vulnerability.fix.state.clone(),
+                versions: entry.vulnerability.fix.versions.clone(),
+            },
+            package: PackageRef {
+                name: entry.artifact.name.clone(),
+                version: entry.artifact.version.clone(),
+                package_type: entry.artifact.artifact_type.clone(),
+                purl: entry.artifact.purl.clone().filter(|purl| !purl.is_empty()),
+                locations: entry
+                    .artifact
+                    .locations
+                    .iter()
+                    .map(|location| location.path.clone())
+                    .collect(),
+            },
+            source: source.clone(),
+        }
+    }
+
+    /// Id shared by all aliases of the vulnerability: the CVE id if there is one, the id
+    /// otherwise.
+    pub fn canonical_id(&self) -> &str {
+        canonical_id(&self.id, self.aliases.iter().map(String::as_str))
+    }
+}
+
+/// The CVE id among a vulnerability id and its aliases, or the id if there is none.
+pub fn canonical_id<'a>(id: &'a str, aliases: impl IntoIterator<Item = &'a str>) -> &'a str {
+    std::iter::once(id)
+        .chain(aliases)
+        .find(|id| id.starts_with("CVE-"))
+        .unwrap_or(id)
+}
+
+/// All findings of a grype scan.
+pub fn from_grype(source: &Source, scan: &Scan) -> Vec<Finding> {
+    scan.matches
+        .iter()
+        .map(|entry| Finding::from_grype(source, entry))
+        .collect()
+}
+
+/// Report of `trivy sbom --format json`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TrivyReport {
+    #[serde(default)]
+    pub results: Vec<TrivyResult>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TrivyResult {
+    #[serde(default)]
+    pub target: String,
+    /// Package type in trivy's spelling, e.g. `debian` or `cargo`.
+    #[serde(rename = "Type", default)]
+    pub result_type: String,
+    #[serde(default)]
+    pub vulnerabilities: Vec<TrivyVulnerability>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct TrivyVulnerability {
+    #[serde(rename = "VulnerabilityID")]
+    pub vulnerability_id: String,
+    #[serde(rename = "VendorIDs")]
+    pub vendor_ids: Vec<String>,
+    pub pkg_name: String,
+    pub pkg_path: String,
+    pub pkg_identifier: TrivyPkgIdentifier,
+    pub installed_version: String,
+    /// Comma separated list of fixed versions.
+    pub fixed_version: String,
+    pub status: String,
+    pub severity: String,
+    /// CVSS scores by the source which assigned them, e.g. `nvd`.
+    #[serde(rename = "CVSS")]
+    pub cvss: std::collections::BTreeMap<String, TrivyCvss>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrivyPkgIdentifier {
+    #[serde(rename = "PURL")]
+    pub purl: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct TrivyCvss {
+    pub v2_vector: Option<String>,
+    pub v2_score: Option<f64>,
+    pub v3_vector: Option<String>,
+    pub v3_score: Option<f64>,
+}
+
+/// All findings of a trivy report.
+pub fn from_trivy(source: &Source, report: &TrivyReport) -> Vec<Finding> {
+    report
+        .results
+        .iter()
+        .flat_map(|result| {
+            result
+                .vulnerabilities
+                .iter()
+                .map(|vulnerability| trivy_finding(source, result, vulnerability))
+        })
+        .collect()
+}
+
+fn trivy_finding(source: &Source, result: &TrivyResult, vuln: &TrivyVulnerability) -> Finding {
+    let versions: Vec<String> = vuln
+        .fixed_version
+        .split(',')
+        .map(str::trim)
+        .filter(|version| !version.is_empty())
+        .map(ToOwned::to_owned)
+        .collect();
+    let state = match vuln.status.as_str() {
+        "fixed" => FixState::Fixed,
+        "affected" | "fix_deferred" => FixState::NotFixed,
+        "will_not_fix" | "end_of_life" => FixState::WontFix,
+        _ if !versions.is_empty() => FixState::Fixed,
+        _ => FixState::Unknown,
+    };
+
+    let mut cvss = Vec::new();
+    for (cvss_source, scores) in &vuln.cvss {
+        let cvss_type = if cvss_source == "nvd" {
+            "Primary"
+        } else {
+            "Secondary"
+        };
+        let versions = [
+            (&scores.v3_vector, scores.v3_score),
+            (&scores.v2_vector, scores.v2_score),
+        ];
+        for (vector, score) in versions {
+            let Some(vector) = vector else {
+                continue;
+            };
+            cvss.push(Cvss {
+                source: cvss_source.clone(),
+                cvss_type: cvss_type.into(),
+                version: cvss_version(vector).into(),
+                vector: vector.clone(),
+                metrics: CvssMetrics {
+                    base_score: score
+                        .and_then(|score| Decimal::try_from(score).ok())
+                        .unwrap_or_default(),
+                    exploitability_score: Decimal::default(),
+                    impact_score: Decimal::default(),
+                },
+            });
+        }
+    }
+
+    Finding {
+        id: vuln.vulnerability_id.clone(),
+        aliases: vuln.vendor_ids.clone(),
+        severity: Severity::parse(&vuln.severity),
+        cvss,
+        fix: FixInfo { state, versions },
+        package: PackageRef {
+            name: vuln.pkg_name.clone(),
+            version: vuln.installed_version.clone(),
+            package_type: trivy_package_type(&result.result_type).into(),
+            purl: vuln.pkg_identifier.purl.clone(),
+            locations: [&vuln.pkg_path, &result.target]
+                .into_iter()
+                .find(|path| !path.is_empty())
+                .cloned()
+                .into_iter()
+                .collect(),
+        },
+        source: source.clone(),
+    }
+}
+
+/// CVSS version of a vector: v3 vectors start with `CVSS:3.x/`, v2 vectors have no prefix.
+fn cvss_version(vector: &str) -> &str {
+    vector
+        .strip_prefix("CVSS:")
+        .and_then(|rest| rest.split('/').next())
+        .unwrap_or("2.0")
+}
+
+fn trivy_package_type(result_type: &str) -> &str {
+    match result_type {
+        "debian" | "ubuntu" => "deb",
+        "alpine" | "wolfi" | "chainguard" => "apk",
+        "redhat" | "centos" | "rocky" | "alma" | "amazon" | "oracle" | "photon" | "suse" => "rpm",
+        "cargo" | "rust-binary" => "rust-crate",
+        "gobinary" | "gomod" => "go-module",
+        "jar" | "pom" | "gradle" => "java-archive",
+        "npm" | "yarn" | "pnpm" | "node-pkg" => "npm",
+        "pip" | "pipenv" | "poetry" | "python-pkg" => "python",
+        "bundler" | "gemspec" => "gem",
+        "composer" => "php-composer",
+        "nuget" | "dotnet-core" => "dotnet",
+        other => other,
+    }
+}
@@ -0,0 +1,177 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::config::Config;
+
+/// Version of a firmware component of the host.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Firmware {
+    /// Kind of firmware: `bios`, `microcode`, `nic`, `storage`, or `device` for everything
+    /// reported by fwupd.
+    pub component: String,
+    pub device: String,
+    pub vendor: String,
+    pub version: String,
+}
+
+/// Collect firmware versions from DMI, `/sys`, `ethtool` and fwupd. Collectors that aren't
+/// available on the host are skipped.
+pub async fn collect_firmware(config: &Config) -> Vec<Firmware> {
+    if !config.firmware_inventory {
+        return Vec::new();
+    }
+
+    let mut firmware = Vec::new();
+    firmware.extend(bios());
+    firmware.extend(microcode());
+    firmware.extend(nics().await);
+    firmware.extend(storage());
+    firmware.extend(fwupd().await);
+    firmware
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_owned())
+        .filter(|content| !content.is_empty())
+}
+
+fn bios() -> Option<Firmware> {
+    let dmi = Path::new("/sys/class/dmi/id");
+    Some(Firmware {
+        component: "bios".into(),
+        device: read_trimmed(dmi.join("product_name")).unwrap_or_default(),
+        vendor: read_trimmed(dmi.join("bios_vendor")).unwrap_or_default(),
+        version: read_trimmed(dmi.join("bios_version"))?,
+    })
+}
+
+fn microcode() -> Option<Firmware> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    let field = |name: &str| {
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == name).then(|| value.trim().to_owned())
+        })
+    };
+    Some(Firmware {
+        component: "microcode".into(),
+        device: field("model name").unwrap_or_default(),
+        vendor: field("vendor_id").unwrap_or_default(),
+        version: field("microcode")?,
+    })
+}
+
+async fn nics() -> Vec<Firmware> {
+    let mut firmware = Vec::new();
+    let Ok(interfaces) = fs::read_dir("/sys/class/net") else {
+        return firmware;
+    };
+    for interface in interfaces.filter_map(|entry| entry.ok()) {
+        // Virtual interfaces like bridges and veths have no device and no firmware.
+        if !interface.path().join("device").exists() {
+            continue;
+        }
+        let name = interface.file_name().to_string_lossy().into_owned();
+        let Ok(output) = Command::new("ethtool")
+            .arg("-i")
+            .arg(&name)
+            .kill_on_drop(true)
+            .output()
+            .await
+        else {
+            debug!("ethtool is not available");
+            break;
+        };
+        let info = String::from_utf8_lossy(&output.stdout);
+        let field = |key: &str| {
+            info.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty() && value != "N/A")
+        };
+        if let Some(version) = field("firmware-version") {
+            firmware.push(Firmware {
+                component: "nic".into(),
+                device: name,
+                vendor: field("driver").unwrap_or_default(),
+                version,
+            });
+        }
+    }
+    firmware
+}
+
+fn storage() -> Vec<Firmware> {
+    let mut firmware = Vec::new();
+    for entry in fs::read_dir("/sys/class/nvme")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+    {
+        let path = entry.path();
+        if let Some(version) = read_trimmed(path.join("firmware_rev")) {
+            firmware.push(Firmware {
+                component: "storage".into(),
+                device: read_trimmed(path.join("model"))
+                    .unwrap_or_else(|| entry.file_name().to_string_lossy().into_owned()),
+                vendor: String::new(),
+                version,
+            });
+        }
+    }
+    for entry in fs::read_dir("/sys/block")
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+    {
+        let device = entry.path().join("device");
+        // SCSI and SATA disks report their firmware revision as `rev`.
+        if let Some(version) = read_trimmed(device.join("rev")) {
+            firmware.push(Firmware {
+                component: "storage".into(),
+                device: read_trimmed(device.join("model"))
+                    .unwrap_or_else(|| entry.file_name().to_string_lossy().into_owned()),
+                vendor: read_trimmed(device.join("vendor")).unwrap_or_default(),
+                version,
+            });
+        }
+    }
+    firmware
+}
+
+async fn fwupd() -> Vec<Firmware> {
+    let Ok(output) = Command::new("fwupdmgr")
+        .arg("get-devices")
+        .arg("--json")
+        .kill_on_drop(true)
+        .output()
+        .await
+    else {
+        debug!("fwupd is not available");
+        return Vec::new();
+    };
+    let Ok(devices) = serde_json::from_slice::<Value>(&output.stdout) else {
+        return Vec::new();
+    };
+    devices
+        .get("Devices")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|device| {
+            let field = |key: &str| device.get(key).and_then(Value::as_str).map(str::to_owned);
+            Some(Firmware {
+                component: "device".into(),
+                device: field("Name")?,
+                vendor: field("Vendor").unwrap_or_default(),
+                version: field("Version")?,
+            })
+        })
+        .collect()
+}
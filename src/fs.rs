@@ -0,0 +1,24 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Write a file by writing to a temporary file next to it and renaming it into place, so an
+/// interrupted run never leaves a partially written file behind.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// Create the directory a file is written to. A bare file name, e.g. a configured
+/// `metrics.prom`, is written to the working directory, which exists.
+pub fn create_parent(path: &Path) -> Result<()> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{Config, Source},
+    docker::DockerClient,
+    proxy::Integration,
+    sbom::Sbom,
+    secret::Secret,
+};
+
+/// OCI annotation containing the commit an image was built from.
+const REVISION_LABEL: &str = "org.opencontainers.image.revision";
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct GithubConfig {
+    pub token: Secret,
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// Mapping from images to the repositories they are built from.
+    pub repositories: Vec<GithubRepository>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct GithubRepository {
+    /// Prefix of the image name, e.g. `ghcr.io/famedly/app`.
+    pub image: String,
+    /// Repository in `owner/name` form.
+    pub repository: String,
+    #[serde(rename = "ref", default = "default_ref")]
+    pub git_ref: String,
+}
+
+fn default_api_url() -> String {
+    "https://api.github.com".into()
+}
+
+fn default_ref() -> String {
+    "refs/heads/main".into()
+}
+
+/// Submit the dependencies of deployed images to the GitHub dependency graph of the repository
+/// they were built from, so Dependabot alerts cover what is actually deployed. The commit is
+/// taken from the image's OCI revision label.
+pub async fn submit_dependency_snapshots(
+    config: &Config,
+    docker: &DockerClient,
+    sboms: &HashMap<Source, Value>,
+) -> Result<()> {
+    let Some(github) = &config.github else {
+        return Ok(());
+    };
+
+    let client = config.proxy.client(Integration::Github)?;
+    for (source, sbom) in sboms {
+        let Source::DockerImage { name, id, .. } = source else {
+            continue;
+        };
+        let Some(repository) = github
+            .repositories
+            .iter()
+            .find(|repository| name.starts_with(&repository.image))
+        else {
+            continue;
+        };
+
+        let res = async {
+            let sha = docker
+                .image(id)
+                .await?
+                .and_then(|image| image.labels.get(REVISION_LABEL).cloned())
+                .with_context(|| format!("Image has no {REVISION_LABEL} label"))?;
+            let sbom: Sbom = serde_json::from_value(sbom.clone())?;
+            submit(&client, github, repository, name, &sha, &sbom).await
+        }
+        .await;
+        if let Err(e) = res {
+            warn!("Error submitting dependency snapshot for {source}: {e:?}");
+        }
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(client, github, sbom))]
+async fn submit(
+    client: &reqwest::Client,
+    github: &GithubConfig,
+    repository: &GithubRepository,
+    image: &str,
+    sha: &str,
+    sbom: &Sbom,
+) -> Result<()> {
+    let resolved = sbom
+        .packages
+        .iter()
+        .filter_map(|package| {
+            let purl = package.purl()?;
+            Some((
+                purl.to_owned(),
+                json!({ "package_url": purl, "scope": "runtime" }),
+            ))
+        })
+        .collect::<Map<_, _>>();
+
+    let snapshot = json!({
+        "version": 0,
+        "sha": sha,
+        "ref": repository.git_ref,
+        "job": {
+            // Snapshots with the same correlator replace each other.
+            "correlator": format!("ssce {image}"),
+            "id": Utc::now().timestamp().to_string(),
+        },
+        "detector": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "url": env!("CARGO_PKG_REPOSITORY"),
+        },
+        "scanned": Utc::now().to_rfc3339(),
+        "manifests": {
+            image: { "name": image, "resolved": resolved },
+        },
+    });
+
+    debug!("submitting dependency snapshot");
+    client
+        .post(format!(
+            "{}/repos/{}/dependency-graph/snapshots",
+            github.api_url, repository.repository
+        ))
+        .bearer_auth(github.token.expose())
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", env!("CARGO_PKG_NAME"))
+        .json(&snapshot)
+        .send()
+        .await?
+        .error_for_status()?;
+    info!("submitted dependency snapshot");
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use ring::digest;
+use serde_json::{json, Value};
+
+use crate::{
+    config::{Config, Source},
+    fs::{create_parent, write_atomic},
+    scan::{FixState, Scan},
+    severity::Severity,
+};
+
+const SCHEMA_VERSION: &str = "15.0.7";
+const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Write all findings of this run as a GitLab dependency scanning security report, so they can
+/// be attached to pipelines and show up in the GitLab vulnerability report.
+pub fn write_gitlab_report(
+    config: &Config,
+    started: DateTime<Utc>,
+    scans: &HashMap<Source, Scan>,
+) -> Result<()> {
+    let Some(path) = &config.gitlab_report else {
+        return Ok(());
+    };
+
+    let scanner = json!({
+        "id": "ssce",
+        "name": "software_supply_chain_exporter",
+        "vendor": { "name": "Famedly" },
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+
+    let vulnerabilities = scans
+        .iter()
+        .flat_map(|(source, scan)| {
+            scan.matches.iter().map(move |entry| {
+                let file = entry
+                    .artifact
+                    .locations
+                    .first()
+                    .map(|location| location.path.clone())
+                    .unwrap_or_else(|| source.to_string());
+
+                // SHA-256 rather than the hasher of the standard library, which may change
+                // between releases, so GitLab recognizes findings across runs.
+                let key = format!(
+                    "{source}\n{}\n{}\n{file}\n{}",
+                    entry.artifact.name, entry.artifact.version, entry.vulnerability.id
+                );
+                let id: String = digest::digest(&digest::SHA256, key.as_bytes())
+                    .as_ref()
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+
+                let identifier_type = entry
+                    .vulnerability
+                    .id
+                    .split('-')
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let solution = match entry.vulnerability.fix.state {
+                    FixState::Fixed => Some(format!(
+                        "Upgrade {} to {}",
+                        entry.artifact.name,
+                        entry.vulnerability.fix.versions.join(" or ")
+                    )),
+                    _ => None,
+                };
+
+                json!({
+                    "id": id,
+                    "name": format!("{} in {}", entry.vulnerability.id, entry.artifact.name),
+                    "description": entry.vulnerability.description,
+                    "severity": severity(entry.vulnerability.severity),
+                    "solution": solution,
+                    "identifiers": [{
+                        "type": identifier_type,
+                        "name": entry.vulnerability.id,
+                        "value": entry.vulnerability.id,
+                        "url": entry.vulnerability.urls.first(),
+                    }],
+                    "links": entry.vulnerability.urls.iter().map(|url| json!({ "url": url })).collect::<Vec<_>>(),
+                    "location": {
+                        "file": file,
+                        "dependency": {
+                            "package": { "name": entry.artifact.name },
+                            "version": entry.artifact.version,
+                        },
+                    },
+                })
+            })
+        })
+        .collect::<Vec<Value>>();
+
+    let report = json!({
+        "version": SCHEMA_VERSION,
+        "scan": {
+            "analyzer": scanner,
+            "scanner": scanner,
+            "type": "dependency_scanning",
+            "start_time": started.format(TIME_FORMAT).to_string(),
+            "end_time": Utc::now().format(TIME_FORMAT).to_string(),
+            "status": "success",
+        },
+        "vulnerabilities": vulnerabilities,
+        "dependency_files": [],
+    });
+
+    create_parent(path)?;
+    write_atomic(path, serde_json::to_vec_pretty(&report)?)?;
+    Ok(())
+}
+
+/// Map grype severities onto the ones allowed by the report schema.
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Negligible => "Info",
+        severity => severity.as_str(),
+    }
+}
@@ -0,0 +1,79 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Source, Tags},
+    report::Report,
+    scan::FixState,
+    severity::Severity,
+};
+
+/// Sources belonging together, e.g. all images of a product, whose findings are rolled up into
+/// metrics per group. A source belongs to every group it matches.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct SourceGroup {
+    pub name: String,
+    /// Tags a source needs to have.
+    #[serde(default)]
+    pub tags: Tags,
+    /// Prefixes of image names or paths, of which a source needs to match one. All sources if
+    /// empty.
+    #[serde(default)]
+    pub sources: Vec<String>,
+}
+
+impl SourceGroup {
+    pub fn contains(&self, source: &Source, tags: &Tags) -> bool {
+        let name = source.name();
+        self.tags
+            .iter()
+            .all(|(key, value)| tags.get(key) == Some(value))
+            && (self.sources.is_empty()
+                || self
+                    .sources
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix.as_str())))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GroupRollup {
+    pub name: String,
+    pub sources: usize,
+    pub packages: usize,
+    pub vulnerabilities: usize,
+    /// Most severe finding of all sources.
+    pub highest_severity: Severity,
+}
+
+/// Totals of the sources of each group. Like the other metrics, only findings with a fix are
+/// counted with `only_fixed`.
+pub fn rollups(config: &Config, report: &Report) -> Vec<GroupRollup> {
+    config
+        .groups
+        .iter()
+        .map(|group| {
+            let mut rollup = GroupRollup {
+                name: group.name.clone(),
+                ..Default::default()
+            };
+            for source in &report.sources {
+                if !group.contains(&source.source, &source.tags) {
+                    continue;
+                }
+                let findings = source.findings.iter().filter(|entry| {
+                    !config.only_fixed || entry.vulnerability.fix.state == FixState::Fixed
+                });
+                rollup.sources += 1;
+                rollup.packages += source.packages;
+                for entry in findings {
+                    rollup.vulnerabilities += 1;
+                    rollup.highest_severity =
+                        rollup.highest_severity.max(entry.vulnerability.severity);
+                }
+            }
+            rollup
+        })
+        .collect()
+}
@@ -0,0 +1,223 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use ring::digest;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::{
+    config::{default_true, Config},
+    proxy::Integration,
+    recording::{output, Recording},
+    schema::duration_schema,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct GrypeDbConfig {
+    /// Maximum age of the vulnerability database, counted from when it was built.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_max_age")]
+    pub max_age: Duration,
+    /// What to do when the database is invalid or too old.
+    #[serde(default)]
+    pub on_failure: DbFailureAction,
+    /// Update the database before each run. Disable it for databases staged by configuration
+    /// management, whose checksum is verified once at startup instead.
+    #[serde(default = "default_true")]
+    pub auto_update: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbFailureAction {
+    /// Abort the run instead of producing incomplete scans.
+    #[default]
+    Abort,
+    /// Log a warning and scan anyway.
+    Warn,
+}
+
+impl Default for GrypeDbConfig {
+    fn default() -> Self {
+        Self {
+            max_age: default_max_age(),
+            on_failure: DbFailureAction::default(),
+            auto_update: true,
+        }
+    }
+}
+
+fn default_max_age() -> Duration {
+    Duration::from_secs(5 * 24 * 60 * 60)
+}
+
+/// Whether the checksum of a staged database was verified by this process.
+static VERIFIED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug)]
+pub struct DbStatus {
+    pub built: DateTime<Utc>,
+    pub schema: String,
+    pub valid: bool,
+    /// Directory of the database.
+    pub location: Option<PathBuf>,
+}
+
+/// Update the grype vulnerability database and check that it is usable before scanning.
+#[tracing::instrument(skip(config))]
+pub async fn update_db(config: &Config) -> Result<Option<DbStatus>> {
+    let replay = matches!(config.recording, Some(Recording::Replay(_)));
+    if !replay && config.grype_db.auto_update {
+        let mut command = Command::new("grype");
+        command
+            .arg("db")
+            .arg("update")
+            .arg("--quiet")
+            .kill_on_drop(true);
+        config.proxy.get(Integration::GrypeDb).apply(&mut command);
+        command.spawn()?.wait().await?;
+    }
+
+    let status = db_status(config.recording.as_ref()).await;
+    let mismatch = match &status {
+        Ok(status) if !replay && !config.grype_db.auto_update && !VERIFIED.load(Relaxed) => {
+            verify_checksum(status).await.err()
+        }
+        _ => None,
+    };
+    let problem = match (&status, mismatch) {
+        (Err(e), _) => format!("Failed to get grype database status: {e:?}"),
+        (Ok(status), _) if !status.valid => "The grype database is invalid".into(),
+        (Ok(_), Some(e)) => format!("The checksum of the grype database doesn't match: {e:?}"),
+        // The age is only meaningful at the time of the recording.
+        (Ok(status), None) if replay => return Ok(Some(status.clone())),
+        (Ok(status), None) => {
+            let age = (Utc::now() - status.built).to_std().unwrap_or_default();
+            if age <= config.grype_db.max_age {
+                return Ok(Some(status.clone()));
+            }
+            format!(
+                "The grype database was built {} ago",
+                humantime::format_duration(Duration::from_secs(age.as_secs()))
+            )
+        }
+    };
+    match config.grype_db.on_failure {
+        DbFailureAction::Abort => bail!(problem),
+        DbFailureAction::Warn => warn!("{problem}"),
+    }
+    Ok(status.ok())
+}
+
+/// Compare the checksum of a staged database with the one in its metadata, once per process.
+/// Databases of schema v6 and newer don't use SHA-256 checksums and are only validated by
+/// grype itself.
+async fn verify_checksum(status: &DbStatus) -> Result<()> {
+    let Some(location) = &status.location else {
+        bail!("grype doesn't report the location of the database");
+    };
+    let metadata: Value = serde_json::from_slice(&std::fs::read(location.join("metadata.json"))?)?;
+    let Some(expected) = metadata["checksum"]
+        .as_str()
+        .and_then(|checksum| checksum.strip_prefix("sha256:"))
+    else {
+        debug!("database has no sha256 checksum, relying on grype's validation");
+        VERIFIED.store(true, Relaxed);
+        return Ok(());
+    };
+    let path = location.join("vulnerability.db");
+    let actual = tokio::task::spawn_blocking(move || sha256_file(&path)).await??;
+    if actual != expected {
+        bail!("expected {expected}, got {actual}");
+    }
+    debug!("verified checksum of the grype database");
+    VERIFIED.store(true, Relaxed);
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = digest::Context::new(&digest::SHA256);
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        match file.read(&mut buffer)? {
+            0 => break,
+            read => context.update(&buffer[..read]),
+        }
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+async fn db_status(recording: Option<&Recording>) -> Result<DbStatus> {
+    debug!("querying grype database status");
+    let mut command = Command::new("grype");
+    command
+        .arg("db")
+        .arg("status")
+        .arg("-o")
+        .arg("json")
+        .kill_on_drop(true);
+    let output = output(recording, "grype/db-status", &mut command).await?;
+    if let Ok(status) = serde_json::from_slice::<Value>(&output.stdout) {
+        return Ok(DbStatus {
+            built: status["built"]
+                .as_str()
+                .context("database build time missing")?
+                .parse()?,
+            schema: status["schemaVersion"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            valid: status["valid"].as_bool().unwrap_or_default()
+                && status["error"].as_str().unwrap_or_default().is_empty(),
+            location: status["location"]
+                .as_str()
+                .or(status["path"].as_str())
+                .map(database_dir),
+        });
+    }
+
+    // Older grype versions only print `Key: value` lines.
+    let output = String::from_utf8(output.stdout)?;
+    let field = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+            .map(str::trim)
+    };
+    Ok(DbStatus {
+        built: field("Built")
+            .context("database build time missing")?
+            .parse()?,
+        schema: field("Schema").unwrap_or_default().to_owned(),
+        valid: field("Status") == Some("valid"),
+        location: field("Location").or(field("Path")).map(database_dir),
+    })
+}
+
+/// Newer grype versions report the path of the database file instead of its directory.
+fn database_dir(location: &str) -> PathBuf {
+    let location = PathBuf::from(location);
+    match location.extension() {
+        Some(extension) if extension == "db" => {
+            location.parent().map(Path::to_path_buf).unwrap_or(location)
+        }
+        _ => location,
+    }
+}
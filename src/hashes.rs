@@ -0,0 +1,93 @@
+//! SHA-256 digests of the files syft catalogs, to answer whether a binary known from an
+//! incident is present anywhere, with `ssce find-hash`, without scanning again.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    report::read_report_sources,
+    sbom::{cached_sboms, Sbom},
+};
+
+/// A file, or a package archive without a file, by the SHA-256 digest of its content.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BinaryHash {
+    pub sha256: String,
+    pub path: String,
+}
+
+/// Where a digest was found: the source of the run report or the cached SBOM file.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HashMatch {
+    pub location: String,
+    pub path: String,
+}
+
+/// Lower case hex digest, without the `sha256:` prefix of image and OCI digests.
+fn normalize(digest: &str) -> String {
+    let digest = digest.trim().to_lowercase();
+    digest.strip_prefix("sha256:").unwrap_or(&digest).to_owned()
+}
+
+/// The SHA-256 digests SPDX lists as checksums of files and packages. syft only computes them
+/// for files owned by packages and for archives like JARs.
+pub fn binary_hashes(sbom: &Sbom) -> Vec<BinaryHash> {
+    let files = sbom
+        .files
+        .iter()
+        .map(|file| (&file.checksums, file.fileName.clone()));
+    let packages = sbom.packages.iter().map(|package| {
+        let path = package
+            .locations()
+            .first()
+            .map_or_else(|| package.name.clone(), |path| (*path).to_owned());
+        (&package.checksums, path)
+    });
+    files
+        .chain(packages)
+        .flat_map(|(checksums, path)| {
+            checksums
+                .iter()
+                .filter(|checksum| checksum.algorithm.eq_ignore_ascii_case("sha256"))
+                .map(move |checksum| BinaryHash {
+                    sha256: normalize(&checksum.checksumValue),
+                    path: path.clone(),
+                })
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Search the run report and the cached SBOMs for the digest. The report covers every source
+/// of the last run if `binary_hashes` is enabled, the cache covers images and CI workspaces of
+/// earlier runs as well.
+pub fn find_hash(config: &Config, digest: &str) -> Result<Vec<HashMatch>> {
+    let digest = normalize(digest);
+    let mut matches = BTreeSet::new();
+
+    for (location, source) in read_report_sources(config)? {
+        let hashes: Vec<BinaryHash> =
+            serde_json::from_value(source["binary_hashes"].clone()).unwrap_or_default();
+        for hash in hashes.into_iter().filter(|hash| hash.sha256 == digest) {
+            matches.insert(HashMatch {
+                location: location.clone(),
+                path: hash.path,
+            });
+        }
+    }
+    for (path, sbom) in cached_sboms(config) {
+        for hash in binary_hashes(&sbom) {
+            if hash.sha256 == digest {
+                matches.insert(HashMatch {
+                    location: path.display().to_string(),
+                    path: hash.path,
+                });
+            }
+        }
+    }
+    Ok(matches.into_iter().collect())
+}
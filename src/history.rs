@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    scan::Scan,
+};
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct HistoryEntry<'a> {
+    pub timestamp: DateTime<Utc>,
+    pub source: &'a Source,
+    pub sbom: Option<&'a Value>,
+    pub scan: Option<&'a Scan>,
+}
+
+/// Write the results of this run into the per-source history directories and prune old entries,
+/// so past states of a host can be reconstructed from local data.
+pub fn record_history(
+    config: &Config,
+    sboms: &HashMap<Source, Value>,
+    scans: &HashMap<Source, Scan>,
+) -> Result<()> {
+    let Some(retention) = config.history_retention else {
+        return Ok(());
+    };
+
+    let timestamp = Utc::now();
+    for (source, sbom) in sboms {
+        let dir = config.history_path().join(source.slug());
+        std::fs::create_dir_all(&dir)?;
+
+        let entry = HistoryEntry {
+            timestamp,
+            source,
+            sbom: Some(sbom),
+            scan: scans.get(source),
+        };
+        // Millisecond precision, so runs in quick succession, e.g. started from a script,
+        // don't overwrite each other's entries.
+        let path = dir.join(format!("{}.json", timestamp.format("%Y%m%dT%H%M%S%.3fZ")));
+        debug!(?path, "writing history entry");
+        write_atomic(&path, serde_json::to_vec(&entry)?)?;
+
+        prune(&dir, retention)?;
+    }
+
+    Ok(())
+}
+
+/// Remove all but the `retention` newest entries. File names are timestamps, so sorting them
+/// lexicographically sorts them chronologically. Temporary files of interrupted writes don't
+/// count as entries.
+fn prune(dir: &std::path::Path, retention: usize) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let excess = entries.len().saturating_sub(retention);
+    for path in entries.into_iter().take(excess) {
+        debug!(?path, "pruning history entry");
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
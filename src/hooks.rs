@@ -0,0 +1,62 @@
+use std::process::Stdio;
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+use crate::{config::Config, report::Report};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// Run the configured hooks after a run. Each hook gets the path of the JSON report in
+/// `SSCE_REPORT`, summary counts in further `SSCE_*` variables and the summary as JSON on
+/// stdin. Failing hooks are logged and don't fail the run.
+pub async fn run_hooks(config: &Config, report: &Report) {
+    for hook in &config.hooks {
+        if let Err(e) = run_hook(config, hook, report).await {
+            warn!("Hook {} failed: {e:?}", hook.command);
+        }
+    }
+}
+
+#[tracing::instrument(skip(config, report))]
+async fn run_hook(config: &Config, hook: &Hook, report: &Report) -> Result<()> {
+    debug!("running hook");
+    let mut command = Command::new(&hook.command);
+    command
+        .args(&hook.args)
+        .env("SSCE_REPORT", config.report_path())
+        .env("SSCE_SOURCES", report.summary.sources.to_string())
+        .env("SSCE_PACKAGES", report.summary.packages.to_string())
+        .env("SSCE_FINDINGS", report.summary.findings.to_string())
+        .stdin(Stdio::piped())
+        .kill_on_drop(true);
+    for (severity, count) in &report.summary.findings_by_severity {
+        command.env(
+            format!("SSCE_FINDINGS_{}", severity.as_str().to_uppercase()),
+            count.to_string(),
+        );
+    }
+
+    let mut child = command.spawn()?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(&serde_json::to_vec(&report.summary)?)
+            .await?;
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!("exited with {status}");
+    }
+    Ok(())
+}
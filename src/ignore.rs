@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+/// Name of the files in scanned directories that exclude paths from the scan.
+pub const IGNORE_FILE: &str = ".ssceignore";
+
+/// Syft excludes for the `.ssceignore` files in a directory tree. The files use gitignore
+/// syntax and apply to the directory they're in, so teams can keep their exclusions next to
+/// their data. Directories in `skip` aren't searched, and the walk stays on one file system.
+pub fn ssceignore_excludes(root: &Path, skip: &[PathBuf]) -> Vec<String> {
+    WalkDir::new(root)
+        .same_file_system(true)
+        .into_iter()
+        .filter_entry(|entry| !skip.iter().any(|skip| entry.path() == skip))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && entry.file_name() == IGNORE_FILE)
+        .flat_map(|entry| {
+            let dir = entry.path().parent().unwrap_or(root);
+            let relative = dir.strip_prefix(root).unwrap_or(Path::new(""));
+            match std::fs::read_to_string(entry.path()) {
+                Ok(content) => {
+                    debug!(path = ?entry.path(), "applying ignore file");
+                    excludes(relative, &content, entry.path())
+                }
+                Err(e) => {
+                    warn!("Failed to read {}: {e}", entry.path().display());
+                    Vec::new()
+                }
+            }
+        })
+        .collect()
+}
+
+/// Translate gitignore patterns of a file in `dir`, relative to the scan root, to syft
+/// excludes.
+fn excludes(dir: &Path, content: &str, file: &Path) -> Vec<String> {
+    let mut prefix = String::from(".");
+    for component in dir.components() {
+        prefix.push('/');
+        prefix.push_str(&component.as_os_str().to_string_lossy());
+    }
+
+    let mut excludes = Vec::new();
+    for line in content.lines() {
+        let pattern = line.trim_end();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            continue;
+        }
+        if pattern.starts_with('!') {
+            warn!(
+                "Negated pattern {pattern} in {} is not supported",
+                file.display()
+            );
+            continue;
+        }
+        let pattern = pattern.strip_prefix('\\').unwrap_or(pattern);
+        // Whether a pattern matches files or directories makes no difference to syft.
+        let pattern = pattern.trim_end_matches('/');
+        // Like in gitignore, patterns with a slash are relative to the directory of the file,
+        // others match at any depth below it.
+        let glob = if pattern.contains('/') {
+            format!("{prefix}/{}", pattern.trim_start_matches('/'))
+        } else {
+            format!("{prefix}/**/{pattern}")
+        };
+        excludes.push(format!("{glob}/**"));
+        excludes.push(glob);
+    }
+    excludes
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info};
+use walkdir::WalkDir;
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    schema::duration_schema,
+};
+
+/// File names of package databases, manifests and lock files.
+const MANIFESTS: &[&str] = &[
+    "status",
+    "installed",
+    "Packages",
+    "rpmdb.sqlite",
+    "Cargo.lock",
+    "package.json",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "requirements.txt",
+    "poetry.lock",
+    "Pipfile.lock",
+    "METADATA",
+    "go.mod",
+    "go.sum",
+    "Gemfile.lock",
+    "composer.lock",
+    "pom.xml",
+    "pom.properties",
+    "packages.lock.json",
+];
+
+/// Extensions of archives which contain packages.
+const ARCHIVES: &[&str] = &["jar", "war", "ear", "whl", "gemspec", "nupkg"];
+
+/// Skip syft for host directories in which no package manifest, archive or executable changed
+/// since the last scan.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct DirectoryIndexConfig {
+    /// Run syft after this time even if nothing changed, to catch changes the index doesn't
+    /// cover.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_max_age")]
+    pub max_age: Duration,
+}
+
+fn default_max_age() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
+}
+
+/// Modification times and sizes of the files relevant for the SBOM of a directory.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct FileIndex {
+    created: DateTime<Utc>,
+    files: BTreeMap<PathBuf, (i64, u64)>,
+}
+
+impl FileIndex {
+    pub fn build(config: &Config, root: &Path) -> Self {
+        // Excludes are relative to the scanned directory, like for syft.
+        let mut skip = config
+            .excludes
+            .iter()
+            .map(|exclude| root.join(exclude.strip_prefix("/").unwrap_or(exclude)))
+            .collect::<Vec<_>>();
+        skip.push(config.workspace_path());
+        skip.push(config.base_path.clone());
+
+        // Other file systems mounted below the directory, like /proc or network shares, would
+        // make building the index take as long as the scan it should save.
+        let files = WalkDir::new(root)
+            .same_file_system(true)
+            .into_iter()
+            .filter_entry(|entry| !skip.iter().any(|skip| entry.path() == skip))
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                if !relevant(entry.path()) && !executable(entry.path(), &metadata) {
+                    return None;
+                }
+                let modified = metadata
+                    .modified()
+                    .ok()?
+                    .duration_since(UNIX_EPOCH)
+                    .ok()?
+                    .as_secs() as i64;
+                Some((entry.into_path(), (modified, metadata.len())))
+            })
+            .collect();
+
+        Self {
+            created: Utc::now(),
+            files,
+        }
+    }
+
+    /// The SBOM of the last scan, if none of the indexed files changed since and the index
+    /// isn't older than the maximum age.
+    pub fn unchanged_sbom(&self, config: &Config, source: &Source) -> Option<Value> {
+        let index_config = config.directory_index.as_ref()?;
+        let previous: FileIndex =
+            serde_json::from_slice(&std::fs::read(index_path(config, source)).ok()?).ok()?;
+
+        let age = (Utc::now() - previous.created).to_std().unwrap_or_default();
+        if age >= index_config.max_age {
+            debug!("index is too old, rescanning");
+            return None;
+        }
+        if previous.files != self.files {
+            let changed = self
+                .files
+                .iter()
+                .filter(|(path, state)| previous.files.get(*path) != Some(state))
+                .count();
+            debug!(changed, "indexed files changed, rescanning");
+            return None;
+        }
+
+        let sbom = serde_json::from_slice(&std::fs::read(sbom_path(config, source)).ok()?).ok()?;
+        info!("Nothing changed in {source} since the last scan, reusing its SBOM");
+        Some(sbom)
+    }
+
+    /// Store the index along with the SBOM created for it.
+    pub fn store(&self, config: &Config, source: &Source, sbom: &Value) -> Result<()> {
+        let path = index_path(config, source);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        write_atomic(&sbom_path(config, source), serde_json::to_vec(sbom)?)?;
+        write_atomic(&path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+fn relevant(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    MANIFESTS.contains(&name.as_ref())
+        || path
+            .extension()
+            .is_some_and(|extension| ARCHIVES.contains(&extension.to_string_lossy().as_ref()))
+}
+
+/// Binaries may contain packages, e.g. go modules or the crates of auditable rust binaries.
+#[cfg(unix)]
+fn executable(_path: &Path, metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn executable(path: &Path, _metadata: &Metadata) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "exe" || extension == "dll")
+}
+
+fn index_path(config: &Config, source: &Source) -> PathBuf {
+    config
+        .base_path
+        .join(format!("index/{}.index.json", source.slug()))
+}
+
+fn sbom_path(config: &Config, source: &Source) -> PathBuf {
+    config
+        .base_path
+        .join(format!("index/{}.sbom.json", source.slug()))
+}
@@ -0,0 +1,42 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::config::default_true;
+
+/// Settings of syft's Java archive cataloger.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct JavaArchiveConfig {
+    /// Look into JARs, WARs and EARs nested in other archives, e.g. the libraries bundled in a
+    /// WAR or shaded into a fat JAR.
+    #[serde(default = "default_true")]
+    pub deep: bool,
+    /// Also look into archives which are not indexed, like tarballs, for Java archives.
+    #[serde(default)]
+    pub search_unindexed_archives: bool,
+    /// Number of parent POMs to follow when resolving the versions and licenses of packages.
+    pub max_parent_depth: Option<u32>,
+    /// Fetch missing parent POMs from Maven Central.
+    #[serde(default)]
+    pub use_network: bool,
+}
+
+impl JavaArchiveConfig {
+    /// Pass the settings to syft through its environment variables.
+    pub fn apply(&self, command: &mut Command) {
+        command
+            .env(
+                "SYFT_PACKAGE_SEARCH_INDEXED_ARCHIVES",
+                self.deep.to_string(),
+            )
+            .env(
+                "SYFT_PACKAGE_SEARCH_UNINDEXED_ARCHIVES",
+                self.search_unindexed_archives.to_string(),
+            )
+            .env("SYFT_JAVA_USE_NETWORK", self.use_network.to_string());
+        if let Some(depth) = self.max_parent_depth {
+            command.env("SYFT_JAVA_MAX_PARENT_RECURSIVE_DEPTH", depth.to_string());
+        }
+    }
+}
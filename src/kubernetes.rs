@@ -0,0 +1,139 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{config::Tags, proxy::Proxy};
+
+const POD_NAME_LABEL: &str = "io.kubernetes.pod.name";
+const POD_NAMESPACE_LABEL: &str = "io.kubernetes.pod.namespace";
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct KubernetesConfig {
+    /// Look up the workload owning each pod using the Kubernetes API.
+    #[serde(default)]
+    pub resolve_owners: bool,
+    #[serde(default = "default_api_server")]
+    pub api_server: String,
+    #[serde(default = "default_token_file")]
+    pub token_file: PathBuf,
+    #[serde(default = "default_ca_file")]
+    pub ca_file: PathBuf,
+}
+
+fn default_api_server() -> String {
+    "https://kubernetes.default.svc".into()
+}
+
+fn default_token_file() -> PathBuf {
+    "/var/run/secrets/kubernetes.io/serviceaccount/token".into()
+}
+
+fn default_ca_file() -> PathBuf {
+    "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt".into()
+}
+
+/// Resolves containers started by the kubelet to their pod, namespace and owning workload.
+pub struct Kubernetes {
+    config: KubernetesConfig,
+    client: Option<(reqwest::Client, String)>,
+    owners: HashMap<(String, String), Option<(String, String)>>,
+}
+
+impl Kubernetes {
+    pub fn new(config: &KubernetesConfig, proxy: &Proxy) -> Result<Self> {
+        let client = if config.resolve_owners {
+            let token = std::fs::read_to_string(&config.token_file)
+                .context("Failed to read kubernetes service account token")?;
+            let ca = reqwest::Certificate::from_pem(&std::fs::read(&config.ca_file)?)?;
+            let client = proxy.client_builder()?.add_root_certificate(ca).build()?;
+            Some((client, token.trim().to_owned()))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            client,
+            owners: HashMap::new(),
+        })
+    }
+
+    /// Tags for a container, based on the labels the kubelet attaches to it. Containers that
+    /// weren't started by the kubelet get no tags.
+    pub async fn tags(&mut self, labels: &HashMap<String, String>) -> Tags {
+        let (Some(pod), Some(namespace)) =
+            (labels.get(POD_NAME_LABEL), labels.get(POD_NAMESPACE_LABEL))
+        else {
+            return Tags::new();
+        };
+
+        let mut tags = Tags::from([
+            ("k8s_namespace".to_owned(), namespace.clone()),
+            ("k8s_pod".to_owned(), pod.clone()),
+        ]);
+
+        let key = (namespace.clone(), pod.clone());
+        if !self.owners.contains_key(&key) {
+            let owner = match self.resolve_owner(namespace, pod).await {
+                Ok(owner) => owner,
+                Err(e) => {
+                    warn!("Failed to resolve owner of pod {namespace}/{pod}: {e:?}");
+                    None
+                }
+            };
+            self.owners.insert(key.clone(), owner);
+        }
+        if let Some((kind, name)) = &self.owners[&key] {
+            tags.insert("k8s_owner_kind".into(), kind.clone());
+            tags.insert("k8s_owner".into(), name.clone());
+        }
+
+        tags
+    }
+
+    /// Follow the controller owner references of a pod up to the top level workload, e.g. from
+    /// pod to replica set to deployment.
+    #[tracing::instrument(skip(self))]
+    async fn resolve_owner(&self, namespace: &str, pod: &str) -> Result<Option<(String, String)>> {
+        let Some((client, token)) = &self.client else {
+            return Ok(None);
+        };
+
+        let mut path = format!("/api/v1/namespaces/{namespace}/pods/{pod}");
+        let mut owner = None;
+        loop {
+            debug!(path, "fetching kubernetes object");
+            let object: Value = client
+                .get(format!("{}{path}", self.config.api_server))
+                .bearer_auth(token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let Some(controller) = object["metadata"]["ownerReferences"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|reference| reference["controller"].as_bool().unwrap_or_default())
+            else {
+                return Ok(owner);
+            };
+            let kind = controller["kind"].as_str().unwrap_or_default().to_owned();
+            let name = controller["name"].as_str().unwrap_or_default().to_owned();
+
+            path = match kind.as_str() {
+                "ReplicaSet" => format!("/apis/apps/v1/namespaces/{namespace}/replicasets/{name}"),
+                "Job" => format!("/apis/batch/v1/namespaces/{namespace}/jobs/{name}"),
+                _ => return Ok(Some((kind, name))),
+            };
+            owner = Some((kind, name));
+        }
+    }
+}
@@ -1,5 +1,92 @@
+//! SBOM generation, vulnerability scanning and export of the results as metrics. The `ssce`
+//! binary is a thin CLI around this library, other services can embed the same pipeline:
+//!
+//! ```no_run
+//! use software_supply_chain_exporter::{config::Config, run::run_scan, schedule::Scheduler};
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let config = Config::load("/etc/ssce/config.yaml".as_ref(), None)?;
+//! let report = run_scan(&config, &mut Scheduler::default()).await?;
+//! println!("{} findings", report.summary.findings);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The stages are available on their own as well: `run::discover_sources`,
+//! `sbom::create_sboms`, `scan::scan` and `metrics::encode_metrics`.
+
+pub mod acceptance;
+pub mod admission;
+#[cfg(feature = "notifiers")]
+pub mod alertmanager;
+pub mod applications;
+pub mod attestation;
+#[cfg(feature = "notifiers")]
+pub mod chat;
+pub mod checkpoint;
+pub mod ci;
+pub mod compare;
 pub mod config;
+pub mod cve_details;
+pub mod cvss;
+pub mod dependency_check;
+pub mod digest;
+pub mod discovery;
+pub mod disk_image;
+pub mod distroless;
 pub mod docker;
+pub mod email;
+pub mod error;
+pub mod exploits;
+pub mod finding;
+pub mod firmware;
+pub mod fs;
+pub mod github;
+pub mod gitlab;
+pub mod groups;
+pub mod grype_db;
+pub mod hashes;
+pub mod history;
+pub mod hooks;
+pub mod ignore;
+pub mod index;
+pub mod java;
+#[cfg(feature = "kubernetes")]
+pub mod kubernetes;
+pub mod limits;
+pub mod load;
+pub mod lxd;
+pub mod macos;
+pub mod malicious;
 pub mod metrics;
+pub mod nix;
+pub mod notify;
+pub mod origins;
+pub mod policy;
+pub mod preflight;
+pub mod progress;
+pub mod proxy;
+pub mod query;
+pub mod reachability;
+pub mod recording;
+pub mod redeploy;
+pub mod report;
+pub mod run;
+pub mod runtimes;
 pub mod sbom;
 pub mod scan;
+pub mod schedule;
+pub mod schema;
+pub mod secret;
+pub mod severity;
+pub mod shared_cache;
+pub mod sink;
+pub mod smtp;
+pub mod systemd;
+#[cfg(feature = "notifiers")]
+pub mod tickets;
+pub mod validate;
+pub mod vdr;
+pub mod webhook;
+pub mod windows;
+pub mod workspace;
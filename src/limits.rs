@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::config::Source;
+
+/// Prefix of the SPDX annotation comment which records an exceeded limit in the SBOM.
+pub const LIMIT_ANNOTATION: &str = "ssce limit exceeded: ";
+
+/// Guardrails against SBOMs that are too large to be exported, e.g. when a huge file share is
+/// mounted into a container.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct SbomLimits {
+    /// Maximum number of packages per SBOM.
+    pub max_packages: Option<usize>,
+    /// Maximum size of an SBOM as JSON in bytes.
+    pub max_bytes: Option<usize>,
+    /// What happens to SBOMs exceeding a limit.
+    #[serde(default)]
+    pub on_exceeded: LimitAction,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitAction {
+    /// Drop the files and packages exceeding the limit. The SBOM is annotated, so the
+    /// `sbom_limit_exceeded` metric flags it.
+    #[default]
+    Truncate,
+    /// Don't scan the source at all.
+    Skip,
+}
+
+/// Apply the limits to an SBOM. Returns `None` if the source is skipped.
+pub fn apply_limits(limits: &SbomLimits, source: &Source, mut sbom: Value) -> Option<Value> {
+    let mut exceeded = Vec::new();
+
+    if let Some(max_packages) = limits.max_packages {
+        let packages = package_count(&sbom);
+        if packages > max_packages {
+            warn!(
+                "SBOM of {source} has {packages} packages, more than the limit of {max_packages}"
+            );
+            exceeded.push("packages");
+            if limits.on_exceeded == LimitAction::Skip {
+                return None;
+            }
+            truncate_packages(&mut sbom, max_packages);
+        }
+    }
+
+    if let Some(max_bytes) = limits.max_bytes {
+        let bytes = json_size(&sbom);
+        if bytes > max_bytes {
+            warn!("SBOM of {source} has {bytes} bytes, more than the limit of {max_bytes}");
+            exceeded.push("bytes");
+            if limits.on_exceeded == LimitAction::Skip {
+                return None;
+            }
+            // File entries make up most of the size of SBOMs of large directory trees.
+            if let Some(object) = sbom.as_object_mut() {
+                object.remove("files");
+            }
+            remove_dangling_relationships(&mut sbom);
+            let bytes = json_size(&sbom);
+            if bytes > max_bytes {
+                let packages = package_count(&sbom);
+                truncate_packages(&mut sbom, packages * max_bytes / bytes);
+            }
+        }
+    }
+
+    for limit in exceeded {
+        annotate(&mut sbom, format!("{LIMIT_ANNOTATION}{limit}"));
+    }
+    Some(sbom)
+}
+
+fn json_size(sbom: &Value) -> usize {
+    serde_json::to_vec(sbom)
+        .map(|json| json.len())
+        .unwrap_or_default()
+}
+
+fn package_count(sbom: &Value) -> usize {
+    sbom.get("packages")
+        .and_then(Value::as_array)
+        .map(Vec::len)
+        .unwrap_or_default()
+}
+
+fn truncate_packages(sbom: &mut Value, max_packages: usize) {
+    if let Some(Value::Array(packages)) = sbom.get_mut("packages") {
+        packages.truncate(max_packages);
+    }
+    remove_dangling_relationships(sbom);
+}
+
+/// Remove relationships to packages and files which are no longer part of the SBOM.
+fn remove_dangling_relationships(sbom: &mut Value) {
+    let mut ids = HashSet::new();
+    if let Some(id) = sbom.get("SPDXID").and_then(Value::as_str) {
+        ids.insert(id.to_owned());
+    }
+    for element in ["packages", "files"] {
+        for entry in sbom
+            .get(element)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(id) = entry.get("SPDXID").and_then(Value::as_str) {
+                ids.insert(id.to_owned());
+            }
+        }
+    }
+
+    if let Some(Value::Array(relationships)) = sbom.get_mut("relationships") {
+        relationships.retain(|relationship| {
+            ["spdxElementId", "relatedSpdxElement"].iter().all(|key| {
+                relationship
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .is_none_or(|id| ids.contains(id))
+            })
+        });
+    }
+}
+
+/// Record something the exporter did to the SBOM as SPDX annotation. The annotation is dated
+/// like the SBOM, so annotating the same SBOM again, e.g. when it's read from the cache, gives
+/// the same result.
+pub(crate) fn annotate(sbom: &mut Value, comment: String) {
+    let Some(object) = sbom.as_object_mut() else {
+        return;
+    };
+    let date = object
+        .get("creationInfo")
+        .and_then(|info| info["created"].as_str())
+        .map_or_else(
+            || Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            str::to_owned,
+        );
+    let annotations = object.entry("annotations").or_insert_with(|| json!([]));
+    if let Some(annotations) = annotations.as_array_mut() {
+        annotations.push(json!({
+            "annotator": "Tool: ssce",
+            "annotationDate": date,
+            "annotationType": "OTHER",
+            "comment": comment,
+        }));
+    }
+}
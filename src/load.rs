@@ -0,0 +1,147 @@
+//! Guard against adding scans to an already saturated host. Before the SBOM of each source is
+//! generated, the load average and the pressure stall information of Linux are checked, and
+//! the source is deferred to the next run if the host doesn't calm down in time.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{Config, Source},
+    schema::duration_schema,
+};
+
+/// How often the load is checked again while waiting for it to drop.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Sources deferred since the start of the process, exported as `scans_deferred_total`.
+static DEFERRED: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct LoadGuardConfig {
+    /// Maximum load average of the last minute per CPU, e.g. `1.5`.
+    pub max_load_per_cpu: Option<f64>,
+    /// Maximum share of the last 10 seconds in percent in which some tasks were stalled on
+    /// the CPU, from `/proc/pressure/cpu`.
+    pub max_cpu_pressure: Option<f64>,
+    /// Maximum share of the last 10 seconds in percent in which some tasks were stalled on
+    /// IO, from `/proc/pressure/io`.
+    pub max_io_pressure: Option<f64>,
+    /// How long to wait in total during a run for the load to drop. Once that time is used up,
+    /// sources are deferred to the next run while the host is saturated. Without it, sources
+    /// are deferred right away.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default)]
+    pub max_wait: Duration,
+}
+
+/// Decides whether SBOMs may be generated, and keeps the sources deferred in this run. Without
+/// a configured guard, every source may be scanned.
+#[derive(Debug, Default)]
+pub struct LoadGuard {
+    config: Option<LoadGuardConfig>,
+    /// Time waited for the load to drop so far in this run, shared by all sources so a run
+    /// doesn't wait `max_wait` for each of them.
+    waited: Mutex<Duration>,
+    deferred: Mutex<Vec<Source>>,
+}
+
+impl LoadGuard {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.load_guard.clone(),
+            waited: Mutex::default(),
+            deferred: Mutex::default(),
+        }
+    }
+
+    /// Wait until the host isn't saturated. Returns false if the source is deferred instead.
+    pub async fn admit(&self, source: &Source) -> bool {
+        let Some(config) = &self.config else {
+            return true;
+        };
+        loop {
+            let Some(reason) = saturation(config) else {
+                return true;
+            };
+            let waited = *self.waited.lock().unwrap();
+            if waited >= config.max_wait {
+                warn!(%source, "Deferring the scan to the next run, {reason}");
+                DEFERRED.fetch_add(1, Relaxed);
+                self.deferred.lock().unwrap().push(source.clone());
+                return false;
+            }
+            info!(%source, "Waiting for the load to drop, {reason}");
+            let poll = POLL_INTERVAL.min(config.max_wait - waited);
+            tokio::time::sleep(poll).await;
+            *self.waited.lock().unwrap() += poll;
+        }
+    }
+
+    /// Sources deferred in this run.
+    pub fn deferred(&self) -> Vec<Source> {
+        self.deferred.lock().unwrap().clone()
+    }
+}
+
+/// Number of sources deferred since the start of the process.
+pub fn scans_deferred_total() -> u64 {
+    DEFERRED.load(Relaxed)
+}
+
+/// Why the host is saturated, if it is. Values which can't be read, e.g. pressure stall
+/// information on kernels without it or on other systems, don't count as saturated.
+fn saturation(config: &LoadGuardConfig) -> Option<String> {
+    if let (Some(max), Some(load)) = (config.max_load_per_cpu, load_per_cpu()) {
+        if load > max {
+            return Some(format!("load per CPU is {load:.2}, at most {max} allowed"));
+        }
+    }
+    for (kind, max) in [
+        ("cpu", config.max_cpu_pressure),
+        ("io", config.max_io_pressure),
+    ] {
+        if let (Some(max), Some(pressure)) = (max, pressure(kind)) {
+            if pressure > max {
+                return Some(format!(
+                    "{kind} pressure is {pressure:.1}%, at most {max}% allowed"
+                ));
+            }
+        }
+    }
+    None
+}
+
+/// Load average of the last minute divided by the number of CPUs.
+fn load_per_cpu() -> Option<f64> {
+    let loadavg = std::fs::read_to_string("/proc/loadavg")
+        .inspect_err(|e| debug!("Failed to read the load average: {e}"))
+        .ok()?;
+    let load: f64 = loadavg.split_whitespace().next()?.parse().ok()?;
+    let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+    Some(load / cpus as f64)
+}
+
+/// The `some avg10` value of the pressure stall information, e.g. of
+/// `some avg10=1.53 avg60=0.87 avg300=0.34 total=1234567`.
+fn pressure(kind: &str) -> Option<f64> {
+    let pressure = std::fs::read_to_string(format!("/proc/pressure/{kind}"))
+        .inspect_err(|e| debug!("Failed to read the {kind} pressure: {e}"))
+        .ok()?;
+    pressure
+        .lines()
+        .find_map(|line| line.strip_prefix("some "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
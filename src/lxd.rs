@@ -0,0 +1,102 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+#[cfg(unix)]
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+use tracing::debug;
+
+use crate::config::{merge_tags, Config, Source, Tags};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct LxdConfig {
+    /// Path to the LXD REST API socket, defaults to the snap or the distribution package location.
+    pub socket: Option<PathBuf>,
+}
+
+const DEFAULT_SOCKETS: [&str; 2] = [
+    "/var/snap/lxd/common/lxd/unix.socket",
+    "/var/lib/lxd/unix.socket",
+];
+
+/// Enumerate running LXD containers and return their root file systems as directory sources.
+/// The root file system is accessed through the init process, which works for all storage
+/// backends.
+pub async fn get_lxd_instances(config: &Config) -> Result<HashMap<Source, Tags>> {
+    let Some(lxd) = &config.lxd else {
+        return Ok(HashMap::new());
+    };
+
+    let socket = match &lxd.socket {
+        Some(socket) => socket.clone(),
+        None => DEFAULT_SOCKETS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+            .context("No LXD socket found")?,
+    };
+
+    let response = get(&socket, "/1.0/instances?recursion=2").await?;
+    if response["type"] == "error" {
+        anyhow::bail!("LXD returned an error: {}", response["error"]);
+    }
+
+    let mut instances = HashMap::new();
+    for instance in response["metadata"].as_array().into_iter().flatten() {
+        let name = instance["name"].as_str().unwrap_or_default();
+        if instance["type"].as_str() != Some("container") {
+            debug!(name, "skipping LXD instance which isn't a container");
+            continue;
+        }
+        let Some(pid) = instance["state"]["pid"].as_u64().filter(|pid| *pid > 0) else {
+            debug!(name, "skipping LXD container which isn't running");
+            continue;
+        };
+
+        let mut tags = config.tags.clone();
+        merge_tags(
+            &mut tags,
+            Tags::from([("lxd_instance".to_owned(), name.to_owned())]),
+        );
+        instances.insert(
+            Source::HostDirectory {
+                path: PathBuf::from(format!("/proc/{pid}/root")),
+            },
+            tags,
+        );
+    }
+
+    Ok(instances)
+}
+
+/// Minimal HTTP/1.0 GET over a unix socket. Using HTTP/1.0 means the response isn't chunked and
+/// the connection is closed after the body.
+#[cfg(unix)]
+#[tracing::instrument]
+async fn get(socket: &PathBuf, path: &str) -> Result<Value> {
+    let mut stream = UnixStream::connect(socket).await?;
+    stream
+        .write_all(format!("GET {path} HTTP/1.0\r\nHost: lxd\r\n\r\n").as_bytes())
+        .await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+
+    let body_start = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .context("Malformed response from LXD")?
+        + 4;
+    Ok(serde_json::from_slice(&response[body_start..])?)
+}
+
+#[cfg(not(unix))]
+async fn get(_socket: &PathBuf, _path: &str) -> Result<Value> {
+    anyhow::bail!("LXD is only supported on Linux")
+}
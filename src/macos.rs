@@ -0,0 +1,154 @@
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    fs::{create_parent, write_atomic},
+    sbom::{spdx_package, Sbom},
+    scan::Scan,
+};
+
+const APPLICATIONS: &str = "/Applications";
+
+/// SPDX package entries for Homebrew packages and application bundles, which syft doesn't
+/// catalog.
+#[tracing::instrument]
+pub async fn macos_packages() -> Result<Vec<Value>> {
+    let mut packages = application_bundles().await?;
+    match homebrew_packages().await {
+        Ok(homebrew) => packages.extend(homebrew),
+        Err(e) => warn!("Failed to list homebrew packages: {e:?}"),
+    }
+    Ok(packages)
+}
+
+async fn application_bundles() -> Result<Vec<Value>> {
+    let mut packages = Vec::new();
+    for entry in std::fs::read_dir(APPLICATIONS)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "app") {
+            continue;
+        }
+        let info = match read_plist(&path.join("Contents/Info.plist")).await {
+            Ok(info) => info,
+            Err(e) => {
+                debug!(
+                    ?path,
+                    "skipping application without readable Info.plist: {e:?}"
+                );
+                continue;
+            }
+        };
+
+        let name = info["CFBundleName"]
+            .as_str()
+            .or_else(|| path.file_stem()?.to_str())
+            .unwrap_or_default();
+        let version = info["CFBundleShortVersionString"]
+            .as_str()
+            .unwrap_or_default();
+        let id = info["CFBundleIdentifier"].as_str().unwrap_or(name);
+        packages.push(spdx_package(
+            name,
+            version,
+            &format!("pkg:generic/{id}@{version}"),
+        ));
+    }
+    Ok(packages)
+}
+
+/// Read XML or binary property lists by letting plutil convert them to JSON.
+async fn read_plist(path: &Path) -> Result<Value> {
+    let output = Command::new("plutil")
+        .arg("-convert")
+        .arg("json")
+        .arg("-o")
+        .arg("-")
+        .arg(path)
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("plutil exited with {}", output.status);
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+async fn homebrew_packages() -> Result<Vec<Value>> {
+    debug!("listing homebrew packages");
+    let output = Command::new("brew")
+        .arg("list")
+        .arg("--versions") // One `name version...` line per package
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("brew exited with {}", output.status);
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            Some(parts.map(move |version| {
+                let purl = format!("pkg:brew/{name}@{version}");
+                spdx_package(name, version, &purl)
+            }))
+        })
+        .flatten()
+        .collect())
+}
+
+/// Write a property list with package and vulnerability counts per source, for consumption by
+/// MDM tooling on developer laptops.
+pub fn write_plist_summary(
+    config: &Config,
+    sboms: &HashMap<Source, Value>,
+    scans: &HashMap<Source, Scan>,
+) -> Result<()> {
+    let Some(path) = &config.plist_summary else {
+        return Ok(());
+    };
+
+    let mut plist = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+        "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n",
+    ));
+    for (source, sbom) in sboms {
+        let packages = serde_json::from_value::<Sbom>(sbom.clone())
+            .map(|sbom| sbom.packages.len())
+            .unwrap_or_default();
+        let scan = scans.get(source);
+        let vulnerabilities = scan.map(|scan| scan.matches.len()).unwrap_or_default();
+        let highest_severity = scan.map(Scan::highest_severity).unwrap_or_default().rank();
+
+        writeln!(plist, "<key>{}</key>\n<dict>", escape(&source.to_string()))?;
+        writeln!(plist, "<key>packages</key><integer>{packages}</integer>")?;
+        writeln!(
+            plist,
+            "<key>vulnerabilities</key><integer>{vulnerabilities}</integer>"
+        )?;
+        writeln!(
+            plist,
+            "<key>highest_severity</key><integer>{highest_severity}</integer>"
+        )?;
+        writeln!(plist, "</dict>")?;
+    }
+    plist.push_str("</dict>\n</plist>\n");
+
+    create_parent(path)?;
+    write_atomic(path, plist)?;
+    Ok(())
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,312 @@
+//! Checks of language packages against known malicious packages and names of internal
+//! packages. Unlike vulnerabilities, a single hit means the source likely runs attacker code,
+//! so hits are exported as their own metric family to alert on.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::{
+    config::Source,
+    sbom::{Sbom, SbomEntry},
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct MaliciousPackagesConfig {
+    /// Directory of OSV entries of malicious packages, searched recursively, e.g. the
+    /// `osv/malicious` directory of a checkout of github.com/ossf/malicious-packages.
+    pub osv_path: Option<PathBuf>,
+    /// Names of internal packages by package URL type, e.g. `pypi` or `cargo`. Public packages
+    /// of the same type with a similar but different name are flagged as possible typosquats.
+    #[serde(default)]
+    pub internal_packages: BTreeMap<String, Vec<String>>,
+    /// Maximum number of inserted, removed, replaced or swapped characters for a name to be
+    /// similar to the name of an internal package.
+    #[serde(default = "default_max_distance")]
+    pub max_distance: usize,
+}
+
+fn default_max_distance() -> usize {
+    1
+}
+
+/// OSV ecosystems by package URL type, for the language packages that are checked.
+const ECOSYSTEMS: [(&str, &str); 10] = [
+    ("npm", "npm"),
+    ("pypi", "PyPI"),
+    ("cargo", "crates.io"),
+    ("gem", "RubyGems"),
+    ("golang", "Go"),
+    ("maven", "Maven"),
+    ("nuget", "NuGet"),
+    ("composer", "Packagist"),
+    ("hex", "Hex"),
+    ("pub", "Pub"),
+];
+
+#[derive(Deserialize)]
+struct OsvEntry {
+    id: String,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+}
+
+#[derive(Deserialize)]
+struct OsvAffected {
+    package: OsvPackage,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+/// Advisories of malicious packages by package URL type and normalized name.
+#[derive(Debug, Default)]
+pub struct MaliciousPackages(HashMap<(String, String), Vec<Advisory>>);
+
+#[derive(Debug)]
+struct Advisory {
+    id: String,
+    /// Affected versions. Most malicious packages affect all versions, which OSV lists as a
+    /// range starting at 0 without any versions.
+    versions: Vec<String>,
+}
+
+impl MaliciousPackages {
+    /// Ids of the advisories of the package version.
+    fn advisories(&self, purl_type: &str, name: &str, version: &str) -> Vec<&str> {
+        self.0
+            .get(&(purl_type.to_owned(), normalize(purl_type, name)))
+            .into_iter()
+            .flatten()
+            .filter(|advisory| {
+                advisory.versions.is_empty() || advisory.versions.iter().any(|v| v == version)
+            })
+            .map(|advisory| advisory.id.as_str())
+            .collect()
+    }
+}
+
+/// Entries loaded before, with their directory and its modification time.
+type Loaded = (PathBuf, Option<SystemTime>, Arc<MaliciousPackages>);
+
+/// The entries loaded by an earlier run of the daemon, as loading them takes a while.
+static LOADED: Mutex<Option<Loaded>> = Mutex::new(None);
+
+/// Load the OSV entries of the configured directory, skipping files which aren't OSV entries.
+/// The entries of an earlier run are reused until the modification time of the directory
+/// changes, e.g. when the checkout is updated.
+pub fn load_malicious_packages(config: &MaliciousPackagesConfig) -> Arc<MaliciousPackages> {
+    let Some(path) = &config.osv_path else {
+        return Arc::default();
+    };
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let mut loaded = LOADED.lock().unwrap();
+    if let Some((_, _, packages)) = loaded
+        .as_ref()
+        .filter(|(loaded, loaded_modified, _)| loaded == path && *loaded_modified == modified)
+    {
+        return packages.clone();
+    }
+    let packages = Arc::new(read_osv_entries(path));
+    *loaded = Some((path.clone(), modified, packages.clone()));
+    packages
+}
+
+fn read_osv_entries(path: &Path) -> MaliciousPackages {
+    let mut packages = MaliciousPackages::default();
+    let files = WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"));
+    for file in files {
+        let entry: OsvEntry = match std::fs::read(file.path())
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_slice(&content)?))
+        {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Skipping {}: {e:?}", file.path().display());
+                continue;
+            }
+        };
+        for affected in entry.affected {
+            let Some(purl_type) = ECOSYSTEMS
+                .iter()
+                .find(|(_, ecosystem)| *ecosystem == affected.package.ecosystem)
+                .map(|(purl_type, _)| *purl_type)
+            else {
+                continue;
+            };
+            // syft names Maven packages by their artifact id only.
+            let name = match affected.package.ecosystem.as_str() {
+                "Maven" => affected
+                    .package
+                    .name
+                    .rsplit(':')
+                    .next()
+                    .unwrap_or_default()
+                    .to_owned(),
+                _ => affected.package.name,
+            };
+            packages
+                .0
+                .entry((purl_type.to_owned(), normalize(purl_type, &name)))
+                .or_default()
+                .push(Advisory {
+                    id: entry.id.clone(),
+                    versions: affected.versions,
+                });
+        }
+    }
+    debug!(packages = packages.0.len(), "loaded malicious packages");
+    packages
+}
+
+/// A package which is known to be malicious or possibly typosquats an internal package.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SuspiciousPackage {
+    pub name: String,
+    pub version: String,
+    pub reason: SuspicionReason,
+    /// Id of the OSV entry of a malicious package, e.g. `MAL-2024-1234`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advisory: Option<String>,
+    /// The internal package a possible typosquat is named like.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similar_to: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SuspicionReason {
+    Malicious,
+    Typosquat,
+}
+
+impl SuspicionReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Malicious => "malicious",
+            Self::Typosquat => "typosquat",
+        }
+    }
+}
+
+/// The name as the package manager compares names: pip ignores case and treats `_`, `.` and
+/// `-` the same, cargo does so for `_` and `-`. Go modules and Maven artifacts are case
+/// sensitive, the other registries aren't.
+fn normalize(purl_type: &str, name: &str) -> String {
+    match purl_type {
+        "pypi" => name.to_lowercase().replace(['_', '.'], "-"),
+        "cargo" => name.to_lowercase().replace('_', "-"),
+        "golang" | "maven" => name.to_owned(),
+        _ => name.to_lowercase(),
+    }
+}
+
+/// Package URL type of language packages which are checked.
+fn checked_type(package: &SbomEntry) -> Option<&str> {
+    let purl_type = package.purl_type()?;
+    ECOSYSTEMS
+        .iter()
+        .any(|(known, _)| *known == purl_type)
+        .then_some(purl_type)
+}
+
+/// Edit distance counting swapped adjacent characters as one edit, as typosquats often swap
+/// them.
+fn distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// Language packages of the SBOM which are known to be malicious or are named like an internal
+/// package without being it, once per package and reason.
+pub fn suspicious_packages(
+    config: &MaliciousPackagesConfig,
+    malicious: &MaliciousPackages,
+    source: &Source,
+    sbom: &Sbom,
+) -> Vec<SuspiciousPackage> {
+    let mut suspicious = BTreeSet::new();
+    for package in &sbom.packages {
+        let Some(purl_type) = checked_type(package) else {
+            continue;
+        };
+        for advisory in malicious.advisories(purl_type, &package.name, &package.versionInfo) {
+            suspicious.insert(SuspiciousPackage {
+                name: package.name.clone(),
+                version: package.versionInfo.clone(),
+                reason: SuspicionReason::Malicious,
+                advisory: Some(advisory.to_owned()),
+                similar_to: None,
+            });
+        }
+        let internal = config
+            .internal_packages
+            .get(purl_type)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let name = normalize(purl_type, &package.name);
+        if internal
+            .iter()
+            .any(|internal| normalize(purl_type, internal) == name)
+        {
+            continue;
+        }
+        if let Some(similar_to) = internal.iter().find(|internal| {
+            (1..=config.max_distance).contains(&distance(&name, &normalize(purl_type, internal)))
+        }) {
+            suspicious.insert(SuspiciousPackage {
+                name: package.name.clone(),
+                version: package.versionInfo.clone(),
+                reason: SuspicionReason::Typosquat,
+                advisory: None,
+                similar_to: Some(similar_to.clone()),
+            });
+        }
+    }
+    for package in &suspicious {
+        warn!(
+            %source,
+            package = package.name,
+            version = package.version,
+            reason = package.reason.as_str(),
+            "suspicious package"
+        );
+    }
+    suspicious.into_iter().collect()
+}
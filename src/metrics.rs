@@ -1,35 +1,202 @@
-use std::{collections::HashMap, fs::File, io::Write};
+use std::{collections::HashMap, sync::atomic::AtomicU64};
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{NaiveTime, Utc};
 use prometheus_client::{
-    encoding::{text::encode, EncodeLabelSet},
-    metrics::{counter::Counter, family::Family},
+    encoding::{text::encode, EncodeLabel, EncodeLabelSet, LabelSetEncoder},
+    metrics::{counter::Counter, family::Family, gauge::Gauge},
     registry::Registry,
 };
-use rust_decimal::Decimal;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::{
-    config::{Config, Source},
-    sbom::Sbom,
-    scan::{Cvss, CvssMetrics, Scan},
+    acceptance::{is_accepted, load_acceptances},
+    compare::Agreement,
+    config::{Config, Source, Tags},
+    cvss::{self, environmental_score, CvssEnvironment},
+    distroless::{incomplete_reason, packages_by_type},
+    error::errors_total,
+    groups::rollups,
+    load::scans_deferred_total,
+    origins::package_origins,
+    reachability::likely_used,
+    redeploy::FindingKey,
+    report::Report,
+    runtimes::detect_runtimes,
+    sbom::{cache_stats, Sbom},
+    scan::{Cvss, CvssMetrics, FixState, Scan},
+    severity::Severity,
 };
 
-pub fn export_metrics(
+/// Metrics of the CVSS vector exported as labels, see `cvss_vector_labels`.
+const CVSS_VECTOR_LABELS: [(&str, &str); 5] = [
+    ("AV", "cvss_attack_vector"),
+    ("AC", "cvss_attack_complexity"),
+    ("PR", "cvss_privileges_required"),
+    ("UI", "cvss_user_interaction"),
+    ("S", "cvss_scope"),
+];
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnversionedPackages {
+    /// Leave them out of the metrics.
+    #[default]
+    Skip,
+    /// Export them in the `sbom` metric family with the version `unknown`.
+    Unknown,
+    /// Export them in the `unversioned_packages` metric family.
+    Separate,
+}
+
+/// Encode the results of a run as metrics in the OpenMetrics text format.
+pub fn encode_metrics(
     config: &Config,
+    sources: &HashMap<Source, Tags>,
     sboms: HashMap<Source, Value>,
     scans: HashMap<Source, Scan>,
-) -> Result<()> {
+    fixed_in_newer_tag: &HashMap<FindingKey, String>,
+    agreements: &HashMap<Source, Agreement>,
+    report: &Report,
+) -> Result<String> {
     let mut registry = <Registry>::default();
     let syft_metrics = Family::<SbomLabels, Counter>::default();
-    let grype_metrics = Family::<ScanLabels, Counter>::default();
+    let package_occurrences = Family::<SbomLabels, Gauge>::default();
+    let unversioned = Family::<UnversionedLabels, Gauge>::default();
+    let grype_metrics = Family::<ExtraLabels<ScanLabels>, Counter>::default();
+    let highest_severity = Family::<SourceLabels, Gauge>::default();
+    let fixable_critical = Family::<SourceLabels, Gauge>::default();
+    let by_ecosystem = Family::<EcosystemLabels, Gauge>::default();
+    let package_count = Family::<SourceLabels, Gauge>::default();
+    let sbom_method = Family::<MethodLabels, Gauge>::default();
+    let unexpected_origin = Family::<OriginLabels, Gauge>::default();
+    let download_hosts = Family::<DownloadHostLabels, Gauge>::default();
+    let fix_age = Family::<FindingLabels, Gauge>::default();
+    let cvss_base_score = Family::<FindingLabels, Gauge<f64, AtomicU64>>::default();
+    let cvss_environmental_score = Family::<EnvironmentalLabels, Gauge<f64, AtomicU64>>::default();
+    let newer_tag = Family::<NewerTagLabels, Gauge>::default();
+    let limit_exceeded = Family::<LimitLabels, Gauge>::default();
+    let runtime_info = Family::<RuntimeLabels, Gauge>::default();
+    let packages_cataloged = Family::<PackageTypeLabels, Gauge>::default();
+    let incomplete_sbom = Family::<IncompleteLabels, Gauge>::default();
+    let scanner_agreement = Family::<AgreementLabels, Gauge>::default();
 
-    registry.register("sbom", "", syft_metrics.clone());
+    if config.sbom_metrics {
+        registry.register("sbom", "", syft_metrics.clone());
+        registry.register(
+            "sbom_package_occurrences",
+            "Number of times a package is listed in the SBOM, e.g. once per location it was found at",
+            package_occurrences.clone(),
+        );
+        if config.unversioned_packages == UnversionedPackages::Separate {
+            registry.register(
+                "unversioned_packages",
+                "Packages in the SBOM without a version",
+                unversioned.clone(),
+            );
+        }
+    }
+    registry.register(
+        "sbom_packages",
+        "Number of packages in the SBOM",
+        package_count.clone(),
+    );
+    registry.register(
+        "sbom_source",
+        "How the SBOM was obtained: from an attestation of the image, generated by syft, from \
+         the cache or provided by a vendor",
+        sbom_method.clone(),
+    );
+    if config
+        .package_origins
+        .as_ref()
+        .is_some_and(|origins| origins.metric)
+    {
+        registry.register(
+            "package_unexpected_origin",
+            "Packages downloaded from none of package_origins.expected_hosts, with their supplier, \
+             originator and download host",
+            unexpected_origin.clone(),
+        );
+        registry.register(
+            "packages_by_download_host",
+            "Number of packages by the host they were downloaded from",
+            download_hosts.clone(),
+        );
+    }
+    registry.register(
+        "sbom_limit_exceeded",
+        "SBOMs which exceeded a limit of sbom_limits and were truncated",
+        limit_exceeded.clone(),
+    );
+    registry.register(
+        "packages_cataloged",
+        "Number of packages in the SBOM by package URL type",
+        packages_cataloged.clone(),
+    );
+    registry.register(
+        "possibly_incomplete_sbom",
+        "Images without a package database, like distroless or scratch images, or with an empty SBOM",
+        incomplete_sbom.clone(),
+    );
+    registry.register(
+        "runtime_info",
+        "Language runtimes with their release cycle and end of life date",
+        runtime_info.clone(),
+    );
     registry.register("vulnerability_scans", "", grype_metrics.clone());
+    registry.register(
+        "source_highest_severity",
+        "Highest severity found (0 none/unknown, 1 negligible, 2 low, 3 medium, 4 high, 5 critical)",
+        highest_severity.clone(),
+    );
+    registry.register(
+        "source_fixable_critical_count",
+        "Number of critical vulnerabilities with an available fix",
+        fixable_critical.clone(),
+    );
+    registry.register(
+        "vulnerabilities_by_ecosystem",
+        "Number of vulnerabilities per package ecosystem and severity",
+        by_ecosystem.clone(),
+    );
 
-    std::fs::create_dir_all(config.metrics_path().parent().unwrap())?;
-    let mut output = File::create(config.metrics_path())?;
+    registry.register(
+        "vulnerability_cvss_base_score",
+        "CVSS base score of a vulnerability",
+        cvss_base_score.clone(),
+    );
+    if !config.cvss_environments.is_empty() {
+        registry.register(
+            "vulnerability_cvss_environmental_score",
+            "CVSS score of a vulnerability adjusted by the environmental metrics of the source",
+            cvss_environmental_score.clone(),
+        );
+    }
+    registry.register(
+        "vulnerability_fix_available_days",
+        "Days since a fixed version was released that is not deployed yet",
+        fix_age.clone(),
+    );
+
+    if config.fixed_in_newer_tag.is_some() {
+        registry.register(
+            "vulnerability_fixed_in_newer_tag",
+            "Vulnerabilities which are fixed in a newer tag of the image running on this host",
+            newer_tag.clone(),
+        );
+    }
+
+    if config.scanner_comparison.is_some() {
+        registry.register(
+            "scanner_agreement",
+            "Number of findings found by both grype and trivy, or only by one of them",
+            scanner_agreement.clone(),
+        );
+    }
 
     let mut buffer = String::new();
 
@@ -47,24 +214,252 @@ pub fn export_metrics(
 
     for (source, sbom) in sboms {
         let sbom: Sbom = serde_json::from_value(sbom)?;
+        let source_labels = SourceLabels::new(&source, sources.get(&source));
+        package_count
+            .get_or_create(&source_labels)
+            .set(sbom.packages.len() as i64);
+        sbom_method
+            .get_or_create(&MethodLabels {
+                method: sbom.method().unwrap_or("unknown").to_owned(),
+                source: source_labels.clone(),
+            })
+            .set(1);
+        if let Some(origins) = config
+            .package_origins
+            .as_ref()
+            .filter(|origins| origins.metric)
+        {
+            // Series per package only for the flagged ones, the full list is in the report.
+            for origin in package_origins(origins, &sbom) {
+                let Some(host) = origin.download_host().map(str::to_owned) else {
+                    continue;
+                };
+                download_hosts
+                    .get_or_create(&DownloadHostLabels {
+                        download_host: host.clone(),
+                        unexpected_host: origin.unexpected_host.to_string(),
+                        source: source_labels.clone(),
+                    })
+                    .inc();
+                if origin.unexpected_host {
+                    unexpected_origin
+                        .get_or_create(&OriginLabels {
+                            package: origin.name,
+                            version: origin.version,
+                            supplier: origin.supplier,
+                            originator: origin.originator,
+                            download_host: host,
+                            source: source_labels.clone(),
+                        })
+                        .set(1);
+                }
+            }
+        }
+        for limit in sbom.limits_exceeded() {
+            limit_exceeded
+                .get_or_create(&LimitLabels {
+                    limit: limit.to_owned(),
+                    source: source_labels.clone(),
+                })
+                .set(1);
+        }
+        for (purl_type, count) in packages_by_type(&sbom) {
+            packages_cataloged
+                .get_or_create(&PackageTypeLabels {
+                    purl_type,
+                    source: source_labels.clone(),
+                })
+                .set(count as i64);
+        }
+        if let Some(reason) = incomplete_reason(&source, &sbom) {
+            incomplete_sbom
+                .get_or_create(&IncompleteLabels {
+                    reason: reason.to_owned(),
+                    source: source_labels.clone(),
+                })
+                .set(1);
+        }
+        let today = Utc::now().date_naive();
+        for runtime in detect_runtimes(&sbom) {
+            let eol_date = runtime.eol_date(config);
+            runtime_info
+                .get_or_create(&RuntimeLabels {
+                    eol: match eol_date {
+                        Some(date) => (date <= today).to_string(),
+                        None => "unknown".into(),
+                    },
+                    eol_date: eol_date.map(|date| date.to_string()),
+                    runtime: runtime.runtime,
+                    version: runtime.version,
+                    cycle: runtime.cycle,
+                    source: source_labels.clone(),
+                })
+                .set(1);
+        }
+        if !config.sbom_metrics {
+            continue;
+        }
+        // Packages are listed once per location, so each package is counted once and the
+        // number of its occurrences is exported separately.
+        let mut occurrences: HashMap<(String, String), i64> = HashMap::new();
         for entry in sbom.packages {
-            let source = source.clone().into();
-            if entry.versionInfo.is_empty() {
-                continue;
+            if let Some(allowlist) = &config.sbom_metrics_allowlist {
+                if !allowlist.contains(&entry.name) {
+                    continue;
+                }
+            }
+            let version = match config.unversioned_packages {
+                _ if !entry.versionInfo.is_empty() => entry.versionInfo,
+                UnversionedPackages::Skip => continue,
+                UnversionedPackages::Unknown => "unknown".into(),
+                UnversionedPackages::Separate => {
+                    unversioned
+                        .get_or_create(&UnversionedLabels {
+                            software: entry.name,
+                            source: source_labels.clone(),
+                        })
+                        .set(1);
+                    continue;
+                }
             };
-            syft_metrics
-                .get_or_create(&SbomLabels {
-                    software: entry.name,
-                    version: entry.versionInfo,
-                    source,
+            *occurrences.entry((entry.name, version)).or_default() += 1;
+        }
+        for ((software, version), count) in occurrences {
+            let labels = SbomLabels {
+                software,
+                version,
+                source: source_labels.clone(),
+            };
+            syft_metrics.get_or_create(&labels).inc();
+            package_occurrences.get_or_create(&labels).set(count);
+        }
+    }
+
+    let acceptances = load_acceptances(config);
+    if config.acceptances.is_some() {
+        let expiry = Family::<AcceptanceLabels, Gauge>::default();
+        for acceptance in &acceptances {
+            let end = acceptance
+                .expires
+                .succ_opt()
+                .unwrap_or(acceptance.expires)
+                .and_time(NaiveTime::MIN)
+                .and_utc();
+            expiry
+                .get_or_create(&AcceptanceLabels {
+                    cve: acceptance.cve.clone(),
+                    package: acceptance.scope.package.clone(),
+                    scope: acceptance.scope.source.clone(),
+                    approver: acceptance.approver.clone(),
+                    valid: acceptance.is_valid().to_string(),
                 })
-                .inc();
+                .set(end.timestamp());
         }
+        registry.register(
+            "vulnerability_acceptance_expiry_timestamp_seconds",
+            "End of the last day a vulnerability acceptance applies",
+            expiry,
+        );
     }
 
-    for (source, scan) in scans {
+    let scan_date = config
+        .scan_date_label
+        .then(|| ("scan_date".to_owned(), Utc::now().date_naive().to_string()));
+
+    for ((source, cve, software), tag) in fixed_in_newer_tag {
+        newer_tag
+            .get_or_create(&NewerTagLabels {
+                cve: cve.clone(),
+                software: software.clone(),
+                tag: tag.clone(),
+                source: SourceLabels::new(source, sources.get(source)),
+            })
+            .set(1);
+    }
+
+    for (source, agreement) in agreements {
+        let source_labels = SourceLabels::new(source, sources.get(source));
+        let counts = [
+            ("both", agreement.both),
+            ("grype_only", agreement.grype_only),
+            ("trivy_only", agreement.trivy_only),
+        ];
+        for (found_by, count) in counts {
+            scanner_agreement
+                .get_or_create(&AgreementLabels {
+                    found_by: found_by.to_owned(),
+                    source: source_labels.clone(),
+                })
+                .set(count as i64);
+        }
+    }
+
+    for (source, mut scan) in scans {
+        if config.only_fixed {
+            scan.matches
+                .retain(|entry| entry.vulnerability.fix.state == FixState::Fixed);
+        }
+        let source_labels = SourceLabels::new(&source, sources.get(&source));
+        let environment = CvssEnvironment::find(&config.cvss_environments, sources.get(&source));
+        highest_severity
+            .get_or_create(&source_labels)
+            .set(scan.highest_severity().rank());
+        fixable_critical.get_or_create(&source_labels).set(
+            scan.matches
+                .iter()
+                .filter(|entry| entry.vulnerability.severity == Severity::Critical)
+                .filter(|entry| entry.vulnerability.fix.state == FixState::Fixed)
+                .count() as i64,
+        );
+
         for entry in scan.matches {
-            let source: SourceLabels = source.clone().into();
+            let accepted = config.acceptances.is_some().then(|| {
+                (
+                    "accepted".to_owned(),
+                    is_accepted(&acceptances, &source, sources.get(&source), &entry).to_string(),
+                )
+            });
+            let finding_labels = FindingLabels {
+                cve: entry.vulnerability.id.clone(),
+                software: entry.artifact.name.clone(),
+                version: entry.artifact.version.clone(),
+                source: source_labels.clone(),
+            };
+            if let Some(cvss) = entry.vulnerability.cvss.first() {
+                cvss_base_score
+                    .get_or_create(&finding_labels)
+                    .set(cvss.metrics.base_score.to_f64().unwrap_or_default());
+            }
+            let environmental_score = environment.and_then(|environment| {
+                entry
+                    .vulnerability
+                    .cvss
+                    .iter()
+                    .find_map(|cvss| environmental_score(&cvss.vector, environment))
+            });
+            if let Some(score) = environmental_score {
+                cvss_environmental_score
+                    .get_or_create(&EnvironmentalLabels {
+                        severity: cvss::severity(score).to_owned(),
+                        finding: finding_labels.clone(),
+                    })
+                    .set(score);
+            }
+            if let Some(since) = entry.vulnerability.fix.available_since() {
+                fix_age
+                    .get_or_create(&finding_labels)
+                    .set((Utc::now().date_naive() - since).num_days());
+            }
+
+            by_ecosystem
+                .get_or_create(&EcosystemLabels {
+                    ecosystem: entry.artifact.ecosystem().to_owned(),
+                    severity: entry.vulnerability.severity.to_string(),
+                    source: source_labels.clone(),
+                })
+                .inc();
+
+            let source = source_labels.clone();
             let title: String = format!(
                 "{} {}: {}",
                 source.image.clone().unwrap_or_default(),
@@ -106,29 +501,345 @@ pub fn export_metrics(
                         String::from("undefined"),
                     )
                 };
+            let canonical_id = entry.canonical_id().to_owned();
+            let exploit_available = config.exploits.is_some().then(|| {
+                (
+                    "exploit_available".to_owned(),
+                    (!entry.exploits.is_empty()).to_string(),
+                )
+            });
+            let likely_used = config.reachability_hints.then(|| {
+                (
+                    "likely_used".to_owned(),
+                    likely_used(&entry.artifact).to_owned(),
+                )
+            });
+            let vector = match entry.vulnerability.cvss.first() {
+                Some(cvss) if config.cvss_vector_labels => CVSS_VECTOR_LABELS
+                    .iter()
+                    .map(|(metric, label)| {
+                        // CVSS 2 has authentication instead of privileges required.
+                        let value = cvss.vector_metric(metric).or_else(|| match *metric {
+                            "PR" => cvss.vector_metric("Au"),
+                            _ => None,
+                        });
+                        (label.to_string(), value.unwrap_or_default().to_owned())
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
             grype_metrics
-                .get_or_create(&ScanLabels {
-                    source,
-                    cvss_base_score,
-                    cvss_exploitability_score,
-                    cvss_impact_score,
-                    title,
-                    severity: entry.vulnerability.severity,
-                    urls: entry.vulnerability.urls.join(", "),
-                    cve: entry.vulnerability.id,
-                    fixed: entry.vulnerability.fix.state.to_string(),
-                    fixed_versions: entry.vulnerability.fix.versions.join(", "),
-                    software: entry.artifact.name,
-                    scan_date: Utc::now().date_naive().to_string(),
+                .get_or_create(&ExtraLabels {
+                    extra: scan_date
+                        .iter()
+                        .cloned()
+                        .chain(exploit_available)
+                        .chain(accepted)
+                        .chain(likely_used)
+                        .chain(vector)
+                        .collect(),
+                    labels: ScanLabels {
+                        source,
+                        cvss_base_score,
+                        cvss_exploitability_score,
+                        cvss_impact_score,
+                        title,
+                        severity: entry.vulnerability.severity.to_string(),
+                        urls: entry.vulnerability.urls.join(", "),
+                        canonical_id,
+                        cve: entry.vulnerability.id,
+                        fixed: entry.vulnerability.fix.state.to_string(),
+                        fixed_versions: entry.vulnerability.fix.versions.join(", "),
+                        software: entry.artifact.name,
+                    },
                 })
                 .inc();
         }
     }
 
+    let cache = cache_stats(config);
+    let cache_entries = Gauge::<i64>::default();
+    let cache_bytes = Gauge::<i64>::default();
+    cache_entries.set(cache.entries as i64);
+    cache_bytes.set(cache.bytes as i64);
+    registry.register(
+        "cache_entries",
+        "Number of files in the cache",
+        cache_entries,
+    );
+    registry.register("cache_bytes", "Size of the cache in bytes", cache_bytes);
+
+    let tool_info = Family::<ToolLabels, Gauge>::default();
+    for source in &report.sources {
+        for (stage, tool) in [("sbom", &source.sbom_tool), ("scan", &source.scan_tool)] {
+            if let Some(tool) = tool.as_ref().filter(|tool| !tool.name.is_empty()) {
+                tool_info
+                    .get_or_create(&ToolLabels {
+                        stage: stage.into(),
+                        tool: tool.name.clone(),
+                        version: tool.version.clone(),
+                    })
+                    .inc();
+            }
+        }
+    }
+    registry.register(
+        "ssce_tool_info",
+        "Number of sources processed by each tool version",
+        tool_info,
+    );
+
+    let run_info = Family::<RunLabels, Gauge>::default();
+    run_info
+        .get_or_create(&RunLabels {
+            run_id: report.run_id.clone(),
+            started: report.started.to_rfc3339(),
+        })
+        .set(1);
+    registry.register("ssce_run_info", "Run which produced the metrics", run_info);
+
+    let stale = Gauge::<i64>::default();
+    stale.set(report.stale.into());
+    registry.register(
+        "ssce_results_stale",
+        "Whether the results were restored from the previous run after a daemon restart",
+        stale,
+    );
+
+    let errors = Family::<ErrorLabels, Counter>::default();
+    for (kind, count) in errors_total() {
+        errors
+            .get_or_create(&ErrorLabels { kind: kind.into() })
+            .inc_by(count);
+    }
+    registry.register(
+        "errors",
+        "Errors since the start of the process by the stage that failed",
+        errors,
+    );
+
+    if !config.schedule.min_container_age.is_zero() {
+        let skipped = Gauge::<i64>::default();
+        skipped.set(report.short_lived_containers_skipped as i64);
+        registry.register(
+            "short_lived_containers_skipped",
+            "Containers living shorter than schedule.min_container_age which were skipped in this run",
+            skipped,
+        );
+    }
+
+    if config.load_guard.is_some() {
+        let deferred = Counter::<u64>::default();
+        deferred.inc_by(scans_deferred_total());
+        registry.register(
+            "scans_deferred",
+            "Scans deferred to the next run since the start of the process as the host was saturated",
+            deferred,
+        );
+    }
+
+    if let Some(usage) = &report.build_cache {
+        let records = Gauge::<i64>::default();
+        records.set(usage.records as i64);
+        registry.register(
+            "build_cache_records",
+            "Number of records in the build cache of docker",
+            records,
+        );
+        let bytes = Gauge::<i64>::default();
+        bytes.set(usage.bytes as i64);
+        registry.register(
+            "build_cache_bytes",
+            "Size of the build cache of docker",
+            bytes,
+        );
+        let reclaimable = Gauge::<i64>::default();
+        reclaimable.set(usage.reclaimable_bytes as i64);
+        registry.register(
+            "build_cache_reclaimable_bytes",
+            "Size of the records of the build cache neither in use nor shared",
+            reclaimable,
+        );
+    }
+
+    if !config.groups.is_empty() {
+        let group_sources = Family::<GroupLabels, Gauge>::default();
+        let group_packages = Family::<GroupLabels, Gauge>::default();
+        let group_vulnerabilities = Family::<GroupLabels, Gauge>::default();
+        let group_highest_severity = Family::<GroupLabels, Gauge>::default();
+        for rollup in rollups(config, report) {
+            let labels = GroupLabels { group: rollup.name };
+            group_sources
+                .get_or_create(&labels)
+                .set(rollup.sources as i64);
+            group_packages
+                .get_or_create(&labels)
+                .set(rollup.packages as i64);
+            group_vulnerabilities
+                .get_or_create(&labels)
+                .set(rollup.vulnerabilities as i64);
+            group_highest_severity
+                .get_or_create(&labels)
+                .set(rollup.highest_severity.rank());
+        }
+        registry.register(
+            "group_sources",
+            "Number of sources in a group",
+            group_sources,
+        );
+        registry.register(
+            "group_packages",
+            "Number of packages in all sources of a group",
+            group_packages,
+        );
+        registry.register(
+            "group_vulnerabilities",
+            "Number of vulnerabilities in all sources of a group",
+            group_vulnerabilities,
+        );
+        registry.register(
+            "group_highest_severity",
+            "Highest severity found in any source of a group, like source_highest_severity",
+            group_highest_severity,
+        );
+    }
+
+    if config.malicious_packages.is_some() {
+        let suspicious = Family::<SuspiciousLabels, Gauge>::default();
+        for source in &report.sources {
+            let source_labels = SourceLabels::new(&source.source, Some(&source.tags));
+            for package in &source.suspicious_packages {
+                suspicious
+                    .get_or_create(&SuspiciousLabels {
+                        package: package.name.clone(),
+                        version: package.version.clone(),
+                        reason: package.reason.as_str().to_owned(),
+                        advisory: package.advisory.clone(),
+                        similar_to: package.similar_to.clone(),
+                        source: source_labels.clone(),
+                    })
+                    .set(1);
+            }
+        }
+        registry.register(
+            "suspicious_package",
+            "Packages known to be malicious or named like an internal package, any series \
+             warrants immediate investigation",
+            suspicious,
+        );
+    }
+
+    if config.container_names {
+        let image_containers = Family::<ContainerLabels, Gauge>::default();
+        for source in &report.sources {
+            let source_labels = SourceLabels::new(&source.source, Some(&source.tags));
+            for container in &source.containers {
+                image_containers
+                    .get_or_create(&ContainerLabels {
+                        container: container.clone(),
+                        source: source_labels.clone(),
+                    })
+                    .set(1);
+            }
+        }
+        registry.register(
+            "image_containers",
+            "Containers using an image",
+            image_containers,
+        );
+    }
+
+    if config.firmware_inventory {
+        let firmware_info = Family::<FirmwareLabels, Gauge>::default();
+        for firmware in &report.firmware {
+            firmware_info
+                .get_or_create(&FirmwareLabels {
+                    component: firmware.component.clone(),
+                    device: firmware.device.clone(),
+                    vendor: firmware.vendor.clone(),
+                    version: firmware.version.clone(),
+                })
+                .set(1);
+        }
+        registry.register(
+            "firmware_info",
+            "Firmware versions of the host",
+            firmware_info,
+        );
+    }
+
+    if config.systemd_services {
+        let service_binary = Family::<ServiceLabels, Gauge>::default();
+        let unpackaged = Family::<ServiceLabels, Gauge>::default();
+        for service in &report.services {
+            let labels = ServiceLabels {
+                unit: service.unit.clone(),
+                binary: service.binary.to_string_lossy().into_owned(),
+                package: service.package.clone(),
+            };
+            service_binary.get_or_create(&labels).set(1);
+            if service.package.is_none() {
+                unpackaged.get_or_create(&labels).set(1);
+            }
+        }
+        registry.register(
+            "service_binary_info",
+            "Binaries of enabled systemd services and their packages",
+            service_binary,
+        );
+        registry.register(
+            "unpackaged_service_binary",
+            "Binaries of enabled systemd services which don't belong to any package",
+            unpackaged,
+        );
+    }
+
+    if let Some(db_built) = report.grype_db_built {
+        let built = Gauge::<i64>::default();
+        built.set(db_built.timestamp());
+        registry.register(
+            "grype_db_built_timestamp_seconds",
+            "Time the vulnerability database was built",
+            built,
+        );
+    }
+
     encode(&mut buffer, &registry)?;
-    output.write_all(buffer.as_bytes())?;
+    Ok(sort_series(&buffer))
+}
+
+/// Sort the metric families by name and the series of each family by their labels. Families
+/// are registered conditionally and series are kept in hash maps, so without sorting the output
+/// changes between runs with the same results.
+pub fn sort_series(encoded: &str) -> String {
+    // Each family starts with its `# HELP` line, followed by the other metadata and the series.
+    let mut families: Vec<(&str, Vec<&str>, Vec<&str>)> = Vec::new();
+    for line in encoded.lines() {
+        if let Some(help) = line.strip_prefix("# HELP ") {
+            let name = help.split_whitespace().next().unwrap_or_default();
+            families.push((name, vec![line], Vec::new()));
+        } else if line == "# EOF" {
+            continue;
+        } else if let Some((_, metadata, series)) = families.last_mut() {
+            if line.starts_with('#') {
+                metadata.push(line);
+            } else {
+                series.push(line);
+            }
+        }
+    }
+    families.sort_by_key(|(name, _, _)| *name);
 
-    Ok(())
+    let mut sorted = String::with_capacity(encoded.len());
+    for (_, metadata, mut series) in families {
+        series.sort_unstable();
+        for line in metadata.into_iter().chain(series) {
+            sorted.push_str(line);
+            sorted.push('\n');
+        }
+    }
+    sorted.push_str("# EOF\n");
+    sorted
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
@@ -139,9 +850,54 @@ pub struct SbomLabels {
     pub source: SourceLabels,
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ErrorLabels {
+    pub kind: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct GroupLabels {
+    pub group: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct AcceptanceLabels {
+    pub cve: String,
+    pub package: Option<String>,
+    /// Source prefix of the acceptance.
+    pub scope: Option<String>,
+    pub approver: String,
+    pub valid: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct UnversionedLabels {
+    pub software: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+/// Label set with optional labels in addition to the fixed ones. The derive only supports
+/// flattening a single field, which the label structs already use for the source labels.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ExtraLabels<T> {
+    pub extra: Vec<(String, String)>,
+    pub labels: T,
+}
+
+impl<T: EncodeLabelSet> EncodeLabelSet for ExtraLabels<T> {
+    fn encode(&self, mut encoder: LabelSetEncoder) -> Result<(), std::fmt::Error> {
+        for label in &self.extra {
+            label.encode(encoder.encode_label())?;
+        }
+        self.labels.encode(encoder)
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub struct ScanLabels {
     pub cve: String,
+    pub canonical_id: String,
     pub cvss_base_score: String,
     pub cvss_exploitability_score: String,
     pub cvss_impact_score: String,
@@ -150,29 +906,203 @@ pub struct ScanLabels {
     pub software: String,
     pub fixed: String,
     pub fixed_versions: String,
-    pub scan_date: String,
     pub title: String,
     #[prometheus(flatten)]
     pub source: SourceLabels,
 }
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ToolLabels {
+    pub stage: String,
+    pub tool: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RunLabels {
+    pub run_id: String,
+    pub started: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct EnvironmentalLabels {
+    pub severity: String,
+    #[prometheus(flatten)]
+    pub finding: FindingLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct FindingLabels {
+    pub cve: String,
+    pub software: String,
+    pub version: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct NewerTagLabels {
+    pub cve: String,
+    pub software: String,
+    pub tag: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ContainerLabels {
+    pub container: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct PackageTypeLabels {
+    pub purl_type: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct IncompleteLabels {
+    pub reason: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct RuntimeLabels {
+    pub runtime: String,
+    pub version: String,
+    pub cycle: String,
+    pub eol: String,
+    pub eol_date: Option<String>,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct ServiceLabels {
+    pub unit: String,
+    pub binary: String,
+    pub package: Option<String>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct FirmwareLabels {
+    pub component: String,
+    pub device: String,
+    pub vendor: String,
+    pub version: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct AgreementLabels {
+    pub found_by: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct MethodLabels {
+    pub method: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct OriginLabels {
+    pub package: String,
+    pub version: String,
+    pub supplier: Option<String>,
+    pub originator: Option<String>,
+    pub download_host: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct DownloadHostLabels {
+    pub download_host: String,
+    pub unexpected_host: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct SuspiciousLabels {
+    pub package: String,
+    pub version: String,
+    pub reason: String,
+    pub advisory: Option<String>,
+    pub similar_to: Option<String>,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct LimitLabels {
+    pub limit: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+pub struct EcosystemLabels {
+    pub ecosystem: String,
+    pub severity: String,
+    #[prometheus(flatten)]
+    pub source: SourceLabels,
+}
+
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, EncodeLabelSet)]
 pub struct SourceLabels {
     pub image: Option<String>,
     pub id: Option<String>,
+    pub digest: Option<String>,
     pub path: Option<String>,
+    pub vendor_sbom: Option<String>,
+    #[prometheus(flatten)]
+    pub tags: Vec<(String, String)>,
 }
 
-impl From<Source> for SourceLabels {
-    fn from(value: Source) -> Self {
-        match value {
-            Source::DockerImage { name, id } => Self {
-                image: Some(name),
-                id: Some(id),
+impl SourceLabels {
+    pub fn new(source: &Source, tags: Option<&Tags>) -> Self {
+        // Tag keys are prefixed to avoid clashes with the built-in labels, and sanitized as
+        // docker labels commonly contain dots.
+        let tags = tags
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| {
+                let key = key
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect::<String>();
+                (format!("tag_{key}"), value.clone())
+            })
+            .collect();
+
+        match source {
+            Source::DockerImage { name, id, digest } => Self {
+                image: Some(name.clone()),
+                id: Some(id.clone()),
+                digest: digest.clone(),
+                tags,
+                ..Default::default()
+            },
+            Source::HostDirectory { path }
+            | Source::DiskImage { path }
+            | Source::CiWorkspace { path } => Self {
+                path: Some(path.to_string_lossy().to_string()),
+                tags,
                 ..Default::default()
             },
-            Source::HostDirectory { path } => Self {
+            Source::VendorSbom { path } => Self {
                 path: Some(path.to_string_lossy().to_string()),
+                vendor_sbom: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string()),
+                tags,
                 ..Default::default()
             },
         }
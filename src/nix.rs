@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::{config::Config, sbom::spdx_package};
+
+const CURRENT_SYSTEM: &str = "/run/current-system";
+
+/// Suffixes nix appends to the store paths of additional derivation outputs.
+const OUTPUT_SUFFIXES: [&str; 8] = [
+    "-bin", "-dev", "-lib", "-out", "-man", "-doc", "-info", "-debug",
+];
+
+/// Whether host scans should use the nix store instead of cataloging `/nix/store` with syft.
+/// Unless configured explicitly, this is enabled on NixOS.
+pub fn enabled(config: &Config) -> bool {
+    config
+        .nix
+        .unwrap_or_else(|| Path::new("/etc/NIXOS").exists())
+}
+
+/// SPDX package entries for all store paths in the closure of the current system.
+#[tracing::instrument]
+pub async fn nix_packages() -> Result<Vec<Value>> {
+    debug!("querying the closure of the current system");
+    let output = Command::new("nix-store")
+        .arg("--query")
+        .arg("--requisites")
+        .arg(CURRENT_SYSTEM)
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("nix-store exited with {}", output.status);
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(parse_store_path)
+        .unique()
+        .map(|(name, version)| {
+            let purl = format!("pkg:nix/{name}@{version}");
+            spdx_package(&name, &version, &purl)
+        })
+        .collect())
+}
+
+/// Split a store path like `/nix/store/<hash>-openssl-3.0.13-bin` into name and version, using
+/// the same rule as `builtins.parseDrvName`: the version starts at the first dash followed by
+/// a digit.
+fn parse_store_path(path: &str) -> Option<(String, String)> {
+    let (_hash, name) = path.strip_prefix("/nix/store/")?.split_once('-')?;
+    let name = OUTPUT_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+
+    let split = name
+        .char_indices()
+        .tuple_windows()
+        .find(|((_, a), (_, b))| *a == '-' && b.is_ascii_digit())
+        .map(|((i, _), _)| i);
+    Some(match split {
+        Some(i) => (name[..i].to_owned(), name[i + 1..].to_owned()),
+        None => (name.to_owned(), String::new()),
+    })
+}
@@ -0,0 +1,229 @@
+use std::{collections::BTreeMap, process::Stdio};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
+
+#[cfg(feature = "notifiers")]
+use crate::{
+    alertmanager::{send_alerts, AlertmanagerConfig},
+    chat::{post, ChatConfig},
+};
+use crate::{
+    config::Config,
+    email::{send_email, EmailConfig},
+    policy::{PolicyUpdate, Violation},
+    proxy::Integration,
+    secret::Secret,
+    severity::Severity,
+};
+
+/// A destination for notifications.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NotifierConfig {
+    /// POST the notification as JSON with `subject`, `text` and `data` fields.
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, Secret>,
+    },
+    /// Run a command with the subject in `SSCE_SUBJECT` and the text on stdin.
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Fire an alert for every policy violation in Alertmanager. It only receives policy
+    /// violations, no other notifications.
+    #[cfg(feature = "notifiers")]
+    Alertmanager(AlertmanagerConfig),
+    /// Mail the notification through a sendmail compatible command.
+    Email(EmailConfig),
+    /// Post the notification to a Slack channel through an incoming webhook.
+    #[cfg(feature = "notifiers")]
+    Slack(ChatConfig),
+    /// Post the notification to a Mattermost channel through an incoming webhook.
+    #[cfg(feature = "notifiers")]
+    Mattermost(ChatConfig),
+}
+
+impl NotifierConfig {
+    /// Whether a policy violation of this severity is sent to this notifier.
+    #[cfg(feature = "notifiers")]
+    fn accepts_severity(&self, severity: Severity) -> bool {
+        match self {
+            Self::Slack(chat) | Self::Mattermost(chat) => chat.accepts_severity(severity),
+            _ => true,
+        }
+    }
+
+    #[cfg(not(feature = "notifiers"))]
+    fn accepts_severity(&self, _severity: Severity) -> bool {
+        true
+    }
+}
+
+/// What a notification is about, for routing notifications to different notifiers.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Digest,
+    Violations,
+    Report,
+}
+
+/// A message for humans, with structured data for machines.
+#[derive(Debug)]
+pub struct Notification {
+    pub event: Event,
+    pub subject: String,
+    pub text: String,
+    pub data: Value,
+}
+
+/// Send a notification to all configured notifiers. Failures are logged, so one broken
+/// notifier doesn't prevent the others from being notified.
+pub async fn notify(config: &Config, notification: &Notification) {
+    let Some(client) = client(config) else {
+        return;
+    };
+    for notifier in &config.notifiers {
+        if let Err(e) = send(&client, notifier, notification).await {
+            warn!("Failed to send notification: {e:?}");
+        }
+    }
+}
+
+/// Send the policy violations of a run to all configured notifiers. Alertmanager gets alerts
+/// for all active and resolved violations, the other notifiers a notification about the new
+/// violations.
+pub async fn notify_violations(config: &Config, update: &PolicyUpdate) {
+    let Some(client) = client(config) else {
+        return;
+    };
+    for notifier in &config.notifiers {
+        #[cfg(feature = "notifiers")]
+        if let NotifierConfig::Alertmanager(alertmanager) = notifier {
+            if let Err(e) = send_alerts(&client, alertmanager, update).await {
+                warn!("Failed to send policy violations: {e:?}");
+            }
+            continue;
+        }
+        let violations: Vec<Violation> = update
+            .new
+            .iter()
+            .filter(|violation| notifier.accepts_severity(violation.severity))
+            .cloned()
+            .collect();
+        if violations.is_empty() {
+            continue;
+        }
+        if let Err(e) = send(&client, notifier, &violations_notification(&violations)).await {
+            warn!("Failed to send policy violations: {e:?}");
+        }
+    }
+}
+
+fn client(config: &Config) -> Option<reqwest::Client> {
+    config
+        .proxy
+        .client(Integration::Notifiers)
+        .inspect_err(|e| warn!("Failed to create HTTP client of the notifiers: {e:?}"))
+        .ok()
+}
+
+fn violations_notification(violations: &[Violation]) -> Notification {
+    let mut text = format!("New policy violations: {}\n", violations.len());
+    for violation in violations {
+        text += &format!(
+            "  - {} {} in {} {} of {}",
+            violation.severity,
+            violation.id,
+            violation.package,
+            violation.version,
+            violation.source
+        );
+        if !violation.fixed_versions.is_empty() {
+            text += &format!(", fixed in {}", violation.fixed_versions.join(", "));
+        }
+        text += "\n";
+    }
+    Notification {
+        event: Event::Violations,
+        subject: format!(
+            "{} new policy violations on {}",
+            violations.len(),
+            hostname()
+        ),
+        text,
+        data: json!({ "new_violations": violations }),
+    }
+}
+
+#[tracing::instrument(skip(client, notification))]
+async fn send(
+    client: &reqwest::Client,
+    notifier: &NotifierConfig,
+    notification: &Notification,
+) -> Result<()> {
+    debug!(subject = notification.subject, "sending notification");
+    match notifier {
+        NotifierConfig::Webhook { url, headers } => {
+            let mut request = client.post(url).json(&json!({
+                "subject": notification.subject,
+                "text": notification.text,
+                "data": notification.data,
+            }));
+            for (name, value) in headers {
+                request = request.header(name, value.expose());
+            }
+            request.send().await?.error_for_status()?;
+        }
+        NotifierConfig::Command { command, args } => {
+            let mut child = Command::new(command)
+                .args(args)
+                .env("SSCE_SUBJECT", &notification.subject)
+                .stdin(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            {
+                let mut stdin = child.stdin.take().unwrap();
+                stdin.write_all(notification.text.as_bytes()).await?;
+            }
+            let status = child.wait().await?;
+            if !status.success() {
+                bail!("{command} exited with {status}");
+            }
+        }
+        NotifierConfig::Email(email) => send_email(email, notification, None).await?,
+        #[cfg(feature = "notifiers")]
+        NotifierConfig::Slack(chat) | NotifierConfig::Mattermost(chat)
+            if !chat.accepts(notification.event) =>
+        {
+            debug!("notification not routed to this channel");
+        }
+        #[cfg(feature = "notifiers")]
+        NotifierConfig::Slack(chat) => post(client, chat, "*", notification).await?,
+        #[cfg(feature = "notifiers")]
+        NotifierConfig::Mattermost(chat) => post(client, chat, "**", notification).await?,
+        #[cfg(feature = "notifiers")]
+        NotifierConfig::Alertmanager(_) => {
+            debug!("alertmanager only receives policy violations");
+        }
+    }
+    Ok(())
+}
+
+/// Name of this host for notifications.
+pub fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_owned())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "unknown host".into())
+}
@@ -0,0 +1,108 @@
+//! Where packages come from according to their SPDX supplier, originator and download
+//! location, to flag packages downloaded from hosts other than the expected registries or
+//! internal mirrors, e.g. typosquatted packages or builds bypassing the mirror.
+
+use std::collections::BTreeSet;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::sbom::{Sbom, SbomEntry};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct PackageOriginConfig {
+    /// Export the number of packages per download host, and the packages downloaded from
+    /// unexpected hosts, in addition to the origins in the report.
+    #[serde(default)]
+    pub metric: bool,
+    /// Hosts packages are expected to be downloaded from, e.g. `crates.io` or the internal
+    /// mirror. Subdomains match as well. Packages downloaded from other hosts are flagged,
+    /// nothing is flagged without hosts.
+    #[serde(default)]
+    pub expected_hosts: Vec<String>,
+}
+
+/// Origin of a package, with SPDX's `NOASSERTION` and `NONE` left out.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackageOrigin {
+    pub name: String,
+    pub version: String,
+    /// Distributor of the package, e.g. `Organization: Alpine Linux`.
+    pub supplier: Option<String>,
+    /// Original author of the package.
+    pub originator: Option<String>,
+    pub download_location: Option<String>,
+    /// Whether the download location is on none of the expected hosts.
+    pub unexpected_host: bool,
+}
+
+impl PackageOrigin {
+    pub fn download_host(&self) -> Option<&str> {
+        self.download_location.as_deref().and_then(host)
+    }
+}
+
+/// SPDX values meaning that nothing is known.
+fn asserted(value: &str) -> Option<String> {
+    match value.trim() {
+        "" | "NOASSERTION" | "NONE" => None,
+        value => Some(value.to_owned()),
+    }
+}
+
+/// Host of a download location, which is a URL, possibly with a VCS prefix like
+/// `git+https://`, or an scp-like git location like `git@github.com:org/repo`.
+pub fn host(location: &str) -> Option<&str> {
+    let rest = location
+        .split_once("://")
+        .map_or(location, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+fn is_expected(config: &PackageOriginConfig, host: &str) -> bool {
+    config.expected_hosts.iter().any(|expected| {
+        host.eq_ignore_ascii_case(expected)
+            || host
+                .to_ascii_lowercase()
+                .ends_with(&format!(".{}", expected.to_ascii_lowercase()))
+    })
+}
+
+fn origin(config: &PackageOriginConfig, package: &SbomEntry) -> Option<PackageOrigin> {
+    let supplier = package.supplier.as_deref().and_then(asserted);
+    let originator = package.originator.as_deref().and_then(asserted);
+    let download_location = package.downloadLocation.as_deref().and_then(asserted);
+    if supplier.is_none() && originator.is_none() && download_location.is_none() {
+        return None;
+    }
+    let unexpected_host = !config.expected_hosts.is_empty()
+        && download_location
+            .as_deref()
+            .and_then(host)
+            .is_some_and(|host| !is_expected(config, host));
+    Some(PackageOrigin {
+        name: package.name.clone(),
+        version: package.versionInfo.clone(),
+        supplier,
+        originator,
+        download_location,
+        unexpected_host,
+    })
+}
+
+/// Origins of the packages of the SBOM which assert any, once per package even if the SBOM
+/// lists it at several locations.
+pub fn package_origins(config: &PackageOriginConfig, sbom: &Sbom) -> Vec<PackageOrigin> {
+    sbom.packages
+        .iter()
+        .filter_map(|package| origin(config, package))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    acceptance::{is_accepted, load_acceptances, Acceptance},
+    config::{Config, Source, Tags},
+    fs::write_atomic,
+    report::Report,
+    scan::FixState,
+    severity::Severity,
+};
+
+/// Which findings violate the policy, and are sent to notifiers as soon as they are found.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Lowest severity of a violation, e.g. `High`.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+    /// Only findings with an available fix are violations.
+    #[serde(default)]
+    pub only_fixed: bool,
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Critical
+}
+
+/// A finding violating the policy.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Violation {
+    pub source: Source,
+    pub tags: Tags,
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub severity: Severity,
+    pub fixed_versions: Vec<String>,
+    pub urls: Vec<String>,
+}
+
+impl Violation {
+    /// Identifies the violation across runs. Images are identified by their name without the
+    /// id, so the violation stays the same when the image is rebuilt.
+    pub fn key(&self) -> (String, &str, &str, &str) {
+        (self.source.name(), &self.id, &self.package, &self.version)
+    }
+}
+
+/// Violations of the current run compared to the previous run.
+#[derive(Debug, Default)]
+pub struct PolicyUpdate {
+    pub active: Vec<Violation>,
+    /// Violations which weren't active in the previous run.
+    pub new: Vec<Violation>,
+    /// Violations of the previous run which are gone, e.g. because the package was updated.
+    pub resolved: Vec<Violation>,
+}
+
+/// Findings of the report violating the policy. Accepted findings aren't violations.
+pub fn violations(
+    policy: &PolicyConfig,
+    report: &Report,
+    acceptances: &[Acceptance],
+) -> Vec<Violation> {
+    report
+        .sources
+        .iter()
+        .flat_map(|source| {
+            source
+                .findings
+                .iter()
+                .filter(|entry| entry.vulnerability.severity >= policy.min_severity)
+                .filter(|entry| {
+                    !policy.only_fixed || entry.vulnerability.fix.state == FixState::Fixed
+                })
+                .filter(|entry| {
+                    !is_accepted(acceptances, &source.source, Some(&source.tags), entry)
+                })
+                .map(|entry| Violation {
+                    source: source.source.clone(),
+                    tags: source.tags.clone(),
+                    id: entry.canonical_id().to_owned(),
+                    package: entry.artifact.name.clone(),
+                    version: entry.artifact.version.clone(),
+                    severity: entry.vulnerability.severity,
+                    fixed_versions: entry.vulnerability.fix.versions.clone(),
+                    urls: entry.vulnerability.urls.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Compare the violations of the report against those of the previous run, and remember them
+/// for the next run. Without a policy, nothing is a violation.
+pub fn check_policy(config: &Config, report: &Report) -> Result<PolicyUpdate> {
+    let Some(policy) = &config.policy else {
+        return Ok(PolicyUpdate::default());
+    };
+    let path = config.base_path.join("violations.json");
+    let previous: Vec<Violation> = std::fs::read(&path)
+        .ok()
+        .and_then(|previous| serde_json::from_slice(&previous).ok())
+        .unwrap_or_default();
+    let active = violations(policy, report, &load_acceptances(config));
+
+    let previous_keys: HashSet<_> = previous.iter().map(Violation::key).collect();
+    let active_keys: HashSet<_> = active.iter().map(Violation::key).collect();
+    let new: Vec<Violation> = active
+        .iter()
+        .filter(|violation| !previous_keys.contains(&violation.key()))
+        .cloned()
+        .collect();
+    let resolved: Vec<Violation> = previous
+        .iter()
+        .filter(|violation| !active_keys.contains(&violation.key()))
+        .cloned()
+        .collect();
+    debug!(
+        active = active.len(),
+        new = new.len(),
+        resolved = resolved.len(),
+        "checked policy"
+    );
+
+    write_atomic(&path, serde_json::to_vec(&active)?)?;
+    Ok(PolicyUpdate {
+        active,
+        new,
+        resolved,
+    })
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    docker::DockerClient,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct PreflightConfig {
+    /// Free space required on the base path.
+    #[serde(default)]
+    pub min_free_mb: u64,
+    /// Free space required in the workspace in addition to the size of the largest image.
+    #[serde(default)]
+    pub min_free_workspace_mb: u64,
+    /// What to do when there isn't enough space.
+    #[serde(default)]
+    pub on_insufficient_space: InsufficientSpaceAction,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InsufficientSpaceAction {
+    /// Refuse to start the run.
+    #[default]
+    Abort,
+    /// Skip the largest images until the rest fits into the workspace. Too little space on the
+    /// base path still aborts the run.
+    SkipLargest,
+}
+
+const MB: u64 = 1024 * 1024;
+
+/// Check that there is enough free space for the run, as running out of space while writing
+/// the cache corrupts it. Syft exports images into the workspace, so the largest image has to
+/// fit there in addition to the configured minimum. Returns the sources to scan.
+pub async fn check_space(
+    config: &Config,
+    docker: &DockerClient,
+    sources: Vec<Source>,
+) -> Result<Vec<Source>> {
+    let preflight = &config.preflight;
+    let base_free = available_mb(&config.base_path)?;
+    if base_free < preflight.min_free_mb {
+        bail!(
+            "Only {base_free} MB free in {}, {} MB required",
+            config.base_path.display(),
+            preflight.min_free_mb
+        );
+    }
+
+    let workspace_free = available_mb(&config.workspace_path())?;
+    let Some(usable) = workspace_free.checked_sub(preflight.min_free_workspace_mb) else {
+        bail!(
+            "Only {workspace_free} MB free in workspace {}, {} MB required",
+            config.workspace_path().display(),
+            preflight.min_free_workspace_mb
+        );
+    };
+
+    let sizes = image_sizes(docker, &sources).await;
+    let mut scanned = Vec::new();
+    for (source, size) in sources.into_iter().zip(sizes) {
+        let size = size.div_ceil(MB);
+        if size <= usable {
+            scanned.push(source);
+            continue;
+        }
+        let problem = format!(
+            "{source} needs about {size} MB, but only {usable} MB are usable in the workspace"
+        );
+        match preflight.on_insufficient_space {
+            InsufficientSpaceAction::Abort => bail!(problem),
+            InsufficientSpaceAction::SkipLargest => warn!("Skipping {problem}"),
+        }
+    }
+    Ok(scanned)
+}
+
+fn available_mb(path: &Path) -> Result<u64> {
+    std::fs::create_dir_all(path)?;
+    let available = fs4::available_space(path)
+        .with_context(|| format!("Failed to get free space of {}", path.display()))?;
+    debug!(?path, available, "free space");
+    Ok(available / MB)
+}
+
+/// Size of each source in bytes, as far as it's known up front. Only docker images are
+/// estimated, other sources are scanned in place.
+async fn image_sizes(docker: &DockerClient, sources: &[Source]) -> Vec<u64> {
+    let mut sizes = Vec::new();
+    for source in sources {
+        let size = match source {
+            Source::DockerImage { id, .. } => match docker.image(id).await {
+                Ok(Some(image)) => image.size.max(0) as u64,
+                _ => 0,
+            },
+            _ => 0,
+        };
+        sizes.push(size);
+    }
+    sizes
+}
@@ -0,0 +1,57 @@
+use std::io::IsTerminal;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use tracing::info;
+
+use crate::config::Source;
+
+/// Per-source progress of a pipeline stage. When running interactively a progress bar with ETA
+/// is drawn to stderr, otherwise only the log lines are written.
+pub struct Progress {
+    stage: &'static str,
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn new(stage: &'static str, total: usize) -> Self {
+        let bar = ProgressBar::with_draw_target(
+            Some(total as u64),
+            if std::io::stderr().is_terminal() {
+                ProgressDrawTarget::stderr()
+            } else {
+                ProgressDrawTarget::hidden()
+            },
+        );
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{prefix} [{elapsed_precise}] {wide_bar} {pos}/{len} (ETA {eta}) {msg}",
+            )
+            .expect("progress template is valid"),
+        );
+        bar.set_prefix(stage);
+        Self { stage, bar }
+    }
+
+    /// Mark a source as the one currently being processed.
+    pub fn start(&self, source: &Source) {
+        self.bar.set_message(source.to_string());
+    }
+
+    /// Mark the current source as done.
+    pub fn inc(&self) {
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        info!(
+            "{} finished for {} of {} sources in {:.1?}",
+            self.stage,
+            self.bar.position(),
+            self.bar.length().unwrap_or_default(),
+            self.bar.elapsed()
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::secret::Secret;
+
+const PROXY_VARIABLES: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"];
+const NO_PROXY_VARIABLES: [&str; 2] = ["NO_PROXY", "no_proxy"];
+
+/// Proxies of the scanners and HTTP clients. Without configuration, the `HTTP_PROXY`,
+/// `HTTPS_PROXY` and `NO_PROXY` environment variables of the exporter apply to everything.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Proxy of all integrations without their own.
+    #[serde(flatten)]
+    pub default: Proxy,
+    /// Proxies of single integrations, e.g. a direct connection for `grype_db` when the
+    /// corporate proxy breaks database downloads.
+    #[serde(default)]
+    pub integrations: BTreeMap<Integration, Proxy>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct Proxy {
+    /// URL of the proxy, e.g. `http://proxy.example.com:3128`, instead of the one from the
+    /// environment.
+    pub url: Option<Secret>,
+    /// Connect directly, ignoring the proxy of the environment.
+    #[serde(default)]
+    pub direct: bool,
+    /// Hosts and domains connected to directly in addition to `NO_PROXY`, e.g. in-cluster
+    /// endpoints like `.svc.cluster.local`.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(
+    Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Integration {
+    /// Updates of the grype vulnerability database.
+    GrypeDb,
+    /// Vulnerability scans with grype.
+    Grype,
+    /// SBOMs generated by syft and attestations fetched by `docker buildx`, which pull images
+    /// from registries.
+    Syft,
+    /// Scans of the scanner comparison with trivy.
+    Trivy,
+    Notifiers,
+    Sinks,
+    Tickets,
+    Github,
+    SharedCache,
+    Admission,
+    /// The kubernetes API, usually in-cluster and reached directly.
+    Kubernetes,
+    /// Downloads of the ExploitDB and Metasploit indexes.
+    Exploits,
+}
+
+impl ProxyConfig {
+    pub fn get(&self, integration: Integration) -> &Proxy {
+        self.integrations.get(&integration).unwrap_or(&self.default)
+    }
+
+    /// HTTP client of the integration.
+    pub fn client(&self, integration: Integration) -> Result<reqwest::Client> {
+        Ok(self.get(integration).client_builder()?.build()?)
+    }
+}
+
+impl Proxy {
+    /// The configured proxy, or the one from the environment.
+    fn url(&self) -> Option<String> {
+        match &self.url {
+            Some(url) => Some(url.expose().to_owned()),
+            None => PROXY_VARIABLES
+                .iter()
+                .find_map(|variable| std::env::var(variable).ok())
+                .filter(|url| !url.is_empty()),
+        }
+    }
+
+    /// The configured exceptions joined with the ones from the environment.
+    fn no_proxy(&self) -> String {
+        NO_PROXY_VARIABLES
+            .iter()
+            .find_map(|variable| std::env::var(variable).ok())
+            .into_iter()
+            .filter(|hosts| !hosts.is_empty())
+            .chain(self.no_proxy.iter().cloned())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Pass the proxy to a child process through the environment variables most tools, like
+    /// grype and syft, understand.
+    pub fn apply(&self, command: &mut Command) {
+        if self.direct {
+            for variable in PROXY_VARIABLES {
+                command.env_remove(variable);
+            }
+            return;
+        }
+        if let Some(url) = &self.url {
+            for variable in PROXY_VARIABLES {
+                command.env(variable, url.expose());
+            }
+        }
+        if !self.no_proxy.is_empty() {
+            let no_proxy = self.no_proxy();
+            for variable in NO_PROXY_VARIABLES {
+                command.env(variable, &no_proxy);
+            }
+        }
+    }
+
+    pub fn client_builder(&self) -> Result<reqwest::ClientBuilder> {
+        let builder = reqwest::Client::builder();
+        if self.direct {
+            return Ok(builder.no_proxy());
+        }
+        if self.url.is_none() && self.no_proxy.is_empty() {
+            // reqwest uses the environment itself.
+            return Ok(builder);
+        }
+        let Some(url) = self.url() else {
+            return Ok(builder);
+        };
+        let proxy = reqwest::Proxy::all(&url)
+            .context("Invalid proxy URL")?
+            .no_proxy(reqwest::NoProxy::from_string(&self.no_proxy()));
+        Ok(builder.proxy(proxy))
+    }
+}
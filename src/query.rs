@@ -0,0 +1,136 @@
+//! Queries of the local results for `ssce find`, e.g. which sources contain a vulnerable
+//! version of a package during an incident, without scanning again.
+
+use std::{cmp::Ordering, collections::BTreeSet, str::FromStr};
+
+use anyhow::Result;
+
+use crate::{
+    config::Config,
+    redeploy::compare_tags,
+    report::{read_report_sources, PackageVersion},
+    scan::ScanEntry,
+};
+
+/// Version constraint like `<3.0.13` or `>=1.2,<1.4`. A version without operator matches only
+/// itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionConstraint(Vec<(Ordering, bool, String)>);
+
+impl FromStr for VersionConstraint {
+    type Err = String;
+
+    fn from_str(constraint: &str) -> Result<Self, Self::Err> {
+        constraint
+            .split(',')
+            .map(|part| {
+                let part = part.trim();
+                // Operators as the ordering of the package version to the given one, and
+                // whether that ordering is what matches or what doesn't.
+                let (ordering, matching, version) = [
+                    ("<=", Ordering::Greater, false),
+                    (">=", Ordering::Less, false),
+                    ("!=", Ordering::Equal, false),
+                    ("<", Ordering::Less, true),
+                    (">", Ordering::Greater, true),
+                    ("=", Ordering::Equal, true),
+                ]
+                .into_iter()
+                .find_map(|(operator, ordering, matching)| {
+                    Some((ordering, matching, part.strip_prefix(operator)?))
+                })
+                .unwrap_or((Ordering::Equal, true, part));
+                let version = version.trim();
+                if version.is_empty() {
+                    return Err(format!("Missing version in {constraint:?}"));
+                }
+                Ok((ordering, matching, version.to_owned()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, version: &str) -> bool {
+        self.0.iter().all(|(ordering, matching, other)| {
+            (compare_tags(version, other) == *ordering) == *matching
+        })
+    }
+}
+
+/// What to look for. Without a vulnerability id, all packages matching the name and version
+/// are found, vulnerable or not.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub package: Option<String>,
+    pub version: Option<VersionConstraint>,
+    pub cve: Option<String>,
+}
+
+impl Query {
+    fn matches_package(&self, name: &str, version: &str) -> bool {
+        self.package
+            .as_ref()
+            .is_none_or(|package| package.eq_ignore_ascii_case(name))
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|constraint| constraint.matches(version))
+    }
+
+    fn matches_finding(&self, entry: &ScanEntry) -> bool {
+        self.cve.as_ref().is_some_and(|cve| {
+            entry.vulnerability.id.eq_ignore_ascii_case(cve)
+                || entry
+                    .related_vulnerabilities
+                    .iter()
+                    .any(|related| related.id.eq_ignore_ascii_case(cve))
+        }) && self.matches_package(&entry.artifact.name, &entry.artifact.version)
+    }
+}
+
+/// A package of a source matching the query, with the vulnerability for vulnerability queries.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FindMatch {
+    pub location: String,
+    pub package: String,
+    pub version: String,
+    pub cve: Option<String>,
+}
+
+/// Search the report of the last run, which covers every current source, including those that
+/// weren't due in that run. Package queries search the packages of the SBOMs, vulnerability
+/// queries the findings.
+pub fn find(config: &Config, query: &Query) -> Result<Vec<FindMatch>> {
+    let mut matches = BTreeSet::new();
+    for (location, source) in read_report_sources(config)? {
+        if query.cve.is_none() {
+            let packages: Vec<PackageVersion> =
+                serde_json::from_value(source["package_versions"].clone()).unwrap_or_default();
+            for package in packages {
+                if query.matches_package(&package.name, &package.version) {
+                    matches.insert(FindMatch {
+                        location: location.clone(),
+                        package: package.name,
+                        version: package.version,
+                        cve: None,
+                    });
+                }
+            }
+        }
+        let findings: Vec<ScanEntry> =
+            serde_json::from_value(source["findings"].clone()).unwrap_or_default();
+        for entry in findings {
+            if query.matches_finding(&entry) {
+                matches.insert(FindMatch {
+                    location: location.clone(),
+                    package: entry.artifact.name.clone(),
+                    version: entry.artifact.version.clone(),
+                    cve: Some(entry.vulnerability.id.clone()),
+                });
+            }
+        }
+    }
+    Ok(matches.into_iter().collect())
+}
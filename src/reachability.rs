@@ -0,0 +1,74 @@
+//! Heuristics whether a language package is actually used, as opposed to only being declared,
+//! e.g. as development dependency in a lock file next to the application.
+
+use crate::scan::ScanArtifact;
+
+/// Path components of development only code, like tests, examples and documentation.
+const DEV_COMPONENTS: [&str; 8] = [
+    "test",
+    "tests",
+    "__tests__",
+    "spec",
+    "examples",
+    "docs",
+    ".github",
+    "fixtures",
+];
+
+/// Manifests declaring only development dependencies.
+const DEV_MANIFESTS: [&str; 4] = [
+    "requirements-dev.txt",
+    "dev-requirements.txt",
+    "requirements-test.txt",
+    "test-requirements.txt",
+];
+
+/// Whether the package is likely used: `false` if it is only found in development files like
+/// tests, `true` if it is installed where the runtime loads it from or compiled into a binary,
+/// and `unknown` if it is only declared in a manifest or lock file.
+pub fn likely_used(artifact: &ScanArtifact) -> &'static str {
+    if artifact.ecosystem() == "os" {
+        return "true";
+    }
+    let paths = artifact
+        .locations
+        .iter()
+        .map(|location| location.path.as_str());
+    if !artifact.locations.is_empty() && paths.clone().all(development) {
+        "false"
+    } else if paths
+        .clone()
+        .any(|path| loaded(&artifact.artifact_type, path))
+    {
+        "true"
+    } else {
+        "unknown"
+    }
+}
+
+/// Whether a package of this type found at this path is installed where a runtime loads it
+/// from.
+fn loaded(artifact_type: &str, path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    // Packages inside node_modules are resolvable by `require`, unlike lock file entries.
+    (path.contains("/node_modules/") && file == "package.json")
+        // Installed Python distributions, importable from site-packages.
+        || path.contains(".dist-info/")
+        || path.contains(".egg-info")
+        // Archives on the class path and installed gems.
+        || path.ends_with(".jar")
+        || path.ends_with(".war")
+        || path.contains("/specifications/")
+        // Go and Rust binaries only contain the modules they were built from.
+        || (matches!(artifact_type, "go-module" | "rust-crate")
+            && !matches!(file, "go.mod" | "go.sum" | "Cargo.lock" | "Cargo.toml"))
+}
+
+/// Whether a path only belongs to development, e.g. tests or a development requirements file.
+fn development(path: &str) -> bool {
+    let mut components = path.split('/');
+    components.any(|component| DEV_COMPONENTS.contains(&component))
+        || DEV_MANIFESTS
+            .iter()
+            .any(|manifest| path.ends_with(&format!("/{manifest}")) || path == *manifest)
+}
@@ -0,0 +1,125 @@
+//! Recording of the raw outputs of scanners and responses of the docker API, to reproduce
+//! problems of a run offline, e.g. parsing failures only happening on a user's host.
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Output},
+};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::process::Command;
+use tracing::debug;
+
+use crate::fs::write_atomic;
+
+/// Set by `--record` and `--replay`, not part of the config file.
+#[derive(Clone, Debug)]
+pub enum Recording {
+    /// Save scanner outputs and docker responses to the directory.
+    Record(PathBuf),
+    /// Use the outputs and responses saved in the directory instead of running scanners and
+    /// talking to docker.
+    Replay(PathBuf),
+}
+
+/// File of a recording, with characters not allowed in file names on all platforms, like the
+/// colon in image ids, replaced. Keys may contain `/` to group recordings in directories.
+fn path(dir: &Path, key: &str, extension: &str) -> PathBuf {
+    let key: String = key
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '/' => c,
+            _ => '_',
+        })
+        .collect();
+    dir.join(format!("{key}.{extension}"))
+}
+
+/// The recorded output of a command, when replaying.
+pub fn replayed(recording: Option<&Recording>, key: &str) -> Option<Result<Output>> {
+    let Some(Recording::Replay(dir)) = recording else {
+        return None;
+    };
+    debug!(key, "replaying recorded output");
+    let read = || -> Result<Output> {
+        let read = |extension| {
+            let path = path(dir, key, extension);
+            std::fs::read(&path).with_context(|| format!("No recording at {}", path.display()))
+        };
+        let code: i32 = String::from_utf8(read("status")?)?.trim().parse()?;
+        Ok(Output {
+            status: exit_status(code),
+            stdout: read("stdout")?,
+            stderr: read("stderr")?,
+        })
+    };
+    Some(read())
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// Save the output of a command, when recording.
+pub fn record(recording: Option<&Recording>, key: &str, output: &Output) -> Result<()> {
+    let Some(Recording::Record(dir)) = recording else {
+        return Ok(());
+    };
+    debug!(key, "recording output");
+    let status = path(dir, key, "status");
+    std::fs::create_dir_all(status.parent().unwrap())?;
+    // Processes killed by a signal have no exit code.
+    let code = output.status.code().unwrap_or(-1);
+    write_atomic(&status, code.to_string())?;
+    write_atomic(&path(dir, key, "stdout"), &output.stdout)?;
+    write_atomic(&path(dir, key, "stderr"), &output.stderr)
+}
+
+/// Run the command and return its output, or replay the recorded output.
+pub async fn output(
+    recording: Option<&Recording>,
+    key: &str,
+    command: &mut Command,
+) -> Result<Output> {
+    if let Some(output) = replayed(recording, key) {
+        return output;
+    }
+    let output = command.output().await?;
+    record(recording, key, &output)?;
+    Ok(output)
+}
+
+/// Fetch a response, e.g. of the docker API, or replay the recorded response.
+pub async fn response<T, F>(recording: Option<&Recording>, key: &str, fetch: F) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T>>,
+{
+    match recording {
+        Some(Recording::Replay(dir)) => {
+            debug!(key, "replaying recorded response");
+            let path = path(dir, key, "json");
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("No recording at {}", path.display()))?;
+            Ok(serde_json::from_reader(file)?)
+        }
+        Some(Recording::Record(dir)) => {
+            let response = fetch.await?;
+            let path = path(dir, key, "json");
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            write_atomic(&path, serde_json::to_vec_pretty(&response)?)?;
+            Ok(response)
+        }
+        None => fetch.await,
+    }
+}
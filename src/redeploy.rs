@@ -0,0 +1,127 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Source, scan::Scan};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FixedInNewerTag {
+    /// Export which findings are fixed in a newer tag of the same image repository.
+    Annotate,
+    /// Additionally leave those findings out of the vulnerability metrics and reports.
+    Suppress,
+}
+
+/// A finding of a source, identified by vulnerability id and package name.
+pub type FindingKey = (Source, String, String);
+
+/// Findings of images which don't occur in a newer tag of the same repository running on this
+/// host, mapped to the newest such tag. These can be fixed by redeploying.
+pub fn fixed_in_newer_tags(scans: &HashMap<Source, Scan>) -> HashMap<FindingKey, String> {
+    let mut repositories: HashMap<&str, Vec<(&Source, &str, &Scan)>> = HashMap::new();
+    for (source, scan) in scans {
+        let Source::DockerImage { name, .. } = source else {
+            continue;
+        };
+        // Tags without a version, like `latest`, can't be ordered against the others.
+        if let Some((repository, tag)) = split_tag(name).filter(|(_, tag)| is_version(tag)) {
+            repositories
+                .entry(repository)
+                .or_default()
+                .push((source, tag, scan));
+        }
+    }
+
+    let mut fixed = HashMap::new();
+    for images in repositories.values() {
+        for (source, tag, scan) in images {
+            let mut newer = images
+                .iter()
+                .filter(|(_, other, _)| compare_tags(other, tag) == Ordering::Greater)
+                .collect::<Vec<_>>();
+            newer.sort_by(|(_, a, _), (_, b, _)| compare_tags(b, a));
+
+            for entry in &scan.matches {
+                let affected = |scan: &Scan| {
+                    scan.matches.iter().any(|other| {
+                        other.vulnerability.id == entry.vulnerability.id
+                            && other.artifact.name == entry.artifact.name
+                    })
+                };
+                if let Some((_, newer_tag, _)) = newer.iter().find(|(_, _, scan)| !affected(scan)) {
+                    fixed.insert(
+                        (
+                            (*source).clone(),
+                            entry.vulnerability.id.clone(),
+                            entry.artifact.name.clone(),
+                        ),
+                        newer_tag.to_string(),
+                    );
+                }
+            }
+        }
+    }
+    fixed
+}
+
+/// Remove findings that are fixed in a newer tag.
+pub fn suppress(scans: &mut HashMap<Source, Scan>, fixed: &HashMap<FindingKey, String>) {
+    for (source, scan) in scans.iter_mut() {
+        scan.matches.retain(|entry| {
+            !fixed.contains_key(&(
+                source.clone(),
+                entry.vulnerability.id.clone(),
+                entry.artifact.name.clone(),
+            ))
+        });
+    }
+}
+
+/// Split an image reference like `registry:5000/app:1.2` into repository and tag. Images
+/// referenced by digest have no tag.
+fn split_tag(name: &str) -> Option<(&str, &str)> {
+    let (repository, tag) = name.rsplit_once(':')?;
+    if tag.contains('/') || repository.ends_with("@sha256") {
+        return None;
+    }
+    Some((repository, tag))
+}
+
+/// Whether the tag is a version like `1.2` or `v1.2.3-alpine`, as opposed to tags like
+/// `latest` or `main`.
+fn is_version(tag: &str) -> bool {
+    tag.strip_prefix('v')
+        .unwrap_or(tag)
+        .starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Compare tags like versions: runs of digits are compared numerically, everything else
+/// lexicographically, so `1.10` is newer than `1.9`. With equal common parts, the tag with
+/// more parts is newer, so `1.2.1` is newer than `1.2`.
+pub(crate) fn compare_tags(a: &str, b: &str) -> Ordering {
+    let runs = |tag: &str| {
+        let mut runs: Vec<String> = Vec::new();
+        for c in tag.chars() {
+            match runs.last_mut() {
+                Some(run) if run.chars().last().unwrap().is_ascii_digit() == c.is_ascii_digit() => {
+                    run.push(c)
+                }
+                _ => runs.push(c.to_string()),
+            }
+        }
+        runs
+    };
+    let (a, b) = (runs(a), runs(b));
+    for (a, b) in a.iter().zip(b.iter()) {
+        let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
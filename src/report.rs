@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::debug;
+
+use crate::{
+    config::{Config, Source, Tags},
+    firmware::Firmware,
+    fs::{create_parent, write_atomic},
+    grype_db::DbStatus,
+    hashes::{binary_hashes, BinaryHash},
+    malicious::{
+        suspicious_packages, MaliciousPackages, MaliciousPackagesConfig, SuspiciousPackage,
+    },
+    origins::{package_origins, PackageOrigin, PackageOriginConfig},
+    sbom::{Sbom, Tool},
+    scan::{FixState, Scan, ScanEntry},
+    severity::Severity,
+    systemd::ServiceBinary,
+};
+
+#[derive(Serialize, Debug)]
+pub struct BuildCacheUsage {
+    pub records: usize,
+    pub bytes: u64,
+    /// Size of the records neither in use nor shared, which `docker builder prune` removes.
+    pub reclaimable_bytes: u64,
+}
+
+/// Machine readable results of a run, for hooks and other tooling.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    /// Identifies the run, also exported in the `ssce_run_info` metric.
+    pub run_id: String,
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub grype_db_built: Option<DateTime<Utc>>,
+    /// Whether the results were restored from a previous run on daemon startup instead of
+    /// being produced by this run.
+    pub stale: bool,
+    /// Containers skipped for living shorter than `schedule.min_container_age`.
+    pub short_lived_containers_skipped: usize,
+    /// Usage of docker's build cache, see `build_cache`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_cache: Option<BuildCacheUsage>,
+    pub summary: Summary,
+    pub sources: Vec<SourceReport>,
+    /// Sources which were due in this run, but for which no SBOM could be created or which
+    /// couldn't be scanned.
+    pub failed: Vec<Source>,
+    /// Sources which were due in this run, but were deferred to the next one as the host was
+    /// saturated, see `load_guard`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deferred: Vec<Source>,
+    /// Firmware of the host, see `firmware_inventory`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub firmware: Vec<Firmware>,
+    /// Binaries of systemd services, see `systemd_services`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub services: Vec<ServiceBinary>,
+    /// Images with the most findings, fixable critical findings and packages, see
+    /// `worst_offenders`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worst_offenders: Option<WorstOffenders>,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct WorstOffenders {
+    pub by_findings: Vec<ImageRanking>,
+    pub by_fixable_critical: Vec<ImageRanking>,
+    pub by_packages: Vec<ImageRanking>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ImageRanking {
+    pub name: String,
+    pub tags: Tags,
+    pub findings: usize,
+    pub fixable_critical: usize,
+    pub packages: usize,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct Summary {
+    pub sources: usize,
+    pub packages: usize,
+    pub findings: usize,
+    pub findings_by_severity: BTreeMap<Severity, usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SourceReport {
+    pub source: Source,
+    pub tags: Tags,
+    pub packages: usize,
+    pub sbom_tool: Option<Tool>,
+    pub scan_tool: Option<Tool>,
+    /// Names of the containers using the image, see `container_names`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub containers: Vec<String>,
+    /// Paths of the files each package was found in, see `report_package_paths`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub package_paths: Vec<PackagePaths>,
+    /// Supplier, originator and download location of packages, see `package_origins`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub package_origins: Vec<PackageOrigin>,
+    /// Known malicious packages and possible typosquats, see `malicious_packages`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suspicious_packages: Vec<SuspiciousPackage>,
+    /// SHA-256 digests of the cataloged files, see `binary_hashes`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub binary_hashes: Vec<BinaryHash>,
+    /// Names and versions of the packages, for `ssce find`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub package_versions: Vec<PackageVersion>,
+    pub findings: Vec<ScanEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PackageVersion {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PackagePaths {
+    pub name: String,
+    pub version: String,
+    pub purl: Option<String>,
+    pub paths: Vec<String>,
+}
+
+/// Sources of the report written by the last run, by their name, for queries from other
+/// processes.
+pub fn read_report_sources(config: &Config) -> Result<Vec<(String, Value)>> {
+    let path = config.report_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut report: Value = serde_json::from_slice(&std::fs::read(&path)?)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let Value::Array(sources) = report["sources"].take() else {
+        return Ok(Vec::new());
+    };
+    Ok(sources
+        .into_iter()
+        .map(|source| {
+            let name = serde_json::from_value::<Source>(source["source"].clone()).map_or_else(
+                |_| source["source"].to_string(),
+                |source| source.to_string(),
+            );
+            (name, source)
+        })
+        .collect())
+}
+
+/// Identifier of a run started at the given time, unique per host.
+pub fn run_id(started: DateTime<Utc>) -> String {
+    format!(
+        "{}-{}",
+        started.format("%Y%m%dT%H%M%SZ"),
+        std::process::id()
+    )
+}
+
+impl Report {
+    pub fn new(
+        run_id: String,
+        started: DateTime<Utc>,
+        db_status: Option<&DbStatus>,
+        sources: &HashMap<Source, Tags>,
+        sboms: &HashMap<Source, Value>,
+        scans: &HashMap<Source, Scan>,
+        failed: Vec<Source>,
+    ) -> Self {
+        let mut summary = Summary::default();
+        let mut reports = Vec::new();
+        for (source, sbom) in sboms {
+            let sbom = serde_json::from_value::<Sbom>(sbom.clone()).ok();
+            let packages = sbom
+                .as_ref()
+                .map(|sbom| sbom.packages.len())
+                .unwrap_or_default();
+            let package_versions = sbom
+                .iter()
+                .flat_map(|sbom| &sbom.packages)
+                .map(|package| PackageVersion {
+                    name: package.name.clone(),
+                    version: package.versionInfo.clone(),
+                })
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            let findings = scans
+                .get(source)
+                .map(|scan| scan.matches.clone())
+                .unwrap_or_default();
+
+            summary.sources += 1;
+            summary.packages += packages;
+            summary.findings += findings.len();
+            for entry in &findings {
+                *summary
+                    .findings_by_severity
+                    .entry(entry.vulnerability.severity)
+                    .or_default() += 1;
+            }
+
+            reports.push(SourceReport {
+                source: source.clone(),
+                tags: sources.get(source).cloned().unwrap_or_default(),
+                packages,
+                sbom_tool: sbom.as_ref().and_then(Sbom::tool),
+                scan_tool: scans.get(source).map(|scan| scan.descriptor.clone()),
+                containers: Vec::new(),
+                package_paths: Vec::new(),
+                package_origins: Vec::new(),
+                suspicious_packages: Vec::new(),
+                binary_hashes: Vec::new(),
+                package_versions,
+                findings,
+            });
+        }
+        reports.sort_by_key(|report| report.source.to_string());
+
+        Self {
+            run_id,
+            started,
+            finished: Utc::now(),
+            grype_db_built: db_status.map(|status| status.built),
+            stale: false,
+            short_lived_containers_skipped: 0,
+            build_cache: None,
+            summary,
+            sources: reports,
+            failed,
+            deferred: Vec::new(),
+            firmware: Vec::new(),
+            services: Vec::new(),
+            worst_offenders: None,
+        }
+    }
+
+    /// Rank the images by findings, fixable critical findings and packages, keeping the first
+    /// `count` of each ranking.
+    pub fn rank_images(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let images: Vec<ImageRanking> = self
+            .sources
+            .iter()
+            .filter_map(|report| match &report.source {
+                Source::DockerImage { name, .. } => Some(ImageRanking {
+                    name: name.clone(),
+                    tags: report.tags.clone(),
+                    findings: report.findings.len(),
+                    fixable_critical: report
+                        .findings
+                        .iter()
+                        .filter(|entry| entry.vulnerability.severity == Severity::Critical)
+                        .filter(|entry| entry.vulnerability.fix.state == FixState::Fixed)
+                        .count(),
+                    packages: report.packages,
+                }),
+                _ => None,
+            })
+            .collect();
+        let ranking = |key: fn(&ImageRanking) -> usize| {
+            let mut ranking: Vec<ImageRanking> = images
+                .iter()
+                .filter(|image| key(image) > 0)
+                .cloned()
+                .collect();
+            ranking.sort_by(|a, b| key(b).cmp(&key(a)).then_with(|| a.name.cmp(&b.name)));
+            ranking.truncate(count);
+            ranking
+        };
+        self.worst_offenders = Some(WorstOffenders {
+            by_findings: ranking(|image| image.findings),
+            by_fixable_critical: ranking(|image| image.fixable_critical),
+            by_packages: ranking(|image| image.packages),
+        });
+    }
+
+    /// Add the names of the containers using each image, by image id.
+    pub fn add_containers(&mut self, containers: &HashMap<String, Vec<String>>) {
+        for source in &mut self.sources {
+            if let Source::DockerImage { id, .. } = &source.source {
+                source.containers = containers.get(id).cloned().unwrap_or_default();
+            }
+        }
+    }
+
+    /// Add the paths of the files each package was found in, from the SBOM of each source.
+    pub fn add_package_paths(&mut self, sboms: &HashMap<Source, Value>) {
+        for source in &mut self.sources {
+            let Some(sbom) = sboms
+                .get(&source.source)
+                .and_then(|sbom| serde_json::from_value::<Sbom>(sbom.clone()).ok())
+            else {
+                continue;
+            };
+            source.package_paths = sbom
+                .packages
+                .iter()
+                .filter(|package| !package.locations().is_empty())
+                .map(|package| PackagePaths {
+                    name: package.name.clone(),
+                    version: package.versionInfo.clone(),
+                    purl: package.purl().map(str::to_owned),
+                    paths: package.locations().into_iter().map(str::to_owned).collect(),
+                })
+                .collect();
+        }
+    }
+
+    /// Add the origins of the packages, from the SBOM of each source.
+    pub fn add_package_origins(
+        &mut self,
+        config: &PackageOriginConfig,
+        sboms: &HashMap<Source, Value>,
+    ) {
+        for source in &mut self.sources {
+            if let Some(sbom) = sboms
+                .get(&source.source)
+                .and_then(|sbom| serde_json::from_value::<Sbom>(sbom.clone()).ok())
+            {
+                source.package_origins = package_origins(config, &sbom);
+            }
+        }
+    }
+
+    /// Add the known malicious packages and possible typosquats, from the SBOM of each source.
+    pub fn add_suspicious_packages(
+        &mut self,
+        config: &MaliciousPackagesConfig,
+        malicious: &MaliciousPackages,
+        sboms: &HashMap<Source, Value>,
+    ) {
+        for source in &mut self.sources {
+            if let Some(sbom) = sboms
+                .get(&source.source)
+                .and_then(|sbom| serde_json::from_value::<Sbom>(sbom.clone()).ok())
+            {
+                source.suspicious_packages =
+                    suspicious_packages(config, malicious, &source.source, &sbom);
+            }
+        }
+    }
+
+    /// Add the SHA-256 digests of the cataloged files, from the SBOM of each source.
+    pub fn add_binary_hashes(&mut self, sboms: &HashMap<Source, Value>) {
+        for source in &mut self.sources {
+            if let Some(sbom) = sboms
+                .get(&source.source)
+                .and_then(|sbom| serde_json::from_value::<Sbom>(sbom.clone()).ok())
+            {
+                source.binary_hashes = binary_hashes(&sbom);
+            }
+        }
+    }
+
+    pub fn write(&self, config: &Config) -> Result<()> {
+        let path = config.report_path();
+        debug!(?path, "writing run report");
+        create_parent(&path)?;
+        write_atomic(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+//! A complete run, from discovering the sources to exporting the results, as done by `ssce
+//! scan` and on every iteration of `ssce daemon`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::Utc;
+use tracing::{info, warn};
+
+#[cfg(feature = "notifiers")]
+use crate::tickets::open_tickets;
+use crate::{
+    admission::write_verdicts,
+    applications::discover_applications,
+    attestation::write_attestations,
+    checkpoint::Checkpoint,
+    compare::compare_scanners,
+    config::{merge_tags, Config, Source, Tags},
+    cve_details::write_cve_details,
+    dependency_check::write_dependency_check_reports,
+    discovery::run_discovery_commands,
+    docker::{
+        build_cache_usage, get_dangling_images, get_docker_images, image_containers, DockerClient,
+    },
+    email::mail_reports,
+    error::SsceError,
+    exploits::{enrich_exploits, load_exploits},
+    firmware::collect_firmware,
+    github::submit_dependency_snapshots,
+    gitlab::write_gitlab_report,
+    grype_db::update_db,
+    history::record_history,
+    hooks::run_hooks,
+    load::LoadGuard,
+    lxd::get_lxd_instances,
+    macos::write_plist_summary,
+    malicious::load_malicious_packages,
+    metrics::encode_metrics,
+    notify::notify_violations,
+    policy::check_policy,
+    preflight::check_space,
+    proxy::Integration,
+    redeploy::{fixed_in_newer_tags, suppress, FixedInNewerTag},
+    report::{run_id, Report},
+    sbom::{clean, create_sboms, export_sboms, inbox_sources},
+    scan::scan,
+    schedule::{PreviousRun, Scheduler},
+    sink::{write_outputs, Output},
+    systemd::service_binaries,
+    vdr::write_vdrs,
+    webhook::registry_sources,
+};
+
+/// All sources to scan with their tags: images of containers, host directories, CI
+/// workspaces, applications, LXD containers, vendor SBOMs and the sources of discovery
+/// commands.
+pub async fn discover_sources(
+    config: &Config,
+    docker: &DockerClient,
+) -> Result<HashMap<Source, Tags>> {
+    let mut sources = get_docker_images(config, docker).await?;
+    sources.extend(get_dangling_images(config, docker).await?);
+    sources.extend(config.directory_sources());
+
+    info!("Discovering application dependency trees");
+    sources.extend(discover_applications(config));
+
+    info!("Fetching LXD containers");
+    sources.extend(get_lxd_instances(config).await?);
+
+    sources.extend(inbox_sources(config));
+    sources.extend(registry_sources(config));
+
+    info!("Running discovery commands");
+    for (source, tags) in run_discovery_commands(config).await {
+        merge_tags(sources.entry(source).or_default(), tags);
+    }
+    Ok(sources)
+}
+
+/// Export the results of the previous daemon right away, marked as stale, instead of exporting
+/// nothing until the first run completes.
+pub async fn warm_start(
+    config: &Config,
+    scheduler: &mut Scheduler,
+    previous: PreviousRun,
+) -> Result<()> {
+    info!("Export the results of the previous run");
+    let current = previous.sources.keys().cloned().collect::<Vec<_>>();
+    let (sboms, mut scans) = scheduler.results(&current);
+    let fixed_in_newer_tag = match config.fixed_in_newer_tag {
+        Some(mode) => {
+            let fixed = fixed_in_newer_tags(&scans);
+            if mode == FixedInNewerTag::Suppress {
+                suppress(&mut scans, &fixed);
+            }
+            fixed
+        }
+        None => HashMap::new(),
+    };
+    let mut report = Report::new(
+        previous.run_id,
+        previous.finished,
+        None,
+        &previous.sources,
+        &sboms,
+        &scans,
+        Vec::new(),
+    );
+    report.stale = true;
+    let metrics = encode_metrics(
+        config,
+        &previous.sources,
+        sboms,
+        scans,
+        &fixed_in_newer_tag,
+        &scheduler.agreements(),
+        &report,
+    )?;
+    write_outputs(
+        config,
+        &Output {
+            metrics: &metrics,
+            report: &report,
+            client: config.proxy.client(Integration::Sinks)?,
+        },
+    )
+    .await;
+    Ok(())
+}
+
+/// Scan all sources which are due according to the scheduler, and export the results. Use
+/// `Scheduler::default()` for a single run in which all sources are due.
+pub async fn run_scan(config: &Config, scheduler: &mut Scheduler) -> Result<Report> {
+    let started = Utc::now();
+
+    info!("Fetching docker images that are used in containers from docker");
+    let docker = DockerClient::new(config).map_err(SsceError::Discovery)?;
+    let sources = discover_sources(config, &docker)
+        .await
+        .map_err(SsceError::Discovery)?;
+    let containers = image_containers(config, &docker)
+        .await
+        .map_err(SsceError::Discovery)?;
+
+    let current = sources.keys().cloned().collect::<Vec<_>>();
+    let due = current
+        .iter()
+        .filter(|source| scheduler.due(&config.schedule, source, started))
+        .cloned()
+        .collect();
+
+    info!("Check free disk space");
+    let due = check_space(config, &docker, due).await?;
+
+    let checkpoint = Checkpoint::open(config)?;
+
+    info!("Start generating SBOMs");
+    let guard = LoadGuard::new(config);
+    let sboms = create_sboms(config, &due, &checkpoint, &guard).await?;
+
+    info!("Export SBOMs in additional formats");
+    export_sboms(config, &sboms).await?;
+
+    info!("Submit dependency snapshots to GitHub");
+    submit_dependency_snapshots(config, &docker, &sboms).await?;
+
+    info!("Update the vulnerability database");
+    let db_status = update_db(config).await?;
+
+    info!("Compare generated SBOMs against vulnerability databases");
+    let scans = scan(config, &sboms, &checkpoint).await?;
+
+    info!("Record results in history");
+    record_history(config, &sboms, &scans)?;
+
+    let deferred = guard.deferred();
+    let failed = due
+        .iter()
+        .filter(|source| !deferred.contains(source))
+        .filter(|source| !sboms.contains_key(*source) || !scans.contains_key(*source))
+        .cloned()
+        .collect();
+
+    info!("Compare scan results with trivy");
+    let agreements = compare_scanners(config, &sboms, &scans).await;
+
+    scheduler.record(&sboms, &scans, started);
+    scheduler.record_agreements(agreements);
+    let (sboms, mut scans) = scheduler.results(&current);
+    let agreements = scheduler.agreements();
+
+    let fixed_in_newer_tag = match config.fixed_in_newer_tag {
+        Some(mode) => {
+            let fixed = fixed_in_newer_tags(&scans);
+            if mode == FixedInNewerTag::Suppress {
+                suppress(&mut scans, &fixed);
+            }
+            fixed
+        }
+        None => HashMap::new(),
+    };
+
+    info!("Look up public exploits");
+    enrich_exploits(&mut scans, &load_exploits(config).await);
+
+    info!("Write GitLab dependency scanning report");
+    write_gitlab_report(config, started, &scans)?;
+
+    info!("Write property list summary");
+    if let Err(e) = write_plist_summary(config, &sboms, &scans) {
+        warn!("Failed to write the property list summary: {e:?}");
+    }
+
+    info!("Clean up old cache files");
+    clean(config, false).await?;
+
+    info!("Write run report");
+    let mut report = Report::new(
+        run_id(started),
+        started,
+        db_status.as_ref(),
+        &sources,
+        &sboms,
+        &scans,
+        failed,
+    );
+    report.add_containers(&containers);
+    report.short_lived_containers_skipped = docker.short_lived_skipped();
+    report.deferred = deferred;
+    report.build_cache = build_cache_usage(config, &docker).await;
+    report.firmware = collect_firmware(config).await;
+    report.services = service_binaries(config).await;
+    report.rank_images(config.worst_offenders);
+    if config.report_package_paths {
+        report.add_package_paths(&sboms);
+    }
+    if let Some(origins) = &config.package_origins {
+        report.add_package_origins(origins, &sboms);
+    }
+    if config.binary_hashes {
+        report.add_binary_hashes(&sboms);
+    }
+    if let Some(malicious) = &config.malicious_packages {
+        report.add_suspicious_packages(malicious, &load_malicious_packages(malicious), &sboms);
+    }
+    report.write(config)?;
+    write_attestations(config, &report).await?;
+    write_cve_details(config, &scans)?;
+    write_vdrs(config, &sboms, &scans)?;
+    write_dependency_check_reports(config, &scans)?;
+
+    info!("Format SBOM and vulnerability data as metrics");
+    let metrics = encode_metrics(
+        config,
+        &sources,
+        sboms,
+        scans,
+        &fixed_in_newer_tag,
+        &agreements,
+        &report,
+    )?;
+    write_outputs(
+        config,
+        &Output {
+            metrics: &metrics,
+            report: &report,
+            client: config.proxy.client(Integration::Sinks)?,
+        },
+    )
+    .await;
+
+    info!("Run hooks");
+    run_hooks(config, &report).await;
+
+    let violations = check_policy(config, &report)?;
+    notify_violations(config, &violations).await;
+    #[cfg(feature = "notifiers")]
+    open_tickets(config, &violations).await;
+    mail_reports(config, &report).await;
+    write_verdicts(config, &report, &violations.active).await?;
+
+    checkpoint.finish()?;
+    Ok(report)
+}
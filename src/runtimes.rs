@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDate;
+
+use crate::{config::Config, sbom::Sbom};
+
+/// End of life dates of language runtime release cycles. The `runtime_eol` option adds cycles
+/// and overrides these dates.
+const EOL: &[(&str, &str, &str)] = &[
+    ("python", "2.7", "2020-01-01"),
+    ("python", "3.6", "2021-12-23"),
+    ("python", "3.7", "2023-06-27"),
+    ("python", "3.8", "2024-10-07"),
+    ("python", "3.9", "2025-10-31"),
+    ("python", "3.10", "2026-10-31"),
+    ("python", "3.11", "2027-10-31"),
+    ("python", "3.12", "2028-10-31"),
+    ("python", "3.13", "2029-10-31"),
+    ("node", "14", "2023-04-30"),
+    ("node", "16", "2023-09-11"),
+    ("node", "18", "2025-04-30"),
+    ("node", "19", "2023-06-01"),
+    ("node", "20", "2026-04-30"),
+    ("node", "21", "2024-06-01"),
+    ("node", "22", "2027-04-30"),
+    ("node", "23", "2025-06-01"),
+    ("node", "24", "2028-04-30"),
+    ("java", "8", "2026-11-30"),
+    ("java", "11", "2027-10-31"),
+    ("java", "17", "2029-10-31"),
+    ("java", "21", "2029-12-31"),
+    ("ruby", "2.7", "2023-03-31"),
+    ("ruby", "3.0", "2024-04-23"),
+    ("ruby", "3.1", "2025-03-31"),
+    ("ruby", "3.2", "2026-03-31"),
+    ("ruby", "3.3", "2027-03-31"),
+    ("ruby", "3.4", "2028-03-31"),
+    ("go", "1.20", "2024-02-06"),
+    ("go", "1.21", "2024-08-13"),
+    ("go", "1.22", "2025-02-11"),
+    ("go", "1.23", "2025-08-12"),
+    ("go", "1.24", "2026-02-10"),
+];
+
+/// End of life dates by `runtime:cycle`, e.g. `python:3.9`.
+pub type RuntimeEol = BTreeMap<String, NaiveDate>;
+
+/// A language runtime found in an SBOM.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Runtime {
+    /// One of `python`, `node`, `java`, `ruby` and `go`.
+    pub runtime: String,
+    pub version: String,
+    /// Release cycle the end of life date refers to, e.g. `3.11` for python or `20` for node.
+    pub cycle: String,
+}
+
+impl Runtime {
+    pub fn eol_date(&self, config: &Config) -> Option<NaiveDate> {
+        let key = format!("{}:{}", self.runtime, self.cycle);
+        if let Some(date) = config.runtime_eol.get(&key) {
+            return Some(*date);
+        }
+        let date = EOL
+            .iter()
+            .find(|(runtime, cycle, _)| *runtime == self.runtime && *cycle == self.cycle)
+            .map(|(_, _, date)| *date)?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    }
+}
+
+/// Language runtimes in an SBOM, by the packages of the interpreters, toolchains and the go
+/// standard library compiled into binaries.
+pub fn detect_runtimes(sbom: &Sbom) -> Vec<Runtime> {
+    sbom.packages
+        .iter()
+        .filter_map(|package| {
+            let runtime = runtime_of(&package.name)?;
+            let version = numeric_version(&package.versionInfo)?;
+            let cycle = cycle(runtime, &version)?;
+            Some(Runtime {
+                runtime: runtime.to_owned(),
+                version,
+                cycle,
+            })
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn runtime_of(name: &str) -> Option<&'static str> {
+    let versioned = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| rest.chars().all(|c| c.is_ascii_digit() || c == '.'))
+    };
+    if versioned("python") {
+        Some("python")
+    } else if versioned("ruby") {
+        Some("ruby")
+    } else if name == "node" || name == "nodejs" {
+        Some("node")
+    } else if name == "java"
+        || name == "openjdk"
+        || (name.starts_with("openjdk-") && (name.contains("-jre") || name.contains("-jdk")))
+    {
+        Some("java")
+    } else if name == "stdlib" || name == "go" || name == "golang" || versioned("golang-") {
+        Some("go")
+    } else {
+        None
+    }
+}
+
+/// The numeric part of a version, without epoch, `go` prefix or distribution suffixes, e.g.
+/// `3.11.2` for `3.11.2-6+deb12u1`.
+fn numeric_version(version: &str) -> Option<String> {
+    let version = version
+        .split_once(':')
+        .map_or(version, |(_, version)| version);
+    let version = version.strip_prefix("go").unwrap_or(version);
+    let version = version
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == '_')
+        .collect::<String>();
+    let version = version.trim_end_matches('.');
+    (!version.is_empty()).then(|| version.to_owned())
+}
+
+fn cycle(runtime: &str, version: &str) -> Option<String> {
+    let mut parts = version.split(['.', '_']);
+    let major = parts.next()?;
+    match runtime {
+        // Java 8 and older are versioned as `1.8.0_392`.
+        "java" if major == "1" => parts.next().map(ToOwned::to_owned),
+        "java" | "node" => Some(major.to_owned()),
+        _ => Some(format!("{major}.{}", parts.next()?)),
+    }
+}
@@ -1,18 +1,39 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::OsString,
-    fs::File,
-    path::PathBuf,
+    fs::{File, Metadata},
+    path::{Component, Path, PathBuf},
+    process::{Output, Stdio},
     time::{Duration, SystemTime},
 };
 
-use anyhow::{Context, Result};
-use serde_json::Value;
-use tokio::process::Command;
-use tracing::debug;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{debug, warn};
 use walkdir::WalkDir;
 
-use crate::config::{Config, Source};
+use crate::{
+    checkpoint::Checkpoint,
+    ci::{cached_sbom, CI_CATALOGERS, CI_EXCLUDES},
+    config::{Config, Source, Tags},
+    disk_image::DiskImageMount,
+    distroless::{incomplete_reason, BINARY_CATALOGERS},
+    docker::platform,
+    error::SsceError,
+    fs::write_atomic,
+    ignore::ssceignore_excludes,
+    index::FileIndex,
+    limits::{annotate, apply_limits, LIMIT_ANNOTATION},
+    load::LoadGuard,
+    macos, nix,
+    progress::Progress,
+    proxy::Integration,
+    recording::output,
+    windows,
+    workspace::Workspace,
+};
 
 #[allow(non_snake_case)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -20,11 +41,167 @@ pub struct SbomEntry {
     pub name: String,
     #[serde(default)]
     pub versionInfo: String,
+    #[serde(default)]
+    pub externalRefs: Vec<ExternalRef>,
+    #[serde(default)]
+    pub sourceInfo: String,
+    /// SPDX supplier, originator and download location, see `origins`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supplier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub originator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downloadLocation: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checksums: Vec<Checksum>,
+}
+
+impl SbomEntry {
+    /// Paths the package was found at. syft lists them in the source info, e.g.
+    /// `acquired package info from installed java archive: /app/lib/log4j-core-2.14.1.jar`.
+    pub fn locations(&self) -> Vec<&str> {
+        self.sourceInfo
+            .split_once(": ")
+            .map(|(_, locations)| {
+                locations
+                    .split(", ")
+                    .filter(|location| !location.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn purl(&self) -> Option<&str> {
+        self.externalRefs
+            .iter()
+            .find(|reference| reference.referenceType == "purl")
+            .map(|reference| reference.referenceLocator.as_str())
+    }
+
+    /// Type of the package URL, e.g. `deb` or `golang`.
+    pub fn purl_type(&self) -> Option<&str> {
+        self.purl()?.strip_prefix("pkg:")?.split('/').next()
+    }
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExternalRef {
+    pub referenceType: String,
+    pub referenceLocator: String,
+}
+
+/// A checksum of a file or package, e.g. with algorithm `SHA256`.
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Checksum {
+    pub algorithm: String,
+    pub checksumValue: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SbomFile {
+    pub fileName: String,
+    #[serde(default)]
+    pub checksums: Vec<Checksum>,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Sbom {
     pub packages: Vec<SbomEntry>,
+    /// Files syft cataloged, with their digests, see `hashes`.
+    #[serde(default)]
+    pub files: Vec<SbomFile>,
+    #[serde(rename = "creationInfo", default)]
+    pub creation_info: CreationInfo,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Annotation {
+    #[serde(default)]
+    pub comment: String,
+}
+
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CreationInfo {
+    #[serde(default)]
+    pub creators: Vec<String>,
+}
+
+impl Sbom {
+    /// Name and version of the tool that created the SBOM, from creators like
+    /// `Tool: syft-1.2.3`.
+    pub fn tool(&self) -> Option<Tool> {
+        self.creation_info.creators.iter().find_map(|creator| {
+            let (name, version) = creator.strip_prefix("Tool:")?.trim().rsplit_once('-')?;
+            Some(Tool {
+                name: name.to_owned(),
+                version: version.to_owned(),
+            })
+        })
+    }
+
+    /// How the SBOM was obtained, e.g. `attestation`, see `METHOD_ANNOTATION`.
+    pub fn method(&self) -> Option<&str> {
+        self.annotations
+            .iter()
+            .find_map(|annotation| annotation.comment.strip_prefix(METHOD_ANNOTATION))
+    }
+
+    /// Limits of `sbom_limits` the SBOM exceeded, e.g. `packages`.
+    pub fn limits_exceeded(&self) -> impl Iterator<Item = &str> {
+        self.annotations
+            .iter()
+            .filter_map(|annotation| annotation.comment.strip_prefix(LIMIT_ANNOTATION))
+    }
+}
+
+/// Prefix of the SPDX annotation comment which records how the SBOM was obtained: from an
+/// `attestation` of the image, generated by `syft`, read from the `cache` or provided by a
+/// `vendor`.
+pub const METHOD_ANNOTATION: &str = "ssce sbom source: ";
+
+/// The SBOM annotated with the method, replacing the method of SBOMs stored along with other
+/// data, like the file index.
+fn with_method(mut sbom: Value, method: &str) -> Value {
+    if let Some(Value::Array(annotations)) = sbom.get_mut("annotations") {
+        annotations.retain(|annotation| {
+            !annotation["comment"]
+                .as_str()
+                .is_some_and(|comment| comment.starts_with(METHOD_ANNOTATION))
+        });
+    }
+    annotate(&mut sbom, format!("{METHOD_ANNOTATION}{method}"));
+    sbom
+}
+
+/// A tool which produced an SBOM or scan.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub version: String,
+}
+
+/// SPDX package entry for packages which are inventoried without syft.
+pub fn spdx_package(name: &str, version: &str, purl: &str) -> Value {
+    let id = format!("{name}-{version}")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    json!({
+        "SPDXID": format!("SPDXRef-Package-{id}"),
+        "name": name,
+        "versionInfo": version,
+        "downloadLocation": "NOASSERTION",
+        "externalRefs": [{
+            "referenceCategory": "PACKAGE-MANAGER",
+            "referenceType": "purl",
+            "referenceLocator": purl,
+        }],
+    })
 }
 
 /// Call syft for all running containers and create JSON SBOM.
@@ -32,81 +209,209 @@ pub struct Sbom {
 pub async fn create_sboms(
     config: &Config,
     sources: &Vec<Source>,
+    checkpoint: &Checkpoint,
+    guard: &LoadGuard,
 ) -> Result<HashMap<Source, Value>> {
     let mut sboms = HashMap::new();
+    let progress = Progress::new("Generating SBOMs", sources.len());
     for source in sources {
-        if config.generate_sboms {
+        progress.start(source);
+        if let Some(sbom) = checkpoint.sbom(source) {
+            sboms.insert(source.clone(), sbom);
+        } else if config.generate_sboms || matches!(source, Source::VendorSbom { .. }) {
+            if !guard.admit(source).await {
+                progress.inc();
+                continue;
+            }
             let res = create_sbom(config.clone(), source.clone()).await;
             match res {
-                Err(e) => println!("Error creating sbom: {e:?}"),
+                Err(error) => SsceError::Sbom {
+                    source: source.clone(),
+                    error,
+                }
+                .log(),
                 Ok((source, sbom)) => {
-                    sboms.insert(source, sbom);
+                    if let Some(sbom) = apply_limits(&config.sbom_limits, &source, sbom) {
+                        checkpoint.add_sbom(&source, &sbom)?;
+                        sboms.insert(source, sbom);
+                    }
                 }
             }
-        } else if let (Source::DockerImage { ref name, id: _ }, Some(sbom_path)) =
+        } else if let (Source::DockerImage { .. }, Some(sbom_path)) =
             (source, config.sbom_path(source))
         {
-            let res = get_sbom(name.into(), sbom_path).await;
+            let res = get_sbom(config, source, sbom_path).await;
             match res {
-                Err(e) => println!("Error loading sbom: {e:?}"),
+                Err(error) => SsceError::Sbom {
+                    source: source.clone(),
+                    error,
+                }
+                .log(),
                 Ok(sbom) => {
-                    sboms.insert(source.clone(), sbom);
+                    if let Some(sbom) = apply_limits(&config.sbom_limits, source, sbom) {
+                        sboms.insert(source.clone(), sbom);
+                    }
                 }
             }
         }
+        progress.inc();
     }
 
     Ok(sboms)
 }
 
-#[tracing::instrument(skip(sbom_path))]
-async fn get_sbom(scan_target: OsString, sbom_path: PathBuf) -> Result<Value> {
+#[tracing::instrument(skip(config, sbom_path))]
+async fn get_sbom(config: &Config, source: &Source, sbom_path: PathBuf) -> Result<Value> {
     if std::fs::metadata(&sbom_path).is_ok() {
         debug!("found cached sbom, reading and parsing it now");
         let sbom_file = File::open(&sbom_path)?;
         let parsed_sbom = serde_json::from_reader(sbom_file)?;
-        Ok(parsed_sbom)
+        Ok(with_method(parsed_sbom, "cache"))
     } else {
         debug!("Trying to get sbom from image attestations");
-        let mut command = Command::new("docker");
-        let arch = match std::env::consts::ARCH {
-            "x86_64" => "linux/amd64",
-            "aarch64" => "linux/arm64",
-            _ => "",
+        let Source::DockerImage { name, .. } = source else {
+            bail!("Only images have sbom attestations");
         };
+        let platform = platform(config, name);
+        let mut command = Command::new("docker");
 
         command
             .arg("buildx")
             .arg("imagetools")
             .arg("inspect")
-            .arg(scan_target)
+            .arg(name)
             .arg("--format")
-            .arg("{{ json .SBOM }}");
+            .arg("{{ json .SBOM }}")
+            .kill_on_drop(true);
+        config.proxy.get(Integration::Syft).apply(&mut command);
 
-        let output = command.output().await?;
+        let key = format!("docker/attestation-{}", source.slug());
+        let output = output(config.recording.as_ref(), &key, &mut command).await?;
         let output: Value = serde_json::from_slice(&output.stdout)?;
 
-        let parsed_output = match output.get(arch) {
+        // Multi-platform images have one attestation per platform, single platform images have
+        // the attestation at the top level.
+        let parsed_output = match output.get(&platform) {
             Some(v) => v.get("SPDX"),
             None => output.get("SPDX"),
         }
-        .context("Image does not have compatible sbom attestation")?;
+        .with_context(|| format!("Image does not have sbom attestation for {platform}"))?;
+
+        Ok(with_method(parsed_output.to_owned(), "attestation"))
+    }
+}
+
+/// Syft excludes are globs relative to the scanned directory, using forward slashes on all
+/// platforms.
+fn relative_exclude(exclude: &Path) -> String {
+    let components = exclude
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    format!("./{}", components.join("/"))
+}
+
+/// SBOMs provided by vendors in the inbox directory, for software that can't be scanned
+/// directly.
+pub fn inbox_sources(config: &Config) -> HashMap<Source, Tags> {
+    let Some(inbox) = &config.sbom_inbox else {
+        return HashMap::new();
+    };
+    WalkDir::new(inbox)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .map(|entry| {
+            (
+                Source::VendorSbom {
+                    path: entry.into_path(),
+                },
+                config.tags.clone(),
+            )
+        })
+        .collect()
+}
 
-        Ok(parsed_output.to_owned())
+/// Read a vendor SBOM. SPDX is used as is, other formats like CycloneDX are converted to SPDX
+/// with syft.
+#[tracing::instrument(skip(config))]
+async fn read_vendor_sbom(config: &Config, source: &Source, path: &Path) -> Result<Value> {
+    let sbom: Value = serde_json::from_reader(File::open(path)?)?;
+    if sbom.get("spdxVersion").is_some() {
+        return Ok(sbom);
     }
+
+    debug!("converting vendor sbom to spdx");
+    let mut command = Command::new("syft");
+    command
+        .arg("convert")
+        .arg(path)
+        .arg("--quiet")
+        .arg("-o")
+        .arg("spdx-json")
+        .kill_on_drop(true);
+    let key = format!("syft/{}", source.slug());
+    let output = output(config.recording.as_ref(), &key, &mut command).await?;
+    if !output.status.success() {
+        return Err(syft_failure(&output));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
 }
 
 #[tracing::instrument(skip(config))]
 async fn create_sbom(config: Config, source: Source) -> Result<(Source, Value)> {
     let source = source.clone();
+    let mut mount = None;
+    let platform = match source {
+        Source::DockerImage { ref name, .. } => Some(platform(&config, name)),
+        _ => None,
+    };
     let (scan_target, sbom_path): (OsString, Option<PathBuf>) = match source {
-        Source::DockerImage { ref name, id: _ } => (name.into(), config.sbom_path(&source)),
-        Source::HostDirectory { ref path } => (path.into(), config.sbom_path(&source)),
+        Source::DockerImage { ref name, .. } => (name.into(), config.sbom_path(&source)),
+        Source::HostDirectory { ref path } | Source::CiWorkspace { ref path } => {
+            (path.into(), config.sbom_path(&source))
+        }
+        Source::VendorSbom { ref path } => {
+            return Ok((
+                source.clone(),
+                with_method(read_vendor_sbom(&config, &source, path).await?, "vendor"),
+            ))
+        }
+        Source::DiskImage { ref path } => {
+            let disk_image = DiskImageMount::mount(&config, path).await?;
+            let scan_target = disk_image.path.clone().into();
+            mount = Some(disk_image);
+            (scan_target, config.sbom_path(&source))
+        }
+    };
+
+    let index = match source {
+        Source::HostDirectory { ref path } if config.directory_index.is_some() => {
+            let index = FileIndex::build(&config, path);
+            if let Some(sbom) = index.unchanged_sbom(&config, &source) {
+                return Ok((source, with_method(sbom, "cache")));
+            }
+            Some(index)
+        }
+        _ => None,
     };
 
-    if let Some(sbom_path) = sbom_path.clone() {
+    if let (Source::CiWorkspace { ref path }, Some(sbom_path)) = (&source, &sbom_path) {
+        if let Some(cached) = cached_sbom(&config, path, sbom_path) {
+            return Ok((source, with_method(cached, "cache")));
+        }
+    } else if let Some(sbom_path) = sbom_path.clone() {
         debug!("sbom is cacheable, checking for cached result");
-        if let Ok(parsed_cache) = get_sbom(scan_target.clone(), sbom_path).await {
+        if let Ok(parsed_cache) = get_sbom(&config, &source, sbom_path).await {
             return Ok((source, parsed_cache));
         }
     }
@@ -119,37 +424,313 @@ async fn create_sbom(config: Config, source: Source) -> Result<(Source, Value)>
         .arg("-o")
         .arg("spdx-json")
         .arg("--override-default-catalogers")
-        .arg("all")
-        .env("SYFT_PARALLELISM", "1");
+        .arg(match source {
+            Source::CiWorkspace { .. } => CI_CATALOGERS,
+            _ => "all",
+        })
+        .env("SYFT_PARALLELISM", "1")
+        .kill_on_drop(true);
+    if config.binary_hashes {
+        command.env("SYFT_FILE_METADATA_DIGESTS", "sha256");
+    }
+    config.proxy.get(Integration::Syft).apply(&mut command);
+
+    if let Some(platform) = &platform {
+        command.arg("--platform").arg(platform);
+    }
+
+    if let Some(java) = &config.java_archives {
+        java.apply(&mut command);
+    }
+
+    if matches!(
+        source,
+        Source::HostDirectory { .. } | Source::DiskImage { .. }
+    ) {
+        debug!("we're running against a file system, append excludes from the config file");
+        for exclude in &config.excludes {
+            command.arg("--exclude").arg(relative_exclude(exclude));
+        }
+        command
+            .arg("--exclude")
+            .arg(relative_exclude(&config.workspace_path()));
+    }
 
-    if matches!(source, Source::HostDirectory { .. }) {
-        debug!("we're running against a host directory, append excludes from the config file");
-        for exclude in config.excludes {
-            let mut relative_exclude = OsString::from(".");
-            relative_exclude.push(exclude);
-            command.arg("--exclude").arg(relative_exclude);
+    if let Source::CiWorkspace { .. } = source {
+        debug!("append excludes of the ci preset");
+        for exclude in CI_EXCLUDES {
+            command.arg("--exclude").arg(exclude);
         }
     }
 
+    if let Source::HostDirectory { ref path } | Source::CiWorkspace { ref path } = source {
+        debug!("append excludes from ignore files in the directory");
+        let mut skip = config.excludes.clone();
+        skip.push(config.workspace_path());
+        for exclude in ssceignore_excludes(path, &skip) {
+            command.arg("--exclude").arg(exclude);
+        }
+    }
+
+    let nix_system = source == Source::HostDirectory { path: "/".into() } && nix::enabled(&config);
+    let root_directory =
+        matches!(source, Source::HostDirectory { ref path } if path.parent().is_none());
+    let windows_system = cfg!(windows) && root_directory;
+    let macos_system = cfg!(target_os = "macos") && root_directory;
+    if nix_system {
+        debug!("host uses nix, the store is inventoried separately");
+        command.arg("--exclude").arg("./nix");
+    }
+
     debug!("running syft now");
-    let output = command.arg(scan_target).output().await?;
+    command.arg(&scan_target);
+    let workspace = Workspace::new(&config, &source, "syft")?;
+    workspace.apply(&mut command);
+    let key = format!("syft/{}", source.slug());
+    let syft = workspace.limit(async {
+        let output = output(config.recording.as_ref(), &key, &mut command).await?;
+        if !output.status.success() {
+            return Err(syft_failure(&output));
+        }
+        if !matches!(source, Source::DockerImage { .. }) {
+            return Ok(output.stdout);
+        }
+        add_binary_packages(
+            &config,
+            &source,
+            &scan_target,
+            platform.as_deref(),
+            &workspace,
+            output.stdout,
+        )
+        .await
+    });
+    let output = match (&config.shared_cache, &source) {
+        (Some(shared_cache), Source::DockerImage { .. }) => {
+            shared_cache
+                .get_or_create(&config.proxy, &source.cache_key(), syft)
+                .await
+        }
+        _ => syft.await,
+    };
+
+    if let Some(mount) = mount {
+        mount.unmount().await?;
+    }
+    let output = output?;
 
     if let Some(sbom_path) = sbom_path {
         debug!("sbom is cacheable, writing it to cache location");
         std::fs::create_dir_all(sbom_path.parent().unwrap())?;
-        std::fs::write(sbom_path, &output.stdout)?;
+        write_atomic(&sbom_path, &output)?;
     }
 
     debug!("parsing sbom for further processing");
-    let parsed_output = serde_json::from_slice(&output.stdout)?;
+    let mut parsed_output = with_method(serde_json::from_slice(&output)?, "syft");
+
+    if nix_system {
+        debug!("adding nix store packages to sbom");
+        match nix::nix_packages().await {
+            Ok(packages) => {
+                if let Some(Value::Array(existing)) = parsed_output.get_mut("packages") {
+                    existing.extend(packages);
+                }
+            }
+            Err(e) => warn!("Failed to query the nix store, leaving its packages out: {e:?}"),
+        }
+    }
+
+    if macos_system {
+        debug!("adding homebrew packages and applications to sbom");
+        match macos::macos_packages().await {
+            Ok(packages) => {
+                if let Some(Value::Array(existing)) = parsed_output.get_mut("packages") {
+                    existing.extend(packages);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to inventory homebrew and applications, leaving them out: {e:?}")
+            }
+        }
+    }
+
+    if windows_system {
+        debug!("adding installed windows programs to sbom");
+        match windows::windows_packages().await {
+            Ok(packages) => {
+                if let Some(Value::Array(existing)) = parsed_output.get_mut("packages") {
+                    existing.extend(packages);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to inventory installed windows programs, leaving them out: {e:?}")
+            }
+        }
+    }
+
+    if let Some(index) = index {
+        debug!("storing the file index along with the sbom");
+        index.store(&config, &source, &parsed_output)?;
+    }
+
     Ok((source, parsed_output))
 }
 
-pub async fn clean(config: &Config) -> Result<()> {
-    let now = SystemTime::now();
+/// Run syft's binary catalogers on their own for images without a package database, like
+/// distroless and scratch images, and add the executables they identify to the SBOM. Other
+/// SBOMs are returned unchanged.
+async fn add_binary_packages(
+    config: &Config,
+    source: &Source,
+    scan_target: &OsString,
+    platform: Option<&str>,
+    workspace: &Workspace,
+    stdout: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let mut sbom: Value = serde_json::from_slice(&stdout)?;
+    let Some(reason) = incomplete_reason(source, &Sbom::deserialize(&sbom)?) else {
+        return Ok(stdout);
+    };
+    debug!(
+        reason,
+        "image has no package database, running the binary catalogers"
+    );
+    let mut command = Command::new("syft");
+    command
+        .arg("scan")
+        .arg("--quiet")
+        .arg("-o")
+        .arg("spdx-json")
+        .arg("--override-default-catalogers")
+        .arg(BINARY_CATALOGERS)
+        .env("SYFT_PARALLELISM", "1")
+        .kill_on_drop(true);
+    config.proxy.get(Integration::Syft).apply(&mut command);
+    if let Some(platform) = platform {
+        command.arg("--platform").arg(platform);
+    }
+    command.arg(scan_target);
+    workspace.apply(&mut command);
+    let key = format!("syft-binary/{}", source.slug());
+    let output = output(config.recording.as_ref(), &key, &mut command).await?;
+    if !output.status.success() {
+        return Err(syft_failure(&output));
+    }
+    let binaries: Value = serde_json::from_slice(&output.stdout)?;
+
+    let key = |package: &Value| {
+        SbomEntry::deserialize(package)
+            .ok()
+            .map(|entry| match entry.purl() {
+                Some(purl) => purl.to_owned(),
+                None => format!("{}@{}", entry.name, entry.versionInfo),
+            })
+    };
+    let Some(Value::Array(packages)) = sbom.get_mut("packages") else {
+        return Ok(stdout);
+    };
+    let known: HashSet<_> = packages.iter().filter_map(key).collect();
+    let Some(Value::Array(found)) = binaries.get("packages") else {
+        return Ok(stdout);
+    };
+    let added: Vec<_> = found
+        .iter()
+        .filter(|package| key(package).is_some_and(|key| !known.contains(&key)))
+        .cloned()
+        .collect();
+    debug!(
+        count = added.len(),
+        "adding packages of the binary catalogers"
+    );
+    packages.extend(added);
+    Ok(serde_json::to_vec(&sbom)?)
+}
+
+/// Convert the SBOMs into all additional formats requested in the config.
+pub async fn export_sboms(config: &Config, sboms: &HashMap<Source, Value>) -> Result<()> {
+    for (source, sbom) in sboms {
+        for format in &config.sbom_outputs {
+            if let Err(e) = export_sbom(config, source, sbom, format).await {
+                warn!("Error exporting sbom for {source} as {format}: {e:?}");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(skip(config, sbom))]
+async fn export_sbom(config: &Config, source: &Source, sbom: &Value, format: &str) -> Result<()> {
+    let path = config.sbom_output_path(source, format);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+
+    debug!("running syft to convert sbom");
+    let mut child = Command::new("syft")
+        .arg("convert")
+        .arg("-") // Read the SBOM from stdin
+        .arg("--quiet") // Supress non-error output
+        .arg("-o")
+        .arg(format)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // Code block, because we need to ensure stdin is dropped before we try
+    // waiting for the child.
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(&serde_json::to_vec(sbom)?).await?;
+    }
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(syft_failure(&output));
+    }
 
-    let old_files = WalkDir::new(&config.base_path)
+    debug!(?path, "writing converted sbom");
+    write_atomic(&path, output.stdout)?;
+    Ok(())
+}
+
+/// Error for a failed syft run, with its error output and the likely cause.
+fn syft_failure(output: &Output) -> anyhow::Error {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lowercase = stderr.to_lowercase();
+    let cause = if lowercase.contains("no space left on device") {
+        "disk full"
+    } else if lowercase.contains("permission denied") {
+        "permission denied"
+    } else if [
+        "could not fetch image",
+        "no such image",
+        "manifest unknown",
+        "unable to find image",
+    ]
+    .iter()
+    .any(|message| lowercase.contains(message))
+    {
+        "image missing"
+    } else {
+        "unknown cause"
+    };
+    anyhow::anyhow!(
+        "syft exited with {} ({cause}): {}",
+        output.status,
+        stderr.trim()
+    )
+}
+
+/// Files in the cache that are subject to age based cleanup, with their metadata.
+fn cache_files(config: &Config) -> impl Iterator<Item = (PathBuf, Metadata)> {
+    let history_path = config.history_path();
+    let mount_path = config.mount_path();
+
+    WalkDir::new(&config.base_path)
         .into_iter()
+        // History entries are pruned by count, not by age, and mounted disk images must never
+        // be touched.
+        .filter_entry(move |entry| entry.path() != history_path && entry.path() != mount_path)
         .filter_map(|entry| entry.ok())
         .filter_map(|e| Some((e.path().to_owned(), e.metadata().ok()?)))
         .filter(|(_, metadata)| metadata.is_file())
@@ -158,6 +739,39 @@ pub async fn clean(config: &Config) -> Result<()> {
                 .filter(|ext| ext.eq(&OsString::from("json")))
                 .is_some()
         })
+}
+
+/// The cached SBOMs by their path. Cached SBOMs don't record their source.
+pub fn cached_sboms(config: &Config) -> impl Iterator<Item = (PathBuf, Sbom)> {
+    WalkDir::new(config.base_path.join("sbom"))
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| {
+            let sbom = serde_json::from_slice(&std::fs::read(entry.path()).ok()?).ok()?;
+            Some((entry.into_path(), sbom))
+        })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CacheStats {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+pub fn cache_stats(config: &Config) -> CacheStats {
+    cache_files(config).fold(CacheStats::default(), |stats, (_, metadata)| CacheStats {
+        entries: stats.entries + 1,
+        bytes: stats.bytes + metadata.len(),
+    })
+}
+
+/// Remove cache files which haven't been accessed within the cache duration. Returns the
+/// removed files with their sizes. With `dry_run`, nothing is removed.
+pub async fn clean(config: &Config, dry_run: bool) -> Result<Vec<(PathBuf, u64)>> {
+    let now = SystemTime::now();
+
+    let old_files = cache_files(config)
         .filter(|(_, metadata)| {
             // Filter files based on their last access time.
             if let Ok(accessed_time) = metadata.accessed() {
@@ -169,9 +783,12 @@ pub async fn clean(config: &Config) -> Result<()> {
                 false
             }
         })
-        .map(|(path, _)| path);
-    for path in old_files {
-        std::fs::remove_file(path)?;
+        .map(|(path, metadata)| (path, metadata.len()))
+        .collect::<Vec<_>>();
+    if !dry_run {
+        for (path, _) in &old_files {
+            std::fs::remove_file(path)?;
+        }
     }
-    Ok(())
+    Ok(old_files)
 }
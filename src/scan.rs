@@ -1,90 +1,199 @@
-use std::{collections::HashMap, process::Stdio};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufWriter, Write},
+    process::Stdio,
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
 use rust_decimal::Decimal;
 use serde_json::Value;
-use tokio::{io::AsyncWriteExt, process::Command};
+use tokio::process::Command;
 use tracing::debug;
 
-use crate::config::Source;
+use crate::{
+    checkpoint::Checkpoint,
+    config::{Config, Source},
+    error::SsceError,
+    finding::canonical_id,
+    progress::Progress,
+    proxy::Integration,
+    recording::{record, replayed},
+    sbom::Tool,
+    severity::Severity,
+    workspace::Workspace,
+};
 
 /// Call grype to scan SBOMs for vulnerabilities and output JSON report.
 /// Just as with syft, grype doesn't take multiple inputs at once, so once again we loop.
-pub async fn scan(sboms: &HashMap<Source, Value>) -> Result<HashMap<Source, Scan>> {
+pub async fn scan(
+    config: &Config,
+    sboms: &HashMap<Source, Value>,
+    checkpoint: &Checkpoint,
+) -> Result<HashMap<Source, Scan>> {
     let mut scans = HashMap::new();
-    Command::new("grype")
-        .arg("db")
-        .arg("update")
-        .arg("--quiet")
-        .spawn()?
-        .wait()
-        .await?;
-
+    let progress = Progress::new("Scanning SBOMs", sboms.len());
     for (source, sbom) in sboms {
-        let res = scan_single(source.clone(), sbom.clone()).await;
+        progress.start(source);
+        if let Some(scan) = checkpoint.scan(source) {
+            scans.insert(source.clone(), scan);
+            progress.inc();
+            continue;
+        }
+
+        let res = scan_single(config, source.clone(), sbom.clone()).await;
 
         match res {
-            Err(e) => {
-                println!("Failed to scan an sbom: {e}")
+            Err(error) => SsceError::Scan {
+                source: source.clone(),
+                error,
             }
+            .log(),
             Ok((source, scan)) => {
+                checkpoint.add_scan(&source, &scan)?;
                 scans.insert(source, scan);
             }
         }
+        progress.inc();
     }
 
     Ok(scans)
 }
 
-#[tracing::instrument(skip(sbom))]
-async fn scan_single(source: Source, sbom: Value) -> Result<(Source, Scan)> {
+#[tracing::instrument(skip(config, sbom))]
+async fn scan_single(config: &Config, source: Source, sbom: Value) -> Result<(Source, Scan)> {
     debug!("running grype to compare sbom against vulnerability databases");
-    let mut child = Command::new("grype")
+    let key = format!("grype/{}", source.slug());
+    if let Some(output) = replayed(config.recording.as_ref(), &key) {
+        return Ok((source, parse_scan(&output?.stdout)?));
+    }
+    let workspace = Workspace::new(config, &source, "grype")?;
+
+    // The SBOM is passed as file instead of on stdin, so it isn't serialized into memory a
+    // second time, and a grype exiting early can't leave us blocked on a full pipe.
+    debug!("write sbom for grype");
+    let sbom_path = workspace.path().join("sbom.spdx.json");
+    let mut writer = BufWriter::new(File::create(&sbom_path)?);
+    serde_json::to_writer(&mut writer, &sbom)?;
+    writer.flush()?;
+    drop(sbom);
+
+    let mut command = Command::new("grype");
+    command
         .arg("--quiet") // Supress non-error output
         .arg("-o")
         .arg("json")
+        .arg(format!("sbom:{}", sbom_path.display()))
         .env("GRYPE_DB_AUTO_UPDATE", "false")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    // Code block, because we need to ensure stdin is dropped before we try
-    // waiting for the child.
-    {
-        debug!("feed sbom into grype");
-        let mut stdin = child.stdin.take().unwrap();
-        stdin.write_all(&serde_json::to_vec(&sbom)?).await?;
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    if !config.grype_db.auto_update {
+        // Staged databases may be older than grype's own limit, `grype_db.max_age` applies.
+        command.env(
+            "GRYPE_DB_MAX_ALLOWED_BUILT_AGE",
+            format!("{}s", config.grype_db.max_age.as_secs()),
+        );
     }
+    workspace.apply(&mut command);
+    config.proxy.get(Integration::Grype).apply(&mut command);
 
     debug!("wait for grype to finish");
-    let output = child.wait_with_output().await?;
+    let output = workspace
+        .limit(async { Ok(command.output().await?) })
+        .await?;
+    record(config.recording.as_ref(), &key, &output)?;
+    if !output.status.success() {
+        bail!(
+            "grype failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok((source, parse_scan(&output.stdout)?))
+}
 
+fn parse_scan(stdout: &[u8]) -> Result<Scan> {
     debug!("decode vulnerability report");
-    let parsed_output = serde_json::from_slice(&output.stdout)?;
-    Ok((source, parsed_output))
+    let mut parsed_output: Scan = serde_json::from_slice(stdout)?;
+    parsed_output.dedup_aliases();
+    Ok(parsed_output)
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Scan {
     pub matches: Vec<ScanEntry>,
+    /// The grype version that produced the scan.
+    #[serde(default)]
+    pub descriptor: Tool,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ScanEntry {
     pub vulnerability: Vulnerability,
     pub artifact: ScanArtifact,
+    /// Aliases of the vulnerability in other databases, e.g. the CVE of a GHSA.
+    #[serde(rename = "relatedVulnerabilities", default)]
+    pub related_vulnerabilities: Vec<RelatedVulnerability>,
+    /// Databases with public exploit code for the vulnerability, see `exploits`.
+    #[serde(default)]
+    pub exploits: Vec<String>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RelatedVulnerability {
+    pub id: String,
+}
+
+impl ScanEntry {
+    /// Id shared by all aliases of the vulnerability: the CVE id if there is one, the
+    /// vulnerability id otherwise.
+    pub fn canonical_id(&self) -> &str {
+        canonical_id(
+            &self.vulnerability.id,
+            self.related_vulnerabilities
+                .iter()
+                .map(|related| related.id.as_str()),
+        )
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Vulnerability {
     pub id: String,
-    pub severity: String,
+    pub severity: Severity,
+    pub description: String,
     pub urls: Vec<String>,
     pub fix: Fix,
     pub cvss: Vec<Cvss>,
 }
 
+impl Scan {
+    /// Remove findings that are aliases of another finding for the same package, e.g. a GHSA
+    /// and the CVE it refers to. The first finding is kept.
+    pub fn dedup_aliases(&mut self) {
+        let mut seen = HashSet::new();
+        self.matches.retain(|entry| {
+            seen.insert((
+                entry.canonical_id().to_owned(),
+                entry.artifact.name.clone(),
+                entry.artifact.version.clone(),
+            ))
+        });
+    }
+
+    /// Severity of the most severe finding, `Unknown` if there are none.
+    pub fn highest_severity(&self) -> Severity {
+        self.matches
+            .iter()
+            .map(|entry| entry.vulnerability.severity)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Cvss {
@@ -96,6 +205,18 @@ pub struct Cvss {
     pub metrics: CvssMetrics,
 }
 
+impl Cvss {
+    /// Value of a base metric of the vector, e.g. `N` for `AV` in
+    /// `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H`.
+    pub fn vector_metric(&self, metric: &str) -> Option<&str> {
+        self.vector
+            .split('/')
+            .filter_map(|part| part.split_once(':'))
+            .find(|(key, _)| *key == metric)
+            .map(|(_, value)| value)
+    }
+}
+
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct CvssMetrics {
     #[serde(rename = "baseScore", with = "rust_decimal::serde::float")]
@@ -110,6 +231,29 @@ pub struct CvssMetrics {
 pub struct Fix {
     pub versions: Vec<String>,
     pub state: FixState,
+    /// Release information about the fixed versions, only provided by recent grype databases.
+    #[serde(default)]
+    pub available: Vec<FixAvailable>,
+}
+
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FixAvailable {
+    pub version: String,
+    /// Date in `YYYY-MM-DD` form.
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub kind: String,
+}
+
+impl Fix {
+    /// The date the first fixed version became available, if known.
+    pub fn available_since(&self) -> Option<NaiveDate> {
+        self.available
+            .iter()
+            .filter_map(|available| NaiveDate::parse_from_str(&available.date, "%Y-%m-%d").ok())
+            .min()
+    }
 }
 
 #[derive(Clone, Debug, Default, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -131,4 +275,36 @@ impl std::fmt::Display for FixState {
 #[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct ScanArtifact {
     pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(rename = "type", default)]
+    pub artifact_type: String,
+    #[serde(default)]
+    pub locations: Vec<Location>,
+    #[serde(default)]
+    pub purl: Option<String>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Location {
+    pub path: String,
+}
+
+impl ScanArtifact {
+    /// Coarse ecosystem the artifact belongs to, derived from the syft package type.
+    pub fn ecosystem(&self) -> &str {
+        match self.artifact_type.as_str() {
+            "deb" | "rpm" | "apk" | "alpm" | "portage" | "nix" => "os",
+            "java-archive" | "jenkins-plugin" | "graalvm-native-image" => "java",
+            "go-module" => "go",
+            "rust-crate" => "rust",
+            "gem" => "ruby",
+            "php-composer" | "php-pecl" => "php",
+            "python" => "python",
+            "npm" => "npm",
+            "dotnet" => "dotnet",
+            "" => "unknown",
+            other => other,
+        }
+    }
 }
@@ -0,0 +1,355 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::{
+    compare::Agreement,
+    config::{Config, Source, Tags},
+    fs::write_atomic,
+    report::Report,
+    scan::Scan,
+    schema::duration_schema,
+    severity::Severity,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// How often the daemon checks for sources that are due for a scan.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_interval")]
+    pub interval: Duration,
+    /// Re-scan intervals for sources by the severity of their findings. The first matching
+    /// tier applies.
+    #[serde(default)]
+    pub tiers: Vec<ScheduleTier>,
+    /// Re-scan interval for sources not matching any tier.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_rescan_after")]
+    pub rescan_after: Duration,
+    /// Sources whose SBOM changed within this duration are re-scanned as often as the most
+    /// frequently scanned tier.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_rescan_after")]
+    pub changed_within: Duration,
+    /// Containers which are running for less than this, or which ran for less than this before
+    /// they stopped, are skipped, so images of short-lived containers, e.g. of CI jobs, aren't
+    /// scanned. Their images are scanned once a container lives long enough.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default)]
+    pub min_container_age: Duration,
+    /// Daily windows in the local time of the host in which the daemon scans, e.g. from
+    /// `02:00` to `05:00`, to keep scans away from peak traffic. Outside of them, the metrics
+    /// of the last run stay in place. Without windows, the daemon scans at any time.
+    #[serde(default)]
+    pub windows: Vec<ScanWindow>,
+    /// Periods in which the daemon doesn't scan at all, e.g. a release freeze.
+    #[serde(default)]
+    pub blackouts: Vec<Blackout>,
+}
+
+/// A daily window, which ends on the next day if the end is before the start, e.g. from
+/// `22:00` to `04:00`. A scan running at the end of the window is finished.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ScanWindow {
+    #[schemars(with = "String")]
+    pub start: NaiveTime,
+    #[schemars(with = "String")]
+    pub end: NaiveTime,
+}
+
+impl ScanWindow {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Time until the window opens next, zero if it is open.
+    fn until_open(&self, now: DateTime<Local>) -> Duration {
+        if self.contains(now.time()) {
+            return Duration::ZERO;
+        }
+        let until = self.start - now.time();
+        let until = if until < chrono::Duration::zero() {
+            until + chrono::Duration::days(1)
+        } else {
+            until
+        };
+        until.to_std().unwrap_or_default()
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct Blackout {
+    /// Start of the period, e.g. `2024-12-20T00:00:00Z`.
+    #[schemars(with = "String")]
+    pub start: DateTime<Utc>,
+    #[schemars(with = "String")]
+    pub end: DateTime<Utc>,
+}
+
+impl ScheduleConfig {
+    /// Whether a blackout is in effect.
+    pub fn in_blackout(&self, now: DateTime<Local>) -> bool {
+        self.blackouts
+            .iter()
+            .any(|blackout| blackout.start <= now && now < blackout.end)
+    }
+
+    /// How long the daemon has to wait before it may scan, zero if it may scan now.
+    pub fn until_scan_allowed(&self, now: DateTime<Local>) -> Duration {
+        let blackout = self
+            .blackouts
+            .iter()
+            .filter(|blackout| blackout.start <= now && now < blackout.end)
+            .filter_map(|blackout| (blackout.end - now.with_timezone(&Utc)).to_std().ok())
+            .max()
+            .unwrap_or_default();
+        let window = self
+            .windows
+            .iter()
+            .map(|window| window.until_open(now))
+            .min()
+            .unwrap_or_default();
+        // The window might close during the blackout, which is checked again afterwards.
+        blackout.max(window)
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ScheduleTier {
+    pub min_severity: Severity,
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde")]
+    pub rescan_after: Duration,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_interval(),
+            tiers: Vec::new(),
+            rescan_after: default_rescan_after(),
+            changed_within: default_rescan_after(),
+            min_container_age: Duration::ZERO,
+            windows: Vec::new(),
+            blackouts: Vec::new(),
+        }
+    }
+}
+
+fn default_interval() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+fn default_rescan_after() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ScheduledSource {
+    sbom: Value,
+    sbom_hash: u64,
+    scan: Option<Scan>,
+    scanned: DateTime<Utc>,
+    changed: DateTime<Utc>,
+    /// Comparison of the scan with trivy, see `scanner_comparison`.
+    #[serde(default)]
+    agreement: Option<Agreement>,
+}
+
+/// Scheduler state persisted by the daemon, so a restarted daemon can export the previous
+/// results right away and doesn't need to scan all sources again.
+#[derive(Serialize, Deserialize)]
+struct SavedState {
+    run_id: String,
+    finished: DateTime<Utc>,
+    sources: Vec<SavedSource>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSource {
+    source: Source,
+    tags: Tags,
+    state: ScheduledSource,
+}
+
+/// The run whose results a daemon restored on startup.
+pub struct PreviousRun {
+    pub run_id: String,
+    pub finished: DateTime<Utc>,
+    pub sources: HashMap<Source, Tags>,
+}
+
+/// Keeps the latest results per source and decides which sources are due for a re-scan.
+#[derive(Default)]
+pub struct Scheduler {
+    sources: HashMap<Source, ScheduledSource>,
+}
+
+impl Scheduler {
+    /// Whether a source should be scanned in this run. New sources are always due.
+    pub fn due(&self, config: &ScheduleConfig, source: &Source, now: DateTime<Utc>) -> bool {
+        let Some(state) = self.sources.get(source) else {
+            return true;
+        };
+
+        let highest_severity = state
+            .scan
+            .as_ref()
+            .map(Scan::highest_severity)
+            .unwrap_or_default();
+        let mut rescan_after = config
+            .tiers
+            .iter()
+            .find(|tier| highest_severity >= tier.min_severity)
+            .map(|tier| tier.rescan_after)
+            .unwrap_or(config.rescan_after);
+
+        let recently_changed =
+            (now - state.changed).to_std().unwrap_or_default() < config.changed_within;
+        if recently_changed {
+            rescan_after = config
+                .tiers
+                .iter()
+                .map(|tier| tier.rescan_after)
+                .chain([rescan_after])
+                .min()
+                .unwrap_or(rescan_after);
+        }
+
+        (now - state.scanned).to_std().unwrap_or_default() >= rescan_after
+    }
+
+    /// Store the results of a run.
+    pub fn record(
+        &mut self,
+        sboms: &HashMap<Source, Value>,
+        scans: &HashMap<Source, Scan>,
+        now: DateTime<Utc>,
+    ) {
+        for (source, sbom) in sboms {
+            let mut hasher = DefaultHasher::new();
+            sbom.to_string().hash(&mut hasher);
+            let sbom_hash = hasher.finish();
+
+            let changed = match self.sources.get(source) {
+                Some(previous) if previous.sbom_hash == sbom_hash => previous.changed,
+                _ => now,
+            };
+            self.sources.insert(
+                source.clone(),
+                ScheduledSource {
+                    sbom: sbom.clone(),
+                    sbom_hash,
+                    scan: scans.get(source).cloned(),
+                    scanned: now,
+                    changed,
+                    agreement: None,
+                },
+            );
+        }
+    }
+
+    /// Store the scanner comparisons of the sources scanned in a run.
+    pub fn record_agreements(&mut self, agreements: HashMap<Source, Agreement>) {
+        for (source, agreement) in agreements {
+            if let Some(state) = self.sources.get_mut(&source) {
+                state.agreement = Some(agreement);
+            }
+        }
+    }
+
+    /// Latest scanner comparisons of the sources kept by `results`.
+    pub fn agreements(&self) -> HashMap<Source, Agreement> {
+        self.sources
+            .iter()
+            .filter_map(|(source, state)| Some((source.clone(), state.agreement.clone()?)))
+            .collect()
+    }
+
+    /// Latest results of all current sources. Sources which disappeared are forgotten.
+    pub fn results(
+        &mut self,
+        current: &[Source],
+    ) -> (HashMap<Source, Value>, HashMap<Source, Scan>) {
+        self.sources.retain(|source, _| current.contains(source));
+
+        let sboms = self
+            .sources
+            .iter()
+            .map(|(source, state)| (source.clone(), state.sbom.clone()))
+            .collect();
+        let scans = self
+            .sources
+            .iter()
+            .filter_map(|(source, state)| Some((source.clone(), state.scan.clone()?)))
+            .collect();
+        (sboms, scans)
+    }
+
+    /// Persist the results, with the tags of the sources from the report of the run.
+    pub fn save(&self, config: &Config, report: &Report) -> Result<()> {
+        let tags = report
+            .sources
+            .iter()
+            .map(|source| (&source.source, &source.tags))
+            .collect::<HashMap<_, _>>();
+        let state = SavedState {
+            run_id: report.run_id.clone(),
+            finished: report.finished,
+            sources: self
+                .sources
+                .iter()
+                .map(|(source, state)| SavedSource {
+                    source: source.clone(),
+                    tags: tags.get(source).cloned().cloned().unwrap_or_default(),
+                    state: state.clone(),
+                })
+                .collect(),
+        };
+        debug!("saving scheduler state");
+        write_atomic(&config.scheduler_state_path(), serde_json::to_vec(&state)?)?;
+        Ok(())
+    }
+
+    /// Restore the results saved by a previous daemon.
+    pub fn load(config: &Config) -> Option<(Self, PreviousRun)> {
+        let state: SavedState =
+            serde_json::from_slice(&std::fs::read(config.scheduler_state_path()).ok()?).ok()?;
+        info!(
+            "Restored results of {} sources from run {}",
+            state.sources.len(),
+            state.run_id
+        );
+        let mut scheduler = Self::default();
+        let mut sources = HashMap::new();
+        for saved in state.sources {
+            sources.insert(saved.source.clone(), saved.tags);
+            scheduler.sources.insert(saved.source, saved.state);
+        }
+        Some((
+            scheduler,
+            PreviousRun {
+                run_id: state.run_id,
+                finished: state.finished,
+                sources,
+            },
+        ))
+    }
+}
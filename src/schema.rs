@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec},
+    schema_for,
+};
+use serde_json::{json, Value};
+
+use crate::{config::Config, severity::Severity};
+
+/// Schema of durations in humantime format, e.g. `1h 30m`.
+pub fn duration_schema(_: &mut SchemaGenerator) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        format: Some("duration".into()),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// JSON Schema of the config file, including the keys handled while loading it.
+pub fn config_schema() -> RootSchema {
+    let mut schema = schema_for!(Config);
+    let properties = &mut schema.schema.object().properties;
+    properties.insert(
+        "include".into(),
+        serde_json::from_value(json!({
+            "description": "Files or directories of YAML files layered on top of this file",
+            "type": "array",
+            "items": { "type": "string" },
+        }))
+        .unwrap(),
+    );
+    properties.insert(
+        "profiles".into(),
+        serde_json::from_value(json!({
+            "description": "Named sets of values which replace the config values when selected",
+            "type": "object",
+            "additionalProperties": { "type": "object" },
+        }))
+        .unwrap(),
+    );
+    schema
+}
+
+/// A problem found in the config, with the location it was found at if it could be determined.
+pub struct Problem {
+    pub path: String,
+    pub message: String,
+    pub location: Option<(PathBuf, usize)>,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((file, line)) = &self.location {
+            write!(f, "{}:{line}: ", file.display())?;
+        }
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Check the merged config against the schema and check that configured paths exist. All
+/// problems are returned instead of stopping at the first one, and are located in the given
+/// files where possible.
+pub fn validate(config: &Value, files: &[PathBuf]) -> Vec<Problem> {
+    let schema = config_schema();
+    let mut validator = Validator {
+        root: &schema,
+        problems: Vec::new(),
+    };
+    validator.check(
+        &Schema::Object(schema.schema.clone()),
+        config,
+        &mut Vec::new(),
+    );
+    let mut problems = validator.problems;
+
+    for key in ["directories", "ci_workspaces", "disk_images"] {
+        for (i, source) in config[key].as_array().into_iter().flatten().enumerate() {
+            if let Some(path) = source["path"].as_str() {
+                if !Path::new(path).exists() {
+                    problems.push((
+                        vec![key.to_owned(), i.to_string(), "path".to_owned()],
+                        format!("{path} does not exist"),
+                    ));
+                }
+            }
+        }
+    }
+
+    let contents = files
+        .iter()
+        .filter_map(|file| Some((file, std::fs::read_to_string(file).ok()?)))
+        .collect::<Vec<_>>();
+    let mut problems = problems
+        .into_iter()
+        .map(|(path, message)| Problem {
+            location: contents
+                .iter()
+                .find_map(|(file, text)| Some(((*file).clone(), locate(text, &path)?))),
+            path: if path.is_empty() {
+                "config".into()
+            } else {
+                path.join(".")
+            },
+            message,
+        })
+        .collect::<Vec<_>>();
+    problems.sort_by(|a, b| a.location.cmp(&b.location));
+    problems
+}
+
+struct Validator<'a> {
+    root: &'a RootSchema,
+    problems: Vec<(Vec<String>, String)>,
+}
+
+impl Validator<'_> {
+    fn check(&mut self, schema: &Schema, value: &Value, path: &mut Vec<String>) {
+        let schema = match schema {
+            Schema::Bool(true) => return,
+            Schema::Bool(false) => return self.problem(path, "unknown key".into()),
+            Schema::Object(schema) => schema,
+        };
+
+        if let Some(reference) = &schema.reference {
+            let name = reference.trim_start_matches("#/definitions/");
+            if let Some(definition) = self.root.definitions.get(name) {
+                self.check(definition, value, path);
+            }
+        }
+
+        if let Some(subschemas) = &schema.subschemas {
+            for subschema in subschemas.all_of.iter().flatten() {
+                self.check(subschema, value, path);
+            }
+            let alternatives = subschemas
+                .any_of
+                .iter()
+                .chain(subschemas.one_of.iter())
+                .flatten()
+                .collect::<Vec<_>>();
+            // Enums with documented variants are one alternative per variant.
+            let variants = alternatives
+                .iter()
+                .map(|alternative| match alternative {
+                    Schema::Object(alternative) => alternative.enum_values.as_ref(),
+                    Schema::Bool(_) => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            let mut best: Option<Vec<_>> = None;
+            if let Some(variants) = variants.filter(|variants| !variants.is_empty()) {
+                let variants = variants.into_iter().flatten().cloned().collect::<Vec<_>>();
+                self.check_enum(&variants, value, path);
+            } else {
+                // Report the problems of the alternative that matched best.
+                for alternative in alternatives {
+                    let problems = std::mem::take(&mut self.problems);
+                    self.check(alternative, value, path);
+                    let found = std::mem::replace(&mut self.problems, problems);
+                    if best.as_ref().is_none_or(|best| found.len() < best.len()) {
+                        best = Some(found);
+                    }
+                }
+            }
+            self.problems.extend(best.into_iter().flatten());
+        }
+
+        if let Some(types) = &schema.instance_type {
+            let types = match types {
+                SingleOrVec::Single(instance_type) => vec![**instance_type],
+                SingleOrVec::Vec(types) => types.clone(),
+            };
+            if !types
+                .iter()
+                .any(|instance_type| matches(*instance_type, value))
+            {
+                let expected = types
+                    .iter()
+                    .map(|instance_type| format!("{instance_type:?}").to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                return self.problem(path, format!("expected {expected}, found {value}"));
+            }
+        }
+
+        if let Some(values) = &schema.enum_values {
+            self.check_enum(values, value, path);
+        }
+
+        if let (Some("duration"), Some(duration)) = (schema.format.as_deref(), value.as_str()) {
+            if let Err(e) = humantime::parse_duration(duration) {
+                self.problem(path, format!("invalid duration {duration:?}: {e}"));
+            }
+        }
+
+        if let (Some("date"), Some(date)) = (schema.format.as_deref(), value.as_str()) {
+            if let Err(e) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                self.problem(
+                    path,
+                    format!("invalid date {date:?}, expected YYYY-MM-DD: {e}"),
+                );
+            }
+        }
+
+        if let (Some("severity"), Some(severity)) = (schema.format.as_deref(), value.as_str()) {
+            if !Severity::ALL
+                .iter()
+                .any(|known| known.as_str().eq_ignore_ascii_case(severity))
+            {
+                let known = Severity::ALL.map(Severity::as_str).join(", ");
+                self.problem(path, format!("expected one of {known}, found {severity:?}"));
+            }
+        }
+
+        if let (Some(object), Some(value)) = (&schema.object, value.as_object()) {
+            for required in &object.required {
+                if !value.contains_key(required) {
+                    self.problem(path, format!("missing required key {required}"));
+                }
+            }
+            for (key, value) in value {
+                path.push(key.clone());
+                if let Some(property) = object.properties.get(key) {
+                    self.check(property, value, path);
+                } else if let Some(additional) = &object.additional_properties {
+                    self.check(additional, value, path);
+                }
+                path.pop();
+            }
+        }
+
+        if let (Some(array), Some(value)) = (&schema.array, value.as_array()) {
+            if let Some(SingleOrVec::Single(items)) = &array.items {
+                for (i, item) in value.iter().enumerate() {
+                    path.push(i.to_string());
+                    self.check(items, item, path);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    fn check_enum(&mut self, values: &[Value], value: &Value, path: &[String]) {
+        if !values.contains(value) {
+            let values = values
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.problem(path, format!("expected one of {values}, found {value}"));
+        }
+    }
+
+    fn problem(&mut self, path: &[String], message: String) {
+        self.problems.push((path.to_vec(), message));
+    }
+}
+
+fn matches(instance_type: InstanceType, value: &Value) -> bool {
+    match instance_type {
+        InstanceType::Null => value.is_null(),
+        InstanceType::Boolean => value.is_boolean(),
+        InstanceType::Object => value.is_object(),
+        InstanceType::Array => value.is_array(),
+        InstanceType::Number => value.is_number(),
+        InstanceType::String => value.is_string(),
+        InstanceType::Integer => value.is_i64() || value.is_u64(),
+    }
+}
+
+/// Find the line of a key path like `schedule.tiers.0.rescan_after` in a YAML file, by following
+/// the indentation of block mappings and sequences. Returns `None` for flow style and anything
+/// else this simple approach can't follow.
+fn locate(text: &str, path: &[String]) -> Option<usize> {
+    struct Entry<'a> {
+        line: usize,
+        indent: usize,
+        dash: bool,
+        text: &'a str,
+    }
+
+    // Split sequence items like `- path: /` into the dash and the content after it, which is
+    // indented further.
+    let mut entries = Vec::new();
+    for (line, text) in text.lines().enumerate() {
+        let mut content = text.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let mut indent = text.len() - content.len();
+        while let Some(rest) = content
+            .strip_prefix('-')
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+        {
+            entries.push(Entry {
+                line,
+                indent,
+                dash: true,
+                text: "",
+            });
+            let trimmed = rest.trim_start();
+            indent += content.len() - trimmed.len();
+            content = trimmed;
+        }
+        if !content.is_empty() {
+            entries.push(Entry {
+                line,
+                indent,
+                dash: false,
+                text: content,
+            });
+        }
+    }
+
+    let mut start = 0;
+    let mut parent: Option<&Entry> = None;
+    for segment in path {
+        let index = segment.parse::<usize>().ok();
+        let mut level = None;
+        let mut count = 0;
+        let mut matched = None;
+        for (position, entry) in entries.iter().enumerate().skip(start) {
+            if let Some(parent) = parent {
+                // Sequences may be indented as far as the key they belong to.
+                let sequence_of_key = entry.dash && !parent.dash;
+                if entry.indent < parent.indent
+                    || (entry.indent == parent.indent && !sequence_of_key)
+                {
+                    break;
+                }
+            }
+            if entry.indent != *level.get_or_insert(entry.indent) {
+                continue;
+            }
+            match index {
+                Some(index) if entry.dash => {
+                    if count == index {
+                        matched = Some(position);
+                        break;
+                    }
+                    count += 1;
+                }
+                None if !entry.dash => {
+                    let key = entry.text.split(':').next().unwrap_or_default();
+                    if key.trim().trim_matches(['"', '\'']) == segment {
+                        matched = Some(position);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let position = matched?;
+        parent = Some(&entries[position]);
+        start = position + 1;
+    }
+    parent.map(|entry| entry.line + 1)
+}
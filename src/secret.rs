@@ -0,0 +1,69 @@
+use std::{fmt, path::PathBuf};
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A credential from the config file. It can be given inline, or reference an environment
+/// variable or a file, so the config file itself doesn't need to contain it:
+///
+/// ```yaml
+/// token: inline-value
+/// token: { env: SSCE_TOKEN }
+/// token: { file: /run/secrets/ssce_token }
+/// ```
+///
+/// The value is resolved when the config is loaded and is redacted in debug output and when
+/// the config is serialized.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum SecretSource {
+    Value(String),
+    Env { env: String },
+    File { file: PathBuf },
+}
+
+impl Secret {
+    /// The actual secret value, only to be used where it's sent to its destination.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match SecretSource::deserialize(deserializer)? {
+            SecretSource::Value(value) => Ok(Self(value)),
+            SecretSource::Env { env } => std::env::var(&env)
+                .map(Self)
+                .map_err(|e| D::Error::custom(format!("secret from env {env}: {e}"))),
+            SecretSource::File { file } => std::fs::read_to_string(&file)
+                .map(|value| Self(value.trim_end().to_owned()))
+                .map_err(|e| D::Error::custom(format!("secret from {}: {e}", file.display()))),
+        }
+    }
+}
+
+impl JsonSchema for Secret {
+    fn schema_name() -> String {
+        "Secret".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SecretSource::json_schema(gen)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("<redacted>")
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Severity of a vulnerability, ordered from `Unknown` to `Critical`. Scanners spell severities
+/// differently, e.g. `HIGH` and `High`, which are parsed to the same value. Anything else is
+/// `Unknown`, so arbitrary strings never end up in metric labels.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    #[default]
+    Unknown,
+    Negligible,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Self; 6] = [
+        Self::Unknown,
+        Self::Negligible,
+        Self::Low,
+        Self::Medium,
+        Self::High,
+        Self::Critical,
+    ];
+
+    pub fn parse(severity: &str) -> Self {
+        match severity.to_lowercase().as_str() {
+            "negligible" => Self::Negligible,
+            "low" => Self::Low,
+            "medium" => Self::Medium,
+            "high" => Self::High,
+            "critical" => Self::Critical,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Numeric rank, from 0 (unknown) to 5 (critical), as exported in metrics.
+    pub fn rank(self) -> i64 {
+        self as i64
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::Negligible => "Negligible",
+            Self::Low => "Low",
+            Self::Medium => "Medium",
+            Self::High => "High",
+            Self::Critical => "Critical",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Severities in the config are case-insensitive like the parser, e.g. `high` or `High`.
+impl JsonSchema for Severity {
+    fn schema_name() -> String {
+        "Severity".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let alternatives = Self::ALL
+            .iter()
+            .map(|severity| {
+                severity
+                    .as_str()
+                    .chars()
+                    .map(|c| format!("[{}{}]", c.to_ascii_uppercase(), c.to_ascii_lowercase()))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("severity".into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some(format!("^({})$", alternatives.join("|"))),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Severity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::parse(&String::deserialize(deserializer)?))
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    fs::OpenOptions,
+    future::Future,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    fs::write_atomic,
+    proxy::{Integration, ProxyConfig},
+    schema::duration_schema,
+};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct SharedCacheConfig {
+    /// Directory on shared storage, e.g. an NFS mount. Concurrent scans of the same image are
+    /// prevented with lock files.
+    pub path: Option<PathBuf>,
+    /// Base URL of an object store. Entries are created with conditional requests, so the first
+    /// host to finish a scan wins.
+    pub url: Option<String>,
+    /// Locks older than this are considered abandoned by a crashed host.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_lock_timeout")]
+    pub lock_timeout: Duration,
+}
+
+fn default_lock_timeout() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+impl SharedCacheConfig {
+    /// Get the SBOM for an image digest from the shared cache, or create it and store it for
+    /// other hosts.
+    #[tracing::instrument(skip(self, proxy, create))]
+    pub async fn get_or_create(
+        &self,
+        proxy: &ProxyConfig,
+        digest: &str,
+        create: impl Future<Output = Result<Vec<u8>>>,
+    ) -> Result<Vec<u8>> {
+        let name = format!("sbom/{}.json", digest.replace(':', "_"));
+        if let Some(path) = &self.path {
+            self.get_or_create_file(&path.join(name), create).await
+        } else if let Some(url) = &self.url {
+            let client = proxy.client(Integration::SharedCache)?;
+            let url = format!("{}/{name}", url.trim_end_matches('/'));
+            get_or_create_object(&client, &url, create).await
+        } else {
+            create.await
+        }
+    }
+
+    async fn get_or_create_file(
+        &self,
+        path: &Path,
+        create: impl Future<Output = Result<Vec<u8>>>,
+    ) -> Result<Vec<u8>> {
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        let mut lock = path.as_os_str().to_owned();
+        lock.push(".lock");
+        let lock = PathBuf::from(lock);
+
+        loop {
+            if let Ok(sbom) = std::fs::read(path) {
+                debug!("using sbom from shared cache");
+                return Ok(sbom);
+            }
+
+            // Exclusive creation is atomic on NFS as well.
+            match OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let age = std::fs::metadata(&lock)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                        .unwrap_or_default();
+                    if age > self.lock_timeout {
+                        info!("removing abandoned shared cache lock");
+                        std::fs::remove_file(&lock)?;
+                    } else {
+                        debug!("another host is scanning this image, waiting");
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let sbom = create.await;
+        let written = match &sbom {
+            Ok(sbom) => write_atomic(path, sbom),
+            Err(_) => Ok(()),
+        };
+        // Release the lock even if the SBOM couldn't be shared, so other hosts don't wait for
+        // it until the lock times out.
+        std::fs::remove_file(&lock)?;
+        written?;
+        sbom
+    }
+}
+
+async fn get_or_create_object(
+    client: &reqwest::Client,
+    url: &str,
+    create: impl Future<Output = Result<Vec<u8>>>,
+) -> Result<Vec<u8>> {
+    let response = client.get(url).send().await?;
+    if response.status().is_success() {
+        debug!("using sbom from shared cache");
+        return Ok(response.bytes().await?.to_vec());
+    }
+
+    let sbom = create.await?;
+    let response = client
+        .put(url)
+        // Only create the object if no other host has stored it in the meantime.
+        .header("If-None-Match", "*")
+        .body(sbom.clone())
+        .send()
+        .await?;
+    if !response.status().is_success() && response.status() != 412 {
+        bail!("storing sbom in shared cache failed: {}", response.status());
+    }
+    Ok(sbom)
+}
@@ -0,0 +1,276 @@
+use std::{collections::BTreeMap, future::Future, path::PathBuf, pin::Pin};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    config::Config,
+    error::SsceError,
+    fs::{create_parent, write_atomic},
+    notify::hostname,
+    report::Report,
+    secret::Secret,
+};
+
+/// Content type of metrics in the OpenMetrics text format.
+const OPENMETRICS: &str = "application/openmetrics-text; version=1.0.0";
+
+/// The results of a run, as delivered to the output sinks.
+pub struct Output<'a> {
+    /// Metrics in the OpenMetrics text format.
+    pub metrics: &'a str,
+    pub report: &'a Report,
+    /// HTTP client of the sinks, with their proxy.
+    pub client: reqwest::Client,
+}
+
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// A destination for the results of a run.
+pub trait OutputSink: Sync {
+    /// Where the sink delivers to, for logging.
+    fn describe(&self) -> String;
+
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a>;
+}
+
+/// Additional destinations for the metrics and the report of every run.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    /// A metrics file, like `metrics_outputs`.
+    Textfile(MetricsOutput),
+    /// Send the metrics or the report to a URL, e.g. to upload the report to an object store.
+    Http(HttpSink),
+    /// Push the metrics to a Prometheus Pushgateway, grouped by job and host.
+    Pushgateway(PushgatewaySink),
+    /// Write the JSON report to another path, in addition to `report_path`.
+    Report(ReportSink),
+}
+
+impl OutputSink for SinkConfig {
+    fn describe(&self) -> String {
+        match self {
+            Self::Textfile(sink) => sink.describe(),
+            Self::Http(sink) => sink.describe(),
+            Self::Pushgateway(sink) => sink.describe(),
+            Self::Report(sink) => sink.describe(),
+        }
+    }
+
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a> {
+        match self {
+            Self::Textfile(sink) => sink.write(output),
+            Self::Http(sink) => sink.write(output),
+            Self::Pushgateway(sink) => sink.write(output),
+            Self::Report(sink) => sink.write(output),
+        }
+    }
+}
+
+/// Deliver the results of a run to the metrics file, the `metrics_outputs` and all configured
+/// sinks. Failing sinks are logged and counted, and neither keep the others from being written
+/// nor fail the run.
+pub async fn write_outputs(config: &Config, output: &Output<'_>) {
+    let metrics_file = MetricsOutput {
+        path: config.metrics_path(),
+        include: Vec::new(),
+        exclude: Vec::new(),
+    };
+    let sinks = std::iter::once(&metrics_file as &dyn OutputSink)
+        .chain(
+            config
+                .metrics_outputs
+                .iter()
+                .map(|sink| sink as &dyn OutputSink),
+        )
+        .chain(config.sinks.iter().map(|sink| sink as &dyn OutputSink));
+
+    for sink in sinks {
+        debug!(sink = sink.describe(), "writing output");
+        if let Err(error) = sink.write(output).await {
+            SsceError::Export {
+                output: sink.describe(),
+                error,
+            }
+            .log();
+        }
+    }
+}
+
+/// A metrics file with the metric families matching the filters.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct MetricsOutput {
+    pub path: PathBuf,
+    /// Names of the metric families to include, all if empty. A trailing `*` matches any
+    /// suffix, e.g. `source_*`.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Names of the metric families to leave out, applied after `include`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl MetricsOutput {
+    fn matches(patterns: &[String], family: &str) -> bool {
+        patterns
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => family.starts_with(prefix),
+                None => family == pattern,
+            })
+    }
+
+    /// Filter encoded metrics by family. Each family starts with its `# HELP` line.
+    fn filter(&self, encoded: &str) -> String {
+        let mut filtered = String::new();
+        let mut keep = false;
+        for line in encoded.split_inclusive('\n') {
+            if let Some(help) = line.strip_prefix("# HELP ") {
+                let family = help.split_whitespace().next().unwrap_or_default();
+                keep = (self.include.is_empty() || Self::matches(&self.include, family))
+                    && !Self::matches(&self.exclude, family);
+            } else if line.starts_with("# EOF") {
+                keep = true;
+            }
+            if keep {
+                filtered.push_str(line);
+            }
+        }
+        filtered
+    }
+}
+
+impl OutputSink for MetricsOutput {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a> {
+        Box::pin(async move {
+            create_parent(&self.path)?;
+            write_atomic(&self.path, self.filter(output.metrics))
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Content {
+    #[default]
+    Metrics,
+    Report,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Post,
+    Put,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct HttpSink {
+    pub url: String,
+    #[serde(default)]
+    pub method: HttpMethod,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+    /// Whether the metrics or the JSON report is sent.
+    #[serde(default)]
+    pub content: Content,
+}
+
+impl OutputSink for HttpSink {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let mut request = match self.method {
+                HttpMethod::Post => output.client.post(&self.url),
+                HttpMethod::Put => output.client.put(&self.url),
+            };
+            request = match self.content {
+                Content::Metrics => request
+                    .header("Content-Type", OPENMETRICS)
+                    .body(output.metrics.to_owned()),
+                Content::Report => request.json(output.report),
+            };
+            for (name, value) in &self.headers {
+                request = request.header(name, value.expose());
+            }
+            request.send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct PushgatewaySink {
+    /// Base URL of the Pushgateway, e.g. `http://pushgateway:9091`.
+    pub url: String,
+    #[serde(default = "default_job")]
+    pub job: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Secret>,
+}
+
+fn default_job() -> String {
+    "ssce".into()
+}
+
+impl OutputSink for PushgatewaySink {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    /// Replace the metrics of this job and host, so metrics of sources which are gone are
+    /// removed like in the metrics file.
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a> {
+        Box::pin(async move {
+            let url = format!(
+                "{}/metrics/job/{}/instance/{}",
+                self.url.trim_end_matches('/'),
+                self.job,
+                hostname()
+            );
+            let mut request = output
+                .client
+                .put(url)
+                .header("Content-Type", OPENMETRICS)
+                .body(output.metrics.to_owned());
+            for (name, value) in &self.headers {
+                request = request.header(name, value.expose());
+            }
+            request.send().await?.error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct ReportSink {
+    pub path: PathBuf,
+}
+
+impl OutputSink for ReportSink {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn write<'a>(&'a self, output: &'a Output<'a>) -> SinkFuture<'a> {
+        Box::pin(async move {
+            create_parent(&self.path)?;
+            write_atomic(&self.path, serde_json::to_vec_pretty(output.report)?)
+        })
+    }
+}
@@ -0,0 +1,191 @@
+//! Mail delivery to an SMTP server, for hosts without a local MTA. Only what notifications need
+//! is implemented: implicit TLS or STARTTLS, `AUTH PLAIN` and a single message per connection.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+use tracing::debug;
+
+use crate::{notify::hostname, secret::Secret};
+
+/// How long the whole delivery of a mail may take.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct SmtpConfig {
+    pub host: String,
+    /// Defaults to 587 for STARTTLS, 465 for TLS and 25 without encryption.
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Upgrade the connection with `STARTTLS`, as on the submission port.
+    #[default]
+    Starttls,
+    /// TLS from the start, as on port 465.
+    Tls,
+    /// No encryption, only for relays on the host or in a trusted network. Credentials are
+    /// refused.
+    None,
+}
+
+impl SmtpConfig {
+    fn port(&self) -> u16 {
+        self.port.unwrap_or(match self.tls {
+            SmtpTls::Starttls => 587,
+            SmtpTls::Tls => 465,
+            SmtpTls::None => 25,
+        })
+    }
+}
+
+/// Deliver a MIME message to the recipients.
+pub async fn send(config: &SmtpConfig, from: &str, to: &[String], message: &str) -> Result<()> {
+    if config.tls == SmtpTls::None && config.password.is_some() {
+        bail!("refusing to send SMTP credentials without TLS");
+    }
+    tokio::time::timeout(TIMEOUT, deliver(config, from, to, message))
+        .await
+        .with_context(|| format!("no answer from {} in time", config.host))?
+}
+
+async fn deliver(config: &SmtpConfig, from: &str, to: &[String], message: &str) -> Result<()> {
+    debug!(
+        host = config.host,
+        port = config.port(),
+        "connecting to smtp server"
+    );
+    let tcp = TcpStream::connect((config.host.as_str(), config.port())).await?;
+    match config.tls {
+        SmtpTls::Tls => {
+            let mut stream = BufStream::new(tls(config, tcp).await?);
+            expect(&mut stream, 220).await?;
+            session(config, &mut stream, from, to, message).await
+        }
+        SmtpTls::Starttls => {
+            let mut stream = BufStream::new(tcp);
+            expect(&mut stream, 220).await?;
+            command(&mut stream, &format!("EHLO {}", hostname()), 250).await?;
+            command(&mut stream, "STARTTLS", 220).await?;
+            let mut stream = BufStream::new(tls(config, stream.into_inner()).await?);
+            session(config, &mut stream, from, to, message).await
+        }
+        SmtpTls::None => {
+            let mut stream = BufStream::new(tcp);
+            expect(&mut stream, 220).await?;
+            session(config, &mut stream, from, to, message).await
+        }
+    }
+}
+
+/// Verify the server certificate against the Mozilla root certificates.
+async fn tls(
+    config: &SmtpConfig,
+    tcp: TcpStream,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let client = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = ServerName::try_from(config.host.clone())?;
+    Ok(TlsConnector::from(Arc::new(client))
+        .connect(name, tcp)
+        .await?)
+}
+
+async fn session<S: AsyncRead + AsyncWrite + Unpin>(
+    config: &SmtpConfig,
+    stream: &mut BufStream<S>,
+    from: &str,
+    to: &[String],
+    message: &str,
+) -> Result<()> {
+    command(stream, &format!("EHLO {}", hostname()), 250).await?;
+    if let Some(password) = &config.password {
+        let username = config.username.as_deref().unwrap_or(from);
+        let credentials = STANDARD.encode(format!("\0{username}\0{}", password.expose()));
+        command(stream, &format!("AUTH PLAIN {credentials}"), 235).await?;
+    }
+    command(stream, &format!("MAIL FROM:<{from}>"), 250).await?;
+    for recipient in to {
+        command(stream, &format!("RCPT TO:<{recipient}>"), 250).await?;
+    }
+    command(stream, "DATA", 354).await?;
+    // Lines starting with a dot are escaped with another one, a lone dot ends the message.
+    let mut data = String::new();
+    for line in message.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    if !data.ends_with("\r\n") {
+        data.push_str("\r\n");
+    }
+    data.push_str(".\r\n");
+    stream.write_all(data.as_bytes()).await?;
+    stream.flush().await?;
+    expect(stream, 250).await?;
+    command(stream, "QUIT", 221).await
+}
+
+/// Send a command and wait for the expected reply code.
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufStream<S>,
+    command: &str,
+    code: u16,
+) -> Result<()> {
+    stream
+        .write_all(format!("{command}\r\n").as_bytes())
+        .await?;
+    stream.flush().await?;
+    let verb = command.split_whitespace().next().unwrap_or_default();
+    expect(stream, code)
+        .await
+        .with_context(|| format!("{verb} failed"))
+}
+
+/// Read a reply, which spans several lines like `250-first` and `250 last`, and fail unless it
+/// has the expected code.
+async fn expect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufStream<S>,
+    code: u16,
+) -> Result<()> {
+    let mut reply = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            bail!("connection closed by the smtp server");
+        }
+        reply.push_str(&line);
+        if line.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+    }
+    if !reply.starts_with(&code.to_string()) {
+        bail!(
+            "unexpected reply from the smtp server: {}",
+            reply.trim_end()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::config::Config;
+
+/// Binary started by an enabled systemd service.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceBinary {
+    pub unit: String,
+    pub binary: PathBuf,
+    /// Package owning the binary, `None` for binaries installed outside the package manager.
+    pub package: Option<String>,
+}
+
+/// Resolve the binaries of all enabled systemd services and the packages they belong to.
+pub async fn service_binaries(config: &Config) -> Vec<ServiceBinary> {
+    if !config.systemd_services {
+        return Vec::new();
+    }
+
+    let units = match systemctl(&[
+        "list-unit-files",
+        "--type=service",
+        "--state=enabled",
+        "--no-legend",
+        "--no-pager",
+    ])
+    .await
+    {
+        Some(units) => units,
+        None => {
+            warn!("Failed to list systemd units");
+            return Vec::new();
+        }
+    };
+
+    let mut binaries = Vec::new();
+    for unit in units
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+    {
+        // Template units are only started as instances, e.g. `getty@tty1.service`.
+        if unit.contains("@.") {
+            continue;
+        }
+        let Some(exec_start) = systemctl(&["show", "--property=ExecStart", "--value", unit]).await
+        else {
+            continue;
+        };
+        for binary in exec_paths(&exec_start) {
+            let binary = std::fs::canonicalize(&binary).unwrap_or(binary);
+            let package = owning_package(&binary).await;
+            if package.is_none() {
+                debug!(unit, ?binary, "service binary is not packaged");
+            }
+            binaries.push(ServiceBinary {
+                unit: unit.to_owned(),
+                binary,
+                package,
+            });
+        }
+    }
+    binaries
+}
+
+async fn systemctl(args: &[&str]) -> Option<String> {
+    let output = Command::new("systemctl")
+        .args(args)
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Binaries of an `ExecStart` property like
+/// `{ path=/usr/sbin/sshd ; argv[]=/usr/sbin/sshd -D ; ignore_errors=no ; ... }`.
+fn exec_paths(exec_start: &str) -> Vec<PathBuf> {
+    exec_start
+        .split(';')
+        .filter_map(|field| {
+            field
+                .trim()
+                .trim_start_matches('{')
+                .trim()
+                .strip_prefix("path=")
+        })
+        .map(|path| PathBuf::from(path.trim()))
+        .collect()
+}
+
+/// Ask the package managers of the host which package owns a file.
+async fn owning_package(path: &Path) -> Option<String> {
+    // dpkg knows files by the path they were installed to, which differs from the canonical
+    // path on merged /usr systems.
+    let mut candidates = vec![path.to_owned()];
+    if let Ok(unmerged) = path.strip_prefix("/usr") {
+        candidates.push(Path::new("/").join(unmerged));
+    }
+
+    for candidate in &candidates {
+        let queries: [(&str, &[&str]); 3] = [
+            ("dpkg-query", &["--search"]),
+            ("rpm", &["--query", "--file", "--queryformat", "%{NAME}"]),
+            ("pacman", &["--query", "--owns", "--quiet"]),
+        ];
+        for (command, args) in queries {
+            let Ok(output) = Command::new(command)
+                .args(args)
+                .arg(candidate)
+                .kill_on_drop(true)
+                .output()
+                .await
+            else {
+                continue;
+            };
+            if !output.status.success() {
+                continue;
+            }
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let line = stdout.lines().next().unwrap_or_default();
+            // dpkg prints `package[:arch]: /path`
+            let package = match line.split_once(": ") {
+                Some((package, _)) if command == "dpkg-query" => package,
+                _ => line,
+            };
+            let package = package.split(':').next().unwrap_or_default().trim();
+            if !package.is_empty() {
+                return Some(package.to_owned());
+            }
+        }
+    }
+    None
+}
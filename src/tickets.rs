@@ -0,0 +1,234 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::Config,
+    notify::hostname,
+    policy::{PolicyUpdate, Violation},
+    proxy::Integration,
+    secret::Secret,
+};
+
+/// An issue tracker in which an issue is opened for every new policy violation.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TicketConfig {
+    Jira {
+        /// Base URL of Jira, e.g. `https://example.atlassian.net`.
+        url: String,
+        /// Key of the project, e.g. `SEC`.
+        project: String,
+        #[serde(default = "default_issue_type")]
+        issue_type: String,
+        /// User for basic authentication with an API token on Jira Cloud. Without a user, the
+        /// token is sent as bearer token, like personal access tokens on Jira Server.
+        user: Option<String>,
+        token: Secret,
+        /// Labels added to every issue.
+        #[serde(default)]
+        labels: Vec<String>,
+        /// Source tags naming the owners of a source, which are added to the issue.
+        #[serde(default = "default_owner_tags")]
+        owner_tags: Vec<String>,
+    },
+    Gitlab {
+        #[serde(default = "default_gitlab_url")]
+        url: String,
+        /// Project id or path, e.g. `group/security`.
+        project: String,
+        token: Secret,
+        /// Labels added to every issue.
+        #[serde(default)]
+        labels: Vec<String>,
+        /// Source tags naming the owners of a source, which are added to the issue.
+        #[serde(default = "default_owner_tags")]
+        owner_tags: Vec<String>,
+    },
+}
+
+fn default_issue_type() -> String {
+    "Bug".into()
+}
+
+fn default_gitlab_url() -> String {
+    "https://gitlab.com".into()
+}
+
+fn default_owner_tags() -> Vec<String> {
+    vec!["owner".into(), "team".into()]
+}
+
+/// Open an issue for every new policy violation. Issues carry a label derived from the
+/// violation, so a violation never gets a second issue, even when the state of previous runs is
+/// lost or several hosts report the same finding of a shared image.
+pub async fn open_tickets(config: &Config, update: &PolicyUpdate) {
+    let client = match config.proxy.client(Integration::Tickets) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to create HTTP client of the issue trackers: {e:?}");
+            return;
+        }
+    };
+    for tracker in &config.tickets {
+        for violation in &update.new {
+            if let Err(e) = open_ticket(&client, tracker, violation).await {
+                warn!(
+                    "Failed to open issue for {} in {}: {e:?}",
+                    violation.id, violation.source
+                );
+            }
+        }
+    }
+}
+
+async fn open_ticket(
+    client: &reqwest::Client,
+    tracker: &TicketConfig,
+    violation: &Violation,
+) -> Result<()> {
+    let label = dedup_label(violation);
+    let title = format!(
+        "{} {} in {} of {}",
+        violation.severity, violation.id, violation.package, violation.source
+    );
+    match tracker {
+        TicketConfig::Jira {
+            url,
+            project,
+            issue_type,
+            user,
+            token,
+            labels,
+            owner_tags,
+        } => {
+            let url = url.trim_end_matches('/');
+            let auth = |request: reqwest::RequestBuilder| match user {
+                Some(user) => request.basic_auth(user, Some(token.expose())),
+                None => request.bearer_auth(token.expose()),
+            };
+            let existing: Value = auth(client.get(format!("{url}/rest/api/2/search")))
+                .query(&[
+                    (
+                        "jql",
+                        format!("project = \"{project}\" AND labels = \"{label}\""),
+                    ),
+                    ("maxResults", "1".into()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if existing["total"].as_u64().unwrap_or_default() > 0 {
+                debug!(label, "issue exists already");
+                return Ok(());
+            }
+            info!("Opening Jira issue: {title}");
+            auth(client.post(format!("{url}/rest/api/2/issue")))
+                .json(&json!({
+                    "fields": {
+                        "project": { "key": project },
+                        "issuetype": { "name": issue_type },
+                        "summary": title,
+                        "description": description(violation, owner_tags),
+                        "labels": labels.iter().chain([&label]).collect::<Vec<_>>(),
+                    }
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        TicketConfig::Gitlab {
+            url,
+            project,
+            token,
+            labels,
+            owner_tags,
+        } => {
+            let issues = format!(
+                "{}/api/v4/projects/{}/issues",
+                url.trim_end_matches('/'),
+                project.replace('/', "%2F")
+            );
+            let existing: Vec<Value> = client
+                .get(&issues)
+                .header("PRIVATE-TOKEN", token.expose())
+                .query(&[
+                    ("labels", label.as_str()),
+                    ("state", "all"),
+                    ("per_page", "1"),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if !existing.is_empty() {
+                debug!(label, "issue exists already");
+                return Ok(());
+            }
+            info!("Opening GitLab issue: {title}");
+            client
+                .post(&issues)
+                .header("PRIVATE-TOKEN", token.expose())
+                .json(&json!({
+                    "title": title,
+                    "description": description(violation, owner_tags),
+                    "labels": labels.iter().chain([&label]).cloned().collect::<Vec<_>>().join(","),
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+    }
+    Ok(())
+}
+
+fn description(violation: &Violation, owner_tags: &[String]) -> String {
+    let mut description = format!(
+        "{} ({}) was found in {} {} of {} on {}.\n\n",
+        violation.id,
+        violation.severity,
+        violation.package,
+        violation.version,
+        violation.source,
+        hostname()
+    );
+    if violation.fixed_versions.is_empty() {
+        description += "No fixed version is known yet.\n";
+    } else {
+        description += &format!("Fixed in: {}\n", violation.fixed_versions.join(", "));
+    }
+    let owners: Vec<String> = owner_tags
+        .iter()
+        .filter_map(|tag| Some(format!("{tag}: {}", violation.tags.get(tag)?)))
+        .collect();
+    if !owners.is_empty() {
+        description += &format!("Owners: {}\n", owners.join(", "));
+    }
+    for url in &violation.urls {
+        description += &format!("\n{url}");
+    }
+    description
+}
+
+/// Label identifying a violation of a package in a source, independent of the version and of
+/// the image id, as the same issue still applies after an update or rebuild which doesn't fix
+/// it. It's a hash, as Jira labels can't contain spaces and GitLab labels are limited in
+/// length.
+fn dedup_label(violation: &Violation) -> String {
+    let key = format!(
+        "{}\n{}\n{}",
+        violation.source.name(),
+        violation.id,
+        violation.package
+    );
+    // FNV-1a, which unlike the hasher of the standard library is stable across releases.
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    format!("ssce-{hash:016x}")
+}
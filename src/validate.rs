@@ -0,0 +1,138 @@
+use serde_json::Value;
+
+/// Problems found in an SBOM. Errors violate the SPDX or CycloneDX format, warnings are fields
+/// the exporter needs for useful metrics and scans.
+#[derive(Debug, Default)]
+pub struct Validation {
+    pub format: String,
+    pub packages: usize,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Check the required fields of an SPDX 2.x JSON or CycloneDX JSON document and the fields the
+/// exporter relies on, which are versions and package URLs.
+pub fn validate_sbom(sbom: &Value) -> Validation {
+    if sbom.get("spdxVersion").is_some() {
+        validate_spdx(sbom)
+    } else if sbom.get("bomFormat").is_some() {
+        validate_cyclonedx(sbom)
+    } else {
+        Validation {
+            format: "unknown".into(),
+            errors: vec!["neither an SPDX nor a CycloneDX JSON document".into()],
+            ..Default::default()
+        }
+    }
+}
+
+fn validate_spdx(sbom: &Value) -> Validation {
+    let mut validation = Validation {
+        format: sbom["spdxVersion"].as_str().unwrap_or("SPDX").to_owned(),
+        ..Default::default()
+    };
+    require_strings(
+        sbom,
+        "document",
+        &[
+            "spdxVersion",
+            "dataLicense",
+            "SPDXID",
+            "name",
+            "documentNamespace",
+        ],
+        &mut validation.errors,
+    );
+    require_strings(
+        &sbom["creationInfo"],
+        "creationInfo",
+        &["created"],
+        &mut validation.errors,
+    );
+    if !sbom["creationInfo"]["creators"].is_array() {
+        validation
+            .errors
+            .push("creationInfo: missing creators".into());
+    }
+
+    let packages = sbom["packages"].as_array().cloned().unwrap_or_default();
+    validation.packages = packages.len();
+    for (i, package) in packages.iter().enumerate() {
+        let name = package_name(package, "name", i);
+        require_strings(
+            package,
+            &name,
+            &["SPDXID", "name", "downloadLocation"],
+            &mut validation.errors,
+        );
+        if package["versionInfo"]
+            .as_str()
+            .unwrap_or_default()
+            .is_empty()
+        {
+            validation.warnings.push(format!(
+                "{name}: missing versionInfo, the package is left out of the sbom metrics"
+            ));
+        }
+        let has_purl = package["externalRefs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|reference| reference["referenceType"] == "purl");
+        if !has_purl {
+            validation.warnings.push(format!(
+                "{name}: missing purl reference, vulnerabilities can only be matched by name"
+            ));
+        }
+    }
+    validation
+}
+
+fn validate_cyclonedx(sbom: &Value) -> Validation {
+    let mut validation = Validation {
+        format: format!(
+            "CycloneDX {}",
+            sbom["specVersion"].as_str().unwrap_or_default()
+        ),
+        ..Default::default()
+    };
+    if sbom["bomFormat"] != "CycloneDX" {
+        validation
+            .errors
+            .push("document: bomFormat must be CycloneDX".into());
+    }
+    require_strings(sbom, "document", &["specVersion"], &mut validation.errors);
+
+    let components = sbom["components"].as_array().cloned().unwrap_or_default();
+    validation.packages = components.len();
+    for (i, component) in components.iter().enumerate() {
+        let name = package_name(component, "name", i);
+        require_strings(component, &name, &["type", "name"], &mut validation.errors);
+        if component["version"].as_str().unwrap_or_default().is_empty() {
+            validation.warnings.push(format!(
+                "{name}: missing version, the package is left out of the sbom metrics"
+            ));
+        }
+        if component["purl"].as_str().unwrap_or_default().is_empty() {
+            validation.warnings.push(format!(
+                "{name}: missing purl, vulnerabilities can only be matched by name"
+            ));
+        }
+    }
+    validation
+}
+
+fn package_name(package: &Value, key: &str, index: usize) -> String {
+    match package[key].as_str() {
+        Some(name) => format!("package {name}"),
+        None => format!("package #{index}"),
+    }
+}
+
+fn require_strings(value: &Value, context: &str, keys: &[&str], errors: &mut Vec<String>) {
+    for key in keys {
+        if !value[key].is_string() {
+            errors.push(format!("{context}: missing {key}"));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use chrono::Utc;
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::{
+    config::{Config, Source},
+    fs::write_atomic,
+    sbom::{Sbom, SbomEntry},
+    scan::{Cvss, FixState, Scan, ScanEntry},
+    severity::Severity,
+};
+
+const SPEC_VERSION: &str = "1.5";
+
+/// Write a CycloneDX vulnerability disclosure report per source, with the packages of its SBOM
+/// as components and its findings as vulnerabilities affecting them.
+pub fn write_vdrs(
+    config: &Config,
+    sboms: &HashMap<Source, Value>,
+    scans: &HashMap<Source, Scan>,
+) -> Result<()> {
+    let Some(dir) = &config.vdr_path else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(dir)?;
+
+    for (source, sbom) in sboms {
+        let sbom: Sbom = match serde_json::from_value(sbom.clone()) {
+            Ok(sbom) => sbom,
+            Err(e) => {
+                warn!("Failed to parse SBOM of {source}: {e}");
+                continue;
+            }
+        };
+        let path = dir.join(format!("{}.vdr.cdx.json", source.slug()));
+        debug!(?path, "writing vulnerability disclosure report");
+        write_atomic(
+            &path,
+            serde_json::to_vec_pretty(&without_nulls(vdr(source, &sbom, scans.get(source))))?,
+        )?;
+    }
+    Ok(())
+}
+
+fn vdr(source: &Source, sbom: &Sbom, scan: Option<&Scan>) -> Value {
+    let mut components = BTreeMap::new();
+    for package in &sbom.packages {
+        let reference = package_ref(package);
+        components.insert(
+            reference.clone(),
+            json!({
+                "type": "library",
+                "bom-ref": reference,
+                "name": package.name,
+                "version": package.versionInfo,
+                "purl": package.purl(),
+            }),
+        );
+    }
+
+    // Findings of the same vulnerability in several packages are one vulnerability affecting
+    // all of them.
+    let mut vulnerabilities: BTreeMap<&str, (&ScanEntry, Vec<String>)> = BTreeMap::new();
+    for entry in scan.iter().flat_map(|scan| &scan.matches) {
+        let reference = artifact_ref(entry);
+        components.entry(reference.clone()).or_insert_with(|| {
+            json!({
+                "type": "library",
+                "bom-ref": reference,
+                "name": entry.artifact.name,
+                "version": entry.artifact.version,
+                "purl": entry.artifact.purl,
+            })
+        });
+        vulnerabilities
+            .entry(&entry.vulnerability.id)
+            .or_insert_with(|| (entry, Vec::new()))
+            .1
+            .push(reference);
+    }
+
+    let source_type = match source {
+        Source::DockerImage { .. } => "container",
+        _ => "file",
+    };
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": SPEC_VERSION,
+        "version": 1,
+        "metadata": {
+            "timestamp": Utc::now().to_rfc3339(),
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "software_supply_chain_exporter",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": source_type,
+                "bom-ref": source.slug(),
+                "name": source.to_string(),
+            },
+        },
+        "components": components.into_values().collect::<Vec<_>>(),
+        "vulnerabilities": vulnerabilities
+            .into_values()
+            .map(|(entry, affects)| vulnerability(entry, affects))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn vulnerability(entry: &ScanEntry, affects: Vec<String>) -> Value {
+    let vulnerability = &entry.vulnerability;
+    let recommendation = match vulnerability.fix.state {
+        FixState::Fixed => Some(format!(
+            "Upgrade to {}",
+            vulnerability.fix.versions.join(" or ")
+        )),
+        _ => None,
+    };
+    json!({
+        "id": vulnerability.id,
+        "source": {
+            "url": vulnerability.urls.first(),
+        },
+        "references": entry
+            .related_vulnerabilities
+            .iter()
+            .filter(|related| related.id != vulnerability.id)
+            .map(|related| json!({ "id": related.id, "source": {} }))
+            .collect::<Vec<_>>(),
+        "ratings": ratings(vulnerability.severity, &vulnerability.cvss),
+        "description": vulnerability.description,
+        "advisories": vulnerability
+            .urls
+            .iter()
+            .map(|url| json!({ "url": url }))
+            .collect::<Vec<_>>(),
+        "recommendation": recommendation,
+        "affects": affects
+            .into_iter()
+            .map(|reference| json!({ "ref": reference }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn ratings(severity: Severity, cvss: &[Cvss]) -> Vec<Value> {
+    let severity = match severity {
+        Severity::Negligible => "info".to_owned(),
+        severity => severity.as_str().to_lowercase(),
+    };
+    let mut ratings = cvss
+        .iter()
+        .map(|cvss| {
+            let method = match cvss.version.as_str() {
+                "2.0" => "CVSSv2",
+                "3.0" => "CVSSv3",
+                "3.1" => "CVSSv31",
+                "4.0" => "CVSSv4",
+                _ => "other",
+            };
+            json!({
+                "source": { "name": cvss.source },
+                "score": cvss.metrics.base_score.to_f64(),
+                "method": method,
+                "vector": cvss.vector,
+            })
+        })
+        .collect::<Vec<_>>();
+    ratings.push(json!({ "severity": severity }));
+    ratings
+}
+
+/// Leave out keys without a value, as CycloneDX doesn't allow `null` for its string fields like
+/// `purl`.
+fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(without_nulls).collect()),
+        value => value,
+    }
+}
+
+fn package_ref(package: &SbomEntry) -> String {
+    match package.purl() {
+        Some(purl) => purl.to_owned(),
+        None => format!("{}@{}", package.name, package.versionInfo),
+    }
+}
+
+fn artifact_ref(entry: &ScanEntry) -> String {
+    match &entry.artifact.purl {
+        Some(purl) if !purl.is_empty() => purl.clone(),
+        _ => format!("{}@{}", entry.artifact.name, entry.artifact.version),
+    }
+}
@@ -0,0 +1,302 @@
+//! Listener for push events of registries, so pushed images are scanned right away instead of
+//! on the next scheduled run. Pushed images are kept in `registry_images.json` in the base
+//! path until the retention passes, and scanned from the registry like images of containers.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::Notify,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{Config, Source, Tags},
+    fs::write_atomic,
+    schema::duration_schema,
+    secret::Secret,
+};
+
+/// Maximum size of an event, larger requests are refused.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[schemars(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Address to listen on, e.g. `0.0.0.0:9851`.
+    pub listen: String,
+    /// Token the registry has to send, either as `Authorization` header, like Harbor does, or
+    /// as `token` query parameter for registries without custom headers, like Docker Hub.
+    pub token: Option<Secret>,
+    /// How long pushed images are scanned and exported after their last push.
+    #[schemars(schema_with = "duration_schema")]
+    #[serde(with = "humantime_serde", default = "default_retention")]
+    pub retention: Duration,
+}
+
+fn default_retention() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
+}
+
+/// An image pushed to a registry.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct PushedImage {
+    /// Reference of the image, e.g. `harbor.example.com/library/nginx:1.25`.
+    pub reference: String,
+    /// Digest of the manifest, if the event contains it. Docker Hub events don't.
+    pub digest: Option<String>,
+    pub pushed: DateTime<Utc>,
+}
+
+impl PushedImage {
+    /// Registry host of the image, `docker.io` for images without one.
+    pub fn registry(&self) -> &str {
+        match self.reference.split_once('/') {
+            Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => host,
+            _ => "docker.io",
+        }
+    }
+
+    /// Images are identified by their digest, so pushing a new image with the same tag makes
+    /// it a new source, which is due right away.
+    pub fn source(&self) -> Source {
+        Source::DockerImage {
+            name: self.reference.clone(),
+            id: self
+                .digest
+                .clone()
+                .unwrap_or_else(|| self.reference.clone()),
+            digest: self
+                .digest
+                .as_ref()
+                .map(|digest| format!("{}@{digest}", repository(&self.reference))),
+        }
+    }
+}
+
+/// The reference without tag. The tag is after the last colon, unless that colon belongs to a
+/// registry port.
+fn repository(reference: &str) -> &str {
+    match reference.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository,
+        _ => reference,
+    }
+}
+
+/// The images pushed according to a webhook event of Harbor, Docker Hub or the CNCF
+/// distribution registry. Other events, like deletions or scans, are ignored.
+pub fn pushed_images(event: &Value, now: DateTime<Utc>) -> Vec<PushedImage> {
+    let image = |reference: String, digest: Option<&str>| PushedImage {
+        reference,
+        digest: digest.filter(|digest| !digest.is_empty()).map(Into::into),
+        pushed: now,
+    };
+    // Harbor
+    if event["type"] == "PUSH_ARTIFACT" {
+        return event["event_data"]["resources"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|resource| {
+                Some(image(
+                    resource["resource_url"].as_str()?.to_owned(),
+                    resource["digest"].as_str(),
+                ))
+            })
+            .collect();
+    }
+    // Docker Hub
+    if let (Some(repository), Some(tag)) = (
+        event["repository"]["repo_name"].as_str(),
+        event["push_data"]["tag"].as_str(),
+    ) {
+        return vec![image(format!("{repository}:{tag}"), None)];
+    }
+    // Distribution registry notifications
+    event["events"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|event| event["action"] == "push")
+        .filter_map(|event| {
+            let target = &event["target"];
+            let tag = target["tag"].as_str()?;
+            let host = event["request"]["host"].as_str()?;
+            let repository = target["repository"].as_str()?;
+            Some(image(
+                format!("{host}/{repository}:{tag}"),
+                target["digest"].as_str(),
+            ))
+        })
+        .collect()
+}
+
+fn load(config: &Config) -> Vec<PushedImage> {
+    std::fs::read(config.registry_images_path())
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Add pushed images, replacing earlier pushes of the same tag, and drop images pushed before
+/// the retention.
+pub fn record_pushes(config: &Config, pushed: Vec<PushedImage>) -> Result<()> {
+    let mut images: BTreeMap<String, PushedImage> = load(config)
+        .into_iter()
+        .map(|image| (image.reference.clone(), image))
+        .collect();
+    for image in pushed {
+        info!(image = image.reference, "image pushed to registry");
+        images.insert(image.reference.clone(), image);
+    }
+    let retention = config
+        .webhook
+        .as_ref()
+        .map_or_else(default_retention, |webhook| webhook.retention);
+    let oldest = Utc::now() - chrono::Duration::from_std(retention)?;
+    let images: Vec<PushedImage> = images
+        .into_values()
+        .filter(|image| image.pushed >= oldest)
+        .collect();
+    std::fs::create_dir_all(&config.base_path)?;
+    write_atomic(
+        &config.registry_images_path(),
+        serde_json::to_vec_pretty(&images)?,
+    )
+}
+
+/// Images pushed to registries within the retention, tagged with `origin: registry` and the
+/// host of the registry.
+pub fn registry_sources(config: &Config) -> HashMap<Source, Tags> {
+    let Some(webhook) = &config.webhook else {
+        return HashMap::new();
+    };
+    let oldest = Utc::now() - chrono::Duration::from_std(webhook.retention).unwrap_or_default();
+    load(config)
+        .into_iter()
+        .filter(|image| image.pushed >= oldest)
+        .map(|image| {
+            let mut tags = config.tags.clone();
+            tags.insert("origin".into(), "registry".into());
+            tags.insert("registry".into(), image.registry().to_owned());
+            (image.source(), tags)
+        })
+        .collect()
+}
+
+/// Accept webhook events until the listener fails, and notify the daemon about every push.
+pub async fn listen(config: &Config, pushed: Arc<Notify>) -> Result<()> {
+    let Some(webhook) = &config.webhook else {
+        return Ok(());
+    };
+    let listener = TcpListener::bind(&webhook.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", webhook.listen))?;
+    info!(address = webhook.listen, "listening for registry webhooks");
+    // Requests are handled one after another, so pushes are recorded without races. Slow
+    // clients are cut off, so they can't block the listener.
+    loop {
+        let (mut stream, peer) = listener.accept().await?;
+        debug!(%peer, "webhook connection");
+        let handled = tokio::time::timeout(REQUEST_TIMEOUT, handle(&mut stream, webhook))
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("Timed out")));
+        let (status, body) = match handled {
+            Ok(pushed_images) if pushed_images.is_empty() => ("200 OK", "ignored"),
+            Ok(pushed_images) => match record_pushes(config, pushed_images) {
+                Ok(()) => {
+                    pushed.notify_one();
+                    ("202 Accepted", "scheduled")
+                }
+                Err(e) => {
+                    warn!("Failed to record pushed images: {e:?}");
+                    ("500 Internal Server Error", "failed")
+                }
+            },
+            Err(e) => {
+                warn!(%peer, "Refused webhook request: {e:?}");
+                ("400 Bad Request", "refused")
+            }
+        };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    }
+}
+
+/// Read one request and return the images it reports as pushed.
+async fn handle(stream: &mut TcpStream, webhook: &WebhookConfig) -> Result<Vec<PushedImage>> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 8192];
+    let head_end = loop {
+        if let Some(end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if request.len() > MAX_REQUEST_SIZE {
+            bail!("Request too large");
+        }
+        match stream.read(&mut buffer).await? {
+            0 => bail!("Connection closed before the end of the request"),
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    };
+    let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, target) = (
+        request_line.next().unwrap_or_default(),
+        request_line.next().unwrap_or_default(),
+    );
+    if method != "POST" {
+        bail!("Unexpected method {method}");
+    }
+    let headers: HashMap<String, &str> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+
+    if let Some(token) = &webhook.token {
+        let authorization = headers
+            .get("authorization")
+            .map(|value| value.strip_prefix("Bearer ").unwrap_or(value).to_owned());
+        let query = target
+            .split_once('?')
+            .into_iter()
+            .flat_map(|(_, query)| query.split('&'))
+            .find_map(|parameter| parameter.strip_prefix("token="))
+            .map(Into::into);
+        if authorization.or(query).as_deref() != Some(token.expose()) {
+            bail!("Missing or wrong token");
+        }
+    }
+
+    let length: usize = headers
+        .get("content-length")
+        .context("Missing Content-Length")?
+        .parse()?;
+    if length > MAX_REQUEST_SIZE {
+        bail!("Request too large");
+    }
+    while request.len() < head_end + length {
+        match stream.read(&mut buffer).await? {
+            0 => bail!("Connection closed before the end of the body"),
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let event: Value = serde_json::from_slice(&request[head_end..head_end + length])?;
+    Ok(pushed_images(&event, Utc::now()))
+}
@@ -0,0 +1,79 @@
+use anyhow::{bail, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::sbom::spdx_package;
+
+/// Programs registered for uninstallation, which includes MSI packages and most other
+/// installers, winget included.
+const UNINSTALL_KEYS: &str = "HKLM:\\Software\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\*, \
+    HKLM:\\Software\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\*";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InstalledProgram {
+    display_name: String,
+    #[serde(default)]
+    display_version: Option<String>,
+}
+
+/// SPDX package entries for installed Windows programs and Chocolatey packages, which syft
+/// doesn't catalog.
+#[tracing::instrument]
+pub async fn windows_packages() -> Result<Vec<Value>> {
+    let mut packages = installed_programs().await?;
+    match chocolatey_packages().await {
+        Ok(chocolatey) => packages.extend(chocolatey),
+        Err(e) => warn!("Failed to list chocolatey packages: {e:?}"),
+    }
+    Ok(packages)
+}
+
+async fn installed_programs() -> Result<Vec<Value>> {
+    debug!("reading installed programs from the registry");
+    let output = Command::new("powershell")
+        .arg("-NoProfile")
+        .arg("-Command")
+        .arg(format!(
+            "@(Get-ItemProperty {UNINSTALL_KEYS} | Where-Object DisplayName | \
+             Select-Object DisplayName, DisplayVersion) | ConvertTo-Json"
+        ))
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("powershell exited with {}", output.status);
+    }
+
+    let programs: Vec<InstalledProgram> = serde_json::from_slice(&output.stdout)?;
+    Ok(programs
+        .into_iter()
+        .map(|program| {
+            let version = program.display_version.unwrap_or_default();
+            let purl = format!("pkg:generic/{}@{version}", program.display_name);
+            spdx_package(&program.display_name, &version, &purl)
+        })
+        .collect())
+}
+
+async fn chocolatey_packages() -> Result<Vec<Value>> {
+    debug!("listing chocolatey packages");
+    let output = Command::new("choco")
+        .arg("list")
+        .arg("--limit-output") // One `name|version` line per package
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("choco exited with {}", output.status);
+    }
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.split_once('|'))
+        .map(|(name, version)| {
+            let purl = format!("pkg:chocolatey/{name}@{version}");
+            spdx_package(name, version, &purl)
+        })
+        .collect())
+}
@@ -0,0 +1,97 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::config::{Config, Source};
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, Default)]
+#[schemars(deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Directory for temporary files of syft and grype, defaults to `tmp` in the base path.
+    pub path: Option<PathBuf>,
+    /// Abort a scan when its temporary files grow beyond this size.
+    pub max_size_mb: Option<u64>,
+}
+
+const SIZE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A dedicated temporary directory for a single scanner invocation, removed when dropped.
+pub struct Workspace {
+    path: PathBuf,
+    max_bytes: Option<u64>,
+}
+
+impl Workspace {
+    pub fn new(config: &Config, source: &Source, stage: &str) -> Result<Self> {
+        let path = config.workspace_path().join(format!(
+            "{}.{stage}.{}",
+            source.slug(),
+            std::process::id()
+        ));
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::create_dir_all(&path)?;
+        Ok(Self {
+            path,
+            max_bytes: config.workspace.max_size_mb.map(|mb| mb * 1024 * 1024),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Make the command put its temporary files into the workspace.
+    pub fn apply(&self, command: &mut Command) {
+        command.env("TMPDIR", &self.path).env("TMP", &self.path);
+    }
+
+    /// Run the future, failing once the workspace exceeds the size limit. The future is
+    /// dropped in that case, which kills the scanner when it was spawned with `kill_on_drop`.
+    pub async fn limit<T>(&self, run: impl Future<Output = Result<T>>) -> Result<T> {
+        let Some(max_bytes) = self.max_bytes else {
+            return run.await;
+        };
+        tokio::select! {
+            result = run => result,
+            size = exceeded(&self.path, max_bytes) => {
+                bail!("temporary files grew to {size} bytes, exceeding the workspace size limit")
+            }
+        }
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        debug!(path = ?self.path, "removing workspace");
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!("Failed to remove workspace {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Completes with the size of the directory once it exceeds the limit.
+async fn exceeded(path: &Path, max_bytes: u64) -> u64 {
+    loop {
+        tokio::time::sleep(SIZE_POLL_INTERVAL).await;
+        let size = WalkDir::new(path)
+            .into_iter()
+            .filter_map(|entry| entry.ok()?.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum();
+        if size > max_bytes {
+            return size;
+        }
+    }
+}
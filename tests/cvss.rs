@@ -0,0 +1,68 @@
+use std::collections::BTreeMap;
+
+use software_supply_chain_exporter::cvss::environmental_score;
+
+const CRITICAL: &str = "CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H";
+const XSS: &str = "CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N";
+
+fn environment(metrics: &[(&str, &str)]) -> BTreeMap<String, String> {
+    metrics
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn without_environment_the_base_score_is_kept() {
+    assert_eq!(environmental_score(CRITICAL, &environment(&[])), Some(9.8));
+    assert_eq!(environmental_score(XSS, &environment(&[])), Some(6.1));
+    assert_eq!(
+        environmental_score(CRITICAL, &environment(&[("MAV", "X")])),
+        Some(9.8)
+    );
+}
+
+#[test]
+fn modified_metrics_replace_the_base_metrics() {
+    assert_eq!(
+        environmental_score(CRITICAL, &environment(&[("MAV", "L")])),
+        Some(8.4)
+    );
+    assert_eq!(
+        environmental_score(
+            CRITICAL,
+            &environment(&[("MC", "N"), ("MI", "N"), ("MA", "N")])
+        ),
+        Some(0.0)
+    );
+}
+
+#[test]
+fn requirements_weight_the_impact() {
+    assert_eq!(
+        environmental_score(
+            CRITICAL,
+            &environment(&[("CR", "L"), ("IR", "L"), ("AR", "L")])
+        ),
+        Some(8.0)
+    );
+    assert_eq!(
+        environmental_score(XSS, &environment(&[("CR", "H"), ("IR", "H")])),
+        Some(7.4)
+    );
+}
+
+#[test]
+fn other_versions_are_not_scored() {
+    assert_eq!(
+        environmental_score("AV:N/AC:L/Au:N/C:P/I:P/A:P", &environment(&[])),
+        None
+    );
+    assert_eq!(
+        environmental_score(
+            "CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:H/VA:H/SC:N/SI:N/SA:N",
+            &environment(&[])
+        ),
+        None
+    );
+}
@@ -0,0 +1,96 @@
+use serde_json::json;
+use software_supply_chain_exporter::{
+    email::{send_email, EmailConfig},
+    notify::{Event, Notification},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+/// An SMTP server accepting one mail, returning the commands and the message it received.
+async fn smtp_server(listener: TcpListener) -> (Vec<String>, String) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    write.write_all(b"220 test ESMTP\r\n").await.unwrap();
+    let mut commands = Vec::new();
+    let mut message = String::new();
+    while let Some(line) = lines.next_line().await.unwrap() {
+        let reply: &[u8] = match line.split_whitespace().next().unwrap_or_default() {
+            "EHLO" => b"250-test\r\n250 8BITMIME\r\n",
+            "DATA" => b"354 go ahead\r\n",
+            "QUIT" => b"221 bye\r\n",
+            _ => b"250 ok\r\n",
+        };
+        commands.push(line.clone());
+        write.write_all(reply).await.unwrap();
+        if line == "DATA" {
+            while let Some(line) = lines.next_line().await.unwrap() {
+                if line == "." {
+                    break;
+                }
+                message.push_str(&line);
+                message.push('\n');
+            }
+            write.write_all(b"250 queued\r\n").await.unwrap();
+        }
+        if line == "QUIT" {
+            break;
+        }
+    }
+    (commands, message)
+}
+
+#[tokio::test]
+async fn mail_is_delivered_over_smtp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(smtp_server(listener));
+
+    let config: EmailConfig = serde_json::from_value(json!({
+        "from": "ssce@example.com",
+        "to": ["security@example.com", "ops@example.com"],
+        "smtp": { "host": "127.0.0.1", "port": port, "tls": "none" },
+    }))
+    .unwrap();
+    let notification = Notification {
+        event: Event::Digest,
+        subject: "digest".into(),
+        text: "Nothing new.\n".into(),
+        data: json!({}),
+    };
+    send_email(&config, &notification, None).await.unwrap();
+
+    let (commands, message) = server.await.unwrap();
+    assert!(commands[0].starts_with("EHLO "));
+    assert_eq!(
+        commands[1..],
+        [
+            "MAIL FROM:<ssce@example.com>",
+            "RCPT TO:<security@example.com>",
+            "RCPT TO:<ops@example.com>",
+            "DATA",
+            "QUIT",
+        ]
+    );
+    assert!(message.contains("To: security@example.com, ops@example.com\n"));
+}
+
+#[tokio::test]
+async fn credentials_require_tls() {
+    let config: EmailConfig = serde_json::from_value(json!({
+        "from": "ssce@example.com",
+        "to": ["security@example.com"],
+        "smtp": { "host": "127.0.0.1", "tls": "none", "username": "ssce", "password": "secret" },
+    }))
+    .unwrap();
+    let notification = Notification {
+        event: Event::Digest,
+        subject: "digest".into(),
+        text: String::new(),
+        data: json!({}),
+    };
+    let error = send_email(&config, &notification, None).await.unwrap_err();
+    assert!(error.to_string().contains("without TLS"));
+}
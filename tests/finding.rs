@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+
+use software_supply_chain_exporter::{
+    config::Source,
+    finding::{from_grype, from_trivy, Finding, TrivyReport},
+    scan::{FixState, Scan},
+    schema::validate,
+    severity::Severity,
+};
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn source() -> Source {
+    Source::DockerImage {
+        name: "ghcr.io/famedly/example:latest".into(),
+        id: "sha256:0123456789abcdef".into(),
+        digest: None,
+    }
+}
+
+fn grype_findings() -> Vec<Finding> {
+    let scan: Scan = serde_json::from_str(&fixture("grype.json")).unwrap();
+    from_grype(&source(), &scan)
+}
+
+fn trivy_findings() -> Vec<Finding> {
+    let report: TrivyReport = serde_json::from_str(&fixture("trivy.json")).unwrap();
+    from_trivy(&source(), &report)
+}
+
+#[test]
+fn grype_scan_deserializes() {
+    let scan: Scan = serde_json::from_str(&fixture("grype.json")).unwrap();
+    assert_eq!(scan.matches.len(), 2);
+    assert_eq!(scan.descriptor.name, "grype");
+    assert_eq!(scan.descriptor.version, "0.79.1");
+
+    let entry = &scan.matches[0];
+    assert_eq!(entry.vulnerability.fix.state, FixState::Fixed);
+    assert_eq!(entry.vulnerability.fix.available[0].date, "2025-04-08");
+    assert_eq!(entry.vulnerability.cvss[0].vector_metric("AV"), Some("N"));
+    assert_eq!(entry.canonical_id(), "CVE-2025-4574");
+    assert_eq!(
+        entry.artifact.purl.as_deref(),
+        Some("pkg:cargo/crossbeam-channel@0.5.14")
+    );
+}
+
+#[test]
+fn grype_findings_map_all_fields() {
+    let findings = grype_findings();
+    assert_eq!(findings.len(), 2);
+
+    let crate_finding = &findings[0];
+    assert_eq!(crate_finding.id, "GHSA-qc84-gqf4-9926");
+    assert_eq!(crate_finding.aliases, ["CVE-2025-4574"]);
+    assert_eq!(crate_finding.canonical_id(), "CVE-2025-4574");
+    assert_eq!(crate_finding.severity, Severity::High);
+    assert_eq!(crate_finding.cvss.len(), 1);
+    assert_eq!(crate_finding.cvss[0].metrics.base_score.to_string(), "8.1");
+    assert_eq!(crate_finding.fix.state, FixState::Fixed);
+    assert_eq!(crate_finding.fix.versions, ["0.5.15"]);
+    assert_eq!(crate_finding.package.name, "crossbeam-channel");
+    assert_eq!(crate_finding.package.version, "0.5.14");
+    assert_eq!(crate_finding.package.package_type, "rust-crate");
+    assert_eq!(crate_finding.package.locations, ["/usr/local/bin/ssce"]);
+    assert_eq!(crate_finding.source, source());
+
+    // A related vulnerability with the finding's own id is not an alias.
+    let deb_finding = &findings[1];
+    assert!(deb_finding.aliases.is_empty());
+    assert_eq!(deb_finding.fix.state, FixState::WontFix);
+    assert_eq!(deb_finding.package.package_type, "deb");
+    assert_eq!(deb_finding.package.locations.len(), 2);
+}
+
+#[test]
+fn trivy_findings_map_all_fields() {
+    let findings = trivy_findings();
+    assert_eq!(findings.len(), 3);
+
+    let gcc = &findings[0];
+    assert_eq!(gcc.id, "CVE-2023-4039");
+    assert!(gcc.aliases.is_empty());
+    assert_eq!(gcc.severity, Severity::Medium);
+    assert_eq!(gcc.fix.state, FixState::WontFix);
+    assert!(gcc.fix.versions.is_empty());
+    assert_eq!(gcc.package.package_type, "deb");
+    assert_eq!(gcc.cvss.len(), 2);
+    assert_eq!(gcc.cvss[0].source, "nvd");
+    assert_eq!(gcc.cvss[0].cvss_type, "Primary");
+    assert_eq!(gcc.cvss[0].version, "3.1");
+    assert_eq!(gcc.cvss[1].cvss_type, "Secondary");
+
+    let gnutls = &findings[1];
+    assert_eq!(gnutls.aliases, ["DSA-2398-1"]);
+    assert_eq!(gnutls.severity, Severity::Low);
+    assert_eq!(gnutls.fix.state, FixState::NotFixed);
+    assert_eq!(gnutls.cvss[0].version, "2.0");
+    assert_eq!(gnutls.cvss[0].vector_metric("Au"), Some("N"));
+    assert_eq!(gnutls.cvss[0].metrics.base_score.to_string(), "4.3");
+
+    let crossbeam = &findings[2];
+    assert_eq!(crossbeam.canonical_id(), "CVE-2025-4574");
+    assert_eq!(crossbeam.fix.state, FixState::Fixed);
+    assert_eq!(crossbeam.fix.versions, ["0.5.15"]);
+    assert_eq!(crossbeam.package.package_type, "rust-crate");
+    assert_eq!(crossbeam.package.locations, ["usr/local/bin/ssce"]);
+    assert_eq!(
+        crossbeam.package.purl.as_deref(),
+        Some("pkg:cargo/crossbeam-channel@0.5.14")
+    );
+}
+
+#[test]
+fn scanners_agree_on_shared_findings() {
+    let key = |finding: &Finding| {
+        (
+            finding.canonical_id().to_owned(),
+            finding.package.name.clone(),
+            finding.package.version.clone(),
+            finding.package.package_type.clone(),
+        )
+    };
+    let grype = grype_findings().iter().map(key).collect::<Vec<_>>();
+    let trivy = trivy_findings().iter().map(key).collect::<Vec<_>>();
+    for finding in &grype {
+        assert!(
+            trivy.contains(finding),
+            "{finding:?} missing in trivy findings"
+        );
+    }
+}
+
+#[test]
+fn findings_roundtrip() {
+    for finding in grype_findings().into_iter().chain(trivy_findings()) {
+        let json = serde_json::to_string(&finding).unwrap();
+        let parsed: Finding = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, finding);
+    }
+}
+
+#[test]
+fn empty_reports() {
+    let scan: Scan = serde_json::from_str(r#"{"matches": []}"#).unwrap();
+    assert!(from_grype(&source(), &scan).is_empty());
+    assert_eq!(scan.descriptor.name, "");
+
+    let report: TrivyReport = serde_json::from_str(r#"{"SchemaVersion": 2}"#).unwrap();
+    assert!(from_trivy(&source(), &report).is_empty());
+}
+
+#[test]
+fn severities_parse_case_insensitively() {
+    for (value, expected) in [
+        ("CRITICAL", Severity::Critical),
+        ("High", Severity::High),
+        ("medium", Severity::Medium),
+        ("LoW", Severity::Low),
+        ("Negligible", Severity::Negligible),
+        ("UNKNOWN", Severity::Unknown),
+        ("important", Severity::Unknown),
+        ("", Severity::Unknown),
+    ] {
+        assert_eq!(Severity::parse(value), expected, "{value:?}");
+    }
+    let severity: Severity = serde_json::from_str("\"high\"").unwrap();
+    assert_eq!(serde_json::to_string(&severity).unwrap(), "\"High\"");
+}
+
+#[test]
+fn config_severities_are_case_insensitive() {
+    let problems = |severity: &str| {
+        let config = serde_json::json!({ "policy": { "min_severity": severity } });
+        validate(&config, &[])
+            .into_iter()
+            .filter(|problem| problem.path == "policy.min_severity")
+            .count()
+    };
+    assert_eq!(problems("high"), 0);
+    assert_eq!(problems("CRITICAL"), 0);
+    assert_eq!(problems("hihg"), 1);
+}
+
+#[test]
+fn config_runtime_eol_dates_are_validated() {
+    let problems = |date: &str| {
+        let config = serde_json::json!({ "runtime_eol": { "go:1.25": date } });
+        validate(&config, &[])
+            .into_iter()
+            .filter(|problem| problem.path.starts_with("runtime_eol"))
+            .count()
+    };
+    assert_eq!(problems("2026-08-11"), 0);
+    assert_eq!(problems("2026-13-01"), 1);
+    assert_eq!(problems("next year"), 1);
+}
@@ -0,0 +1,200 @@
+//! End-to-end harness for whole runs without containers or scanners: fake `syft`, `grype` and
+//! `docker` executables replay the recorded fixtures, and a fake docker API serves containers
+//! on a unix socket.
+
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use serde_json::{json, Value};
+use software_supply_chain_exporter::config::Config;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixListener,
+};
+
+pub fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// `syft` fails for scan targets containing `broken`, like a registry refusing the image, and
+/// logs every scan target to count cache misses.
+const SYFT: &str = r#"#!/bin/sh
+for target; do :; done
+echo "$target" >> "$(dirname "$0")/syft.log"
+case "$target" in
+*broken*) echo "failed to fetch $target" >&2; exit 1 ;;
+esac
+cat "$FIXTURES/syft.json"
+"#;
+
+/// `grype` reports a fresh database and answers every scan with the recorded scan.
+const GRYPE: &str = r#"#!/bin/sh
+case "$1 $2" in
+"db update") exit 0 ;;
+"db status") echo "{\"built\": \"$(date -u +%Y-%m-%dT%H:%M:%SZ)\", \"schemaVersion\": \"v5\", \"valid\": true}"; exit 0 ;;
+esac
+cat > /dev/null
+cat "$FIXTURES/grype.json"
+"#;
+
+/// `docker buildx imagetools inspect` finds no SBOM attestations.
+const DOCKER: &str = r#"#!/bin/sh
+echo "{}"
+"#;
+
+/// Install the fake executables in front of `PATH`, once for all tests of the binary.
+fn fake_bin() -> &'static Path {
+    static FAKE_BIN: OnceLock<PathBuf> = OnceLock::new();
+    FAKE_BIN.get_or_init(|| {
+        let bin = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("fake-bin");
+        let _ = std::fs::remove_dir_all(&bin);
+        std::fs::create_dir_all(&bin).unwrap();
+        for (name, script) in [("syft", SYFT), ("grype", GRYPE), ("docker", DOCKER)] {
+            let path = bin.join(name);
+            std::fs::write(
+                &path,
+                script.replace("$FIXTURES", &fixtures().to_string_lossy()),
+            )
+            .unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let path =
+            std::env::join_paths(std::iter::once(bin.clone()).chain(std::env::split_paths(&path)))
+                .unwrap();
+        std::env::set_var("PATH", path);
+        bin
+    })
+}
+
+/// Number of times syft was run for the scan target.
+pub fn syft_runs(target: &str) -> usize {
+    std::fs::read_to_string(fake_bin().join("syft.log"))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| *line == target)
+        .count()
+}
+
+/// A running container of the fake docker API.
+pub struct Container {
+    pub name: &'static str,
+    pub image: &'static str,
+    pub image_id: &'static str,
+}
+
+pub struct Harness {
+    pub base_path: PathBuf,
+    pub config: Config,
+    /// Paths of the requests to the fake docker API.
+    pub docker_requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Harness {
+    /// A harness with its own base path, serving the containers on the fake docker API. Runs
+    /// of the same test share the base path, so the cache carries over between them.
+    pub async fn new(test: &str, containers: &[Container]) -> Self {
+        fake_bin();
+        let base_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+        let _ = std::fs::remove_dir_all(&base_path);
+        std::fs::create_dir_all(&base_path).unwrap();
+        let socket = base_path.join("docker.sock");
+        let docker_requests = serve_docker(&socket, containers);
+        let config = serde_json::from_value(json!({
+            "base_path": base_path,
+            "cache_duration": "1d",
+            "excludes": [],
+            // Only the containers of the fake docker API are scanned, not the host.
+            "directories": [],
+            "generate_sboms": true,
+            "sbom_metrics": true,
+            "scan_date_label": false,
+            "docker_socket": socket,
+            "preflight": { "min_free_mb": 0, "min_free_workspace_mb": 0 },
+        }))
+        .unwrap();
+        Self {
+            base_path,
+            config,
+            docker_requests,
+        }
+    }
+
+    pub fn metrics(&self) -> String {
+        std::fs::read_to_string(self.config.metrics_path()).unwrap()
+    }
+
+    pub fn report(&self) -> Value {
+        serde_json::from_slice(&std::fs::read(self.config.report_path()).unwrap()).unwrap()
+    }
+}
+
+/// Serve the containers and their images like the docker API does, one request per
+/// connection.
+fn serve_docker(socket: &Path, containers: &[Container]) -> Arc<Mutex<Vec<String>>> {
+    let listener = UnixListener::bind(socket).unwrap();
+    let container_list: Vec<Value> = containers
+        .iter()
+        .map(|container| {
+            json!({
+                "Id": container.name,
+                "Names": [format!("/{}", container.name)],
+                "Image": container.image,
+                "ImageID": container.image_id,
+                "Labels": {},
+            })
+        })
+        .collect();
+    let image_list: Vec<Value> = containers
+        .iter()
+        .map(|container| {
+            json!({
+                "Id": container.image_id,
+                "ParentId": "",
+                "RepoTags": [container.image],
+                "RepoDigests": [],
+                "Created": 0,
+                "Size": 1_000_000,
+                "SharedSize": -1,
+                "Labels": {},
+                "Containers": 1,
+            })
+        })
+        .collect();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let served = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                return;
+            };
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match stream.read(&mut buffer).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => request.extend_from_slice(&buffer[..read]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            served.lock().unwrap().push(path.to_owned());
+            let (status, body) = match path.split('?').next().unwrap_or_default() {
+                path if path.ends_with("/containers/json") => ("200 OK", json!(container_list)),
+                path if path.ends_with("/images/json") => ("200 OK", json!(image_list)),
+                _ => ("404 Not Found", json!({ "message": "not found" })),
+            };
+            let body = body.to_string();
+            let response = format!(
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    requests
+}
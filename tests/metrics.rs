@@ -0,0 +1,306 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use software_supply_chain_exporter::{
+    config::{Config, Source, Tags},
+    malicious::load_malicious_packages,
+    metrics::{encode_metrics, sort_series, UnversionedPackages},
+    report::Report,
+    scan::Scan,
+};
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Compare with the golden file, or update it when `UPDATE_GOLDEN` is set.
+fn assert_golden(name: &str, actual: &str) {
+    let path = fixtures().join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        actual, expected,
+        "{name} differs, rerun with UPDATE_GOLDEN=1 if the change is intended"
+    );
+}
+
+fn config() -> Config {
+    // The base path doesn't exist, so the cache is empty.
+    serde_json::from_value(json!({
+        "base_path": PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden-metrics"),
+        "cache_duration": "1d",
+        "excludes": [],
+        "generate_sboms": false,
+        "sbom_metrics": true,
+        // The scan date label is the current date.
+        "scan_date_label": false,
+    }))
+    .unwrap()
+}
+
+fn sources() -> HashMap<Source, Tags> {
+    let image = Source::DockerImage {
+        name: "ghcr.io/famedly/example:latest".into(),
+        id: "sha256:0123456789abcdef".into(),
+        digest: None,
+    };
+    let directory = Source::HostDirectory {
+        path: "/opt/app".into(),
+    };
+    HashMap::from([
+        (image, Tags::from([("team".into(), "backend".into())])),
+        (directory, Tags::new()),
+    ])
+}
+
+fn sbom(packages: &[(&str, &str, &str)]) -> Value {
+    let packages: Vec<Value> = packages
+        .iter()
+        .map(|(name, version, purl)| {
+            let download_location = match purl.split('/').next() {
+                Some("pkg:cargo") => format!("https://static.crates.io/crates/{name}/{version}"),
+                Some("pkg:pypi") => format!("https://pypi.mirror.example.com/{name}/{version}"),
+                _ => "NOASSERTION".into(),
+            };
+            json!({
+                "name": name,
+                "versionInfo": version,
+                "supplier": "NOASSERTION",
+                "downloadLocation": download_location,
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }],
+            })
+        })
+        .collect();
+    json!({ "packages": packages })
+}
+
+fn encode() -> String {
+    encode_with(&config())
+}
+
+fn encode_with(config: &Config) -> String {
+    let sources = sources();
+    let sboms: HashMap<Source, Value> = sources
+        .keys()
+        .map(|source| {
+            let sbom = match source {
+                Source::DockerImage { .. } => sbom(&[
+                    (
+                        "crossbeam-channel",
+                        "0.5.14",
+                        "pkg:cargo/crossbeam-channel@0.5.14",
+                    ),
+                    ("libc", "0.2.150", "pkg:cargo/libc@0.2.150"),
+                    // The same package at another location.
+                    ("libc", "0.2.150", "pkg:cargo/libc@0.2.150"),
+                    ("musl", "1.2.4-r2", "pkg:apk/alpine/musl@1.2.4-r2"),
+                ]),
+                _ => sbom(&[
+                    ("requests", "2.31.0", "pkg:pypi/requests@2.31.0"),
+                    ("urllib3", "2.0.7", "pkg:pypi/urllib3@2.0.7"),
+                    // Binary catalogers find packages without version.
+                    ("busybox", "", "pkg:generic/busybox"),
+                ]),
+            };
+            (source.clone(), sbom)
+        })
+        .collect();
+    let mut scan: Scan =
+        serde_json::from_str(&std::fs::read_to_string(fixtures().join("grype.json")).unwrap())
+            .unwrap();
+    // The age of fixes depends on the current date.
+    for entry in &mut scan.matches {
+        entry.vulnerability.fix.available.clear();
+    }
+    let scans: HashMap<Source, Scan> = sources
+        .keys()
+        .map(|source| (source.clone(), scan.clone()))
+        .collect();
+
+    let started = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+    let mut report = Report::new(
+        "20240102T030405Z-1".into(),
+        started,
+        None,
+        &sources,
+        &sboms,
+        &scans,
+        Vec::new(),
+    );
+    if let Some(malicious) = &config.malicious_packages {
+        report.add_suspicious_packages(malicious, &load_malicious_packages(malicious), &sboms);
+    }
+    encode_metrics(
+        config,
+        &sources,
+        sboms,
+        scans,
+        &HashMap::new(),
+        &HashMap::new(),
+        &report,
+    )
+    .unwrap()
+}
+
+#[test]
+fn textfile_output_matches_golden_file() {
+    assert_golden("metrics.txt", &encode());
+}
+
+#[test]
+fn textfile_output_is_stable() {
+    // Every encode uses new hash maps with their own random iteration order.
+    let first = encode();
+    for _ in 0..10 {
+        assert_eq!(encode(), first);
+    }
+}
+
+#[test]
+fn unversioned_packages_are_configurable() {
+    let mut config = config();
+    assert!(!encode_with(&config).contains("busybox"));
+
+    config.unversioned_packages = UnversionedPackages::Unknown;
+    assert!(encode_with(&config).contains("sbom_total{software=\"busybox\",version=\"unknown\","));
+
+    config.unversioned_packages = UnversionedPackages::Separate;
+    let encoded = encode_with(&config);
+    assert!(!encoded.contains("sbom_total{software=\"busybox\""));
+    assert!(encoded.contains(
+        "unversioned_packages{software=\"busybox\",image=\"\",id=\"\",digest=\"\",path=\"/opt/app\",\
+         vendor_sbom=\"\"} 1"
+    ));
+}
+
+#[test]
+fn families_and_series_are_sorted() {
+    let encoded = "# HELP b B.\n# TYPE b gauge\nb{x=\"2\"} 1\nb{x=\"1\"} 1\n\
+                   # HELP a A.\n# TYPE a gauge\na 1\n# EOF\n";
+    assert_eq!(
+        sort_series(encoded),
+        "# HELP a A.\n# TYPE a gauge\na 1\n\
+         # HELP b B.\n# TYPE b gauge\nb{x=\"1\"} 1\nb{x=\"2\"} 1\n# EOF\n"
+    );
+}
+
+#[test]
+fn accepted_findings_are_labeled() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("acceptances.yaml");
+    std::fs::write(
+        &path,
+        "- cve: CVE-2025-4574\n  scope:\n    source: ghcr.io/famedly/\n  justification: Not \
+         reachable\n  approver: security@example.com\n  expires: 2999-12-30\n\
+         - cve: CVE-2023-4039\n  justification: ''\n  approver: nobody\n  expires: 2999-12-30\n",
+    )
+    .unwrap();
+    let mut config = config();
+    config.acceptances = Some(path);
+    let encoded = encode_with(&config);
+
+    let scans: Vec<&str> = encoded
+        .lines()
+        .filter(|line| line.starts_with("vulnerability_scans_total"))
+        .collect();
+    let accepted = |cve: &str, source: &str| {
+        scans
+            .iter()
+            .find(|line| line.contains(&format!("cve=\"{cve}\"")) && line.contains(source))
+            .unwrap()
+            .contains("accepted=\"true\"")
+    };
+    // Accepted by an alias, only within the scope.
+    assert!(accepted("GHSA-qc84-gqf4-9926", "ghcr.io/famedly/example"));
+    assert!(!accepted("GHSA-qc84-gqf4-9926", "/opt/app"));
+    // Acceptances without justification don't apply.
+    assert!(!accepted("CVE-2023-4039", "ghcr.io/famedly/example"));
+
+    assert!(encoded.contains(
+        "vulnerability_acceptance_expiry_timestamp_seconds{cve=\"CVE-2025-4574\",package=\"\",\
+         scope=\"ghcr.io/famedly/\",approver=\"security@example.com\",valid=\"true\"} 32503593600"
+    ));
+}
+
+#[test]
+fn groups_are_rolled_up() {
+    let mut config = config();
+    config.groups = serde_json::from_value(json!([
+        { "name": "all" },
+        { "name": "famedly", "sources": ["ghcr.io/famedly/"] },
+        { "name": "backend", "tags": { "team": "backend" }, "sources": ["/opt/"] },
+    ]))
+    .unwrap();
+    let encoded = encode_with(&config);
+
+    for expected in [
+        "group_sources{group=\"all\"} 2",
+        "group_vulnerabilities{group=\"all\"} 4",
+        "group_sources{group=\"famedly\"} 1",
+        "group_vulnerabilities{group=\"famedly\"} 2",
+        "group_highest_severity{group=\"famedly\"} 4",
+        // Sources need to match both the tags and a prefix.
+        "group_sources{group=\"backend\"} 0",
+    ] {
+        assert!(encoded.contains(expected), "missing {expected}");
+    }
+}
+
+#[test]
+fn unexpected_download_hosts_are_flagged() {
+    let mut config = config();
+    config.package_origins = serde_json::from_value(json!({
+        "metric": true,
+        "expected_hosts": ["crates.io"],
+    }))
+    .unwrap();
+    let encoded = encode_with(&config);
+
+    for expected in [
+        "package_unexpected_origin{package=\"requests\",version=\"2.31.0\",supplier=\"\",\
+         originator=\"\",download_host=\"pypi.mirror.example.com\"",
+        "packages_by_download_host{download_host=\"pypi.mirror.example.com\",\
+         unexpected_host=\"true\"",
+        "packages_by_download_host{download_host=\"static.crates.io\",unexpected_host=\"false\",\
+         image=\"ghcr.io/famedly/example:latest\"",
+    ] {
+        assert!(encoded.contains(expected), "missing {expected}");
+    }
+    // Packages from expected hosts are only counted.
+    assert!(!encoded.contains("package_unexpected_origin{package=\"libc\""));
+}
+
+#[test]
+fn malicious_packages_and_typosquats_are_flagged() {
+    let mut config = config();
+    config.malicious_packages = serde_json::from_value(json!({
+        "osv_path": fixtures().join("malicious"),
+        "internal_packages": {
+            "pypi": ["urllib4"],
+            "cargo": ["crossbeam-channel"],
+            "npm": ["libcc"],
+        },
+    }))
+    .unwrap();
+    let encoded = encode_with(&config);
+
+    for expected in [
+        "suspicious_package{package=\"libc\",version=\"0.2.150\",reason=\"malicious\",\
+         advisory=\"MAL-0000-0001\",similar_to=\"\"",
+        "suspicious_package{package=\"urllib3\",version=\"2.0.7\",reason=\"typosquat\",\
+         advisory=\"\",similar_to=\"urllib4\"",
+    ] {
+        assert!(encoded.contains(expected), "missing {expected}");
+    }
+    // Only other versions of requests are malicious, and internal packages aren't typosquats.
+    assert!(!encoded.contains("suspicious_package{package=\"requests\""));
+    assert!(!encoded.contains("suspicious_package{package=\"crossbeam-channel\""));
+    // Names of internal packages of other ecosystems don't count.
+    assert!(!encoded.contains("similar_to=\"libcc\""));
+}
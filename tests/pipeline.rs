@@ -0,0 +1,324 @@
+#![cfg(all(unix, feature = "docker"))]
+
+mod harness;
+
+use harness::{syft_runs, Container, Harness};
+use software_supply_chain_exporter::{
+    admission::AdmissionConfig,
+    error::SsceError,
+    hashes::find_hash,
+    policy::PolicyConfig,
+    query::{find, Query},
+    recording::Recording,
+    run::run_scan,
+    schedule::Scheduler,
+    severity::Severity,
+    webhook::{pushed_images, record_pushes, WebhookConfig},
+};
+
+#[tokio::test]
+async fn run_exports_metrics_and_report() {
+    let harness = Harness::new(
+        "pipeline-export",
+        &[Container {
+            name: "web",
+            image: "example/export:1.0",
+            image_id: "sha256:e1",
+        }],
+    )
+    .await;
+
+    let report = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert_eq!(report.sources.len(), 1);
+    assert_eq!(report.summary.packages, 2);
+    assert_eq!(report.summary.findings, 2);
+    assert!(report.failed.is_empty());
+
+    let metrics = harness.metrics();
+    assert!(metrics.contains(
+        "sbom_packages{image=\"example/export:1.0\",id=\"sha256:e1\",digest=\"\",path=\"\",\
+         vendor_sbom=\"\"} 2"
+    ));
+    assert!(metrics.contains("cve=\"GHSA-qc84-gqf4-9926\""));
+    assert!(metrics.contains("sbom_source{method=\"syft\",image=\"example/export:1.0\""));
+    assert!(metrics.ends_with("# EOF\n"));
+    assert_eq!(harness.report()["run_id"], report.run_id.as_str());
+    assert!(harness
+        .docker_requests
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|path| path.contains("/containers/json")));
+}
+
+#[tokio::test]
+async fn failed_sbom_does_not_fail_the_run() {
+    let harness = Harness::new(
+        "pipeline-failed-sbom",
+        &[
+            Container {
+                name: "web",
+                image: "example/healthy:1.0",
+                image_id: "sha256:f1",
+            },
+            Container {
+                name: "worker",
+                image: "example/broken:1.0",
+                image_id: "sha256:f2",
+            },
+        ],
+    )
+    .await;
+
+    let report = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert_eq!(report.sources.len(), 1);
+    assert_eq!(report.failed.len(), 1);
+    assert!(report.failed[0].to_string().contains("example/broken:1.0"));
+
+    let metrics = harness.metrics();
+    assert!(metrics.contains("example/healthy:1.0"));
+    assert!(!metrics.contains("example/broken:1.0"));
+    assert!(!metrics.contains("errors_total{kind=\"sbom\"} 0"));
+}
+
+#[tokio::test]
+async fn cached_sboms_are_reused() {
+    let harness = Harness::new(
+        "pipeline-cache",
+        &[Container {
+            name: "web",
+            image: "example/cached:1.0",
+            image_id: "sha256:c1",
+        }],
+    )
+    .await;
+
+    for _ in 0..2 {
+        run_scan(&harness.config, &mut Scheduler::default())
+            .await
+            .unwrap();
+    }
+    assert_eq!(syft_runs("example/cached:1.0"), 1);
+    assert!(harness
+        .metrics()
+        .contains("sbom_source{method=\"cache\",image=\"example/cached:1.0\""));
+}
+
+#[tokio::test]
+async fn unreachable_docker_fails_discovery() {
+    let mut harness = Harness::new("pipeline-no-docker", &[]).await;
+    harness.config.docker_socket = Some(
+        harness
+            .base_path
+            .join("missing.sock")
+            .to_string_lossy()
+            .into_owned(),
+    );
+
+    let error = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<SsceError>(),
+        Some(SsceError::Discovery(_))
+    ));
+    assert_eq!(SsceError::exit_code_of(&error), 2);
+}
+
+#[tokio::test]
+async fn recorded_run_replays_offline() {
+    let mut recorded = Harness::new(
+        "pipeline-record",
+        &[Container {
+            name: "web",
+            image: "example/recorded:1.0",
+            image_id: "sha256:r1",
+        }],
+    )
+    .await;
+    let recording = recorded.base_path.join("recording");
+    recorded.config.recording = Some(Recording::Record(recording.clone()));
+    let original = run_scan(&recorded.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert!(recording.join("docker/containers.json").exists());
+    assert!(recording.join("syft/sha256_r1.stdout").exists());
+
+    // The fake docker API of the replay has no containers, so everything comes from the
+    // recording.
+    let mut replayed = Harness::new("pipeline-replay", &[]).await;
+    replayed.config.recording = Some(Recording::Replay(recording));
+    let replay = run_scan(&replayed.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert_eq!(syft_runs("example/recorded:1.0"), 1);
+    assert_eq!(replay.summary.sources, 1);
+    assert_eq!(replay.summary.findings, original.summary.findings);
+    assert!(replayed.metrics().contains("example/recorded:1.0"));
+}
+
+#[tokio::test]
+async fn admission_verdicts_follow_the_policy() {
+    let mut harness = Harness::new(
+        "pipeline-admission",
+        &[Container {
+            name: "web",
+            image: "example/admission:1.0",
+            image_id: "sha256:a1",
+        }],
+    )
+    .await;
+    let path = harness.base_path.join("admission.json");
+    harness.config.policy = Some(PolicyConfig {
+        min_severity: Severity::High,
+        only_fixed: false,
+    });
+    harness.config.admission = Some(AdmissionConfig {
+        path: Some(path.clone()),
+        url: None,
+        headers: Default::default(),
+    });
+
+    let report = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    let verdicts: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    let verdict = &verdicts["sha256:a1"];
+    assert_eq!(verdict["verdict"], "fail");
+    assert_eq!(verdict["image"], "example/admission:1.0");
+    assert_eq!(verdict["violations"], 1);
+    assert_eq!(verdict["run_id"], report.run_id.as_str());
+}
+
+#[tokio::test]
+async fn pushed_images_are_scanned_from_the_registry() {
+    let mut harness = Harness::new("pipeline-webhook", &[]).await;
+    harness.config.webhook = Some(WebhookConfig {
+        listen: "127.0.0.1:0".into(),
+        token: None,
+        retention: std::time::Duration::from_secs(60 * 60),
+    });
+    let event = serde_json::json!({
+        "type": "PUSH_ARTIFACT",
+        "event_data": {
+            "resources": [{
+                "digest": "sha256:b1",
+                "tag": "1.0",
+                "resource_url": "harbor.example.com/library/pushed:1.0",
+            }],
+        },
+    });
+    let pushed = pushed_images(&event, chrono::Utc::now());
+    assert_eq!(pushed.len(), 1);
+    record_pushes(&harness.config, pushed).unwrap();
+
+    let report = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert_eq!(report.sources.len(), 1);
+    assert_eq!(syft_runs("harbor.example.com/library/pushed:1.0"), 1);
+    let metrics = harness.metrics();
+    assert!(metrics.contains("tag_origin=\"registry\",tag_registry=\"harbor.example.com\""));
+    assert!(metrics.contains("digest=\"harbor.example.com/library/pushed@sha256:b1\""));
+}
+
+#[tokio::test]
+async fn binaries_are_found_by_hash() {
+    let mut harness = Harness::new(
+        "pipeline-hashes",
+        &[Container {
+            name: "web",
+            image: "example/hashes:1.0",
+            image_id: "sha256:h1",
+        }],
+    )
+    .await;
+    harness.config.binary_hashes = true;
+    run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+
+    let digest = "SHA256:9B1F2E9D7A6C0C1D5B7E3F4A8C2D6E0F1A3B5C7D9E1F2A4B6C8D0E2F4A6B8C0D";
+    let matches = find_hash(&harness.config, digest).unwrap();
+    // Found in the report and in the cached SBOM of the image.
+    assert_eq!(matches.len(), 2);
+    assert!(matches
+        .iter()
+        .all(|found| found.path == "/usr/lib/libgcc_s.so.1"));
+    assert!(matches
+        .iter()
+        .any(|found| found.location.contains("example/hashes:1.0")));
+    assert!(find_hash(&harness.config, "sha256:00").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn cached_results_are_queryable() {
+    let harness = Harness::new(
+        "pipeline-find",
+        &[Container {
+            name: "web",
+            image: "example/find:1.0",
+            image_id: "sha256:q1",
+        }],
+    )
+    .await;
+    run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+
+    let query = |package: Option<&str>, version: Option<&str>, cve: Option<&str>| Query {
+        package: package.map(Into::into),
+        version: version.map(|version| version.parse().unwrap()),
+        cve: cve.map(Into::into),
+    };
+    let packages = find(&harness.config, &query(Some("libgcc"), Some("<14"), None)).unwrap();
+    // Listed once, by the source rather than the cache file.
+    assert_eq!(packages.len(), 1);
+    assert!(packages[0].location.contains("example/find:1.0"));
+    assert_eq!(packages[0].version, "13.2.1_git20231014-r0");
+    assert!(find(
+        &harness.config,
+        &query(Some("libgcc"), Some(">=13.3"), None)
+    )
+    .unwrap()
+    .is_empty());
+
+    let findings = find(
+        &harness.config,
+        &query(None, None, Some("GHSA-qc84-gqf4-9926")),
+    )
+    .unwrap();
+    assert_eq!(findings.len(), 1);
+    assert!(findings[0].location.contains("example/find:1.0"));
+    assert_eq!(findings[0].cve.as_deref(), Some("GHSA-qc84-gqf4-9926"));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn saturated_host_defers_scans() {
+    let mut harness = Harness::new(
+        "pipeline-load-guard",
+        &[Container {
+            name: "web",
+            image: "example/deferred:1.0",
+            image_id: "sha256:d1",
+        }],
+    )
+    .await;
+    // Any load is above a negative maximum, and nothing is waited for.
+    harness.config.load_guard =
+        serde_json::from_value(serde_json::json!({ "max_load_per_cpu": -1.0 })).unwrap();
+    let report = run_scan(&harness.config, &mut Scheduler::default())
+        .await
+        .unwrap();
+    assert!(report.sources.is_empty());
+    assert!(report.failed.is_empty());
+    assert_eq!(report.deferred.len(), 1);
+    assert_eq!(syft_runs("example/deferred:1.0"), 0);
+    assert!(harness.metrics().contains("scans_deferred_total"));
+}
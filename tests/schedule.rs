@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde_json::json;
+use software_supply_chain_exporter::schedule::ScheduleConfig;
+
+fn schedule(
+    windows: &[(&str, &str)],
+    blackouts: &[(DateTime<Utc>, DateTime<Utc>)],
+) -> ScheduleConfig {
+    serde_json::from_value(json!({
+        "windows": windows
+            .iter()
+            .map(|(start, end)| json!({ "start": start, "end": end }))
+            .collect::<Vec<_>>(),
+        "blackouts": blackouts
+            .iter()
+            .map(|(start, end)| json!({ "start": start, "end": end }))
+            .collect::<Vec<_>>(),
+    }))
+    .unwrap()
+}
+
+/// Local time on a day without daylight saving time transitions.
+fn at(hour: u32, minute: u32) -> DateTime<Local> {
+    let time = NaiveDate::from_ymd_opt(2025, 1, 15)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap();
+    Local.from_local_datetime(&time).unwrap()
+}
+
+fn hours(hours: u64) -> Duration {
+    Duration::from_secs(hours * 60 * 60)
+}
+
+#[test]
+fn scans_are_allowed_without_windows() {
+    assert_eq!(
+        schedule(&[], &[]).until_scan_allowed(at(12, 0)),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn windows_exclude_their_end() {
+    let schedule = schedule(&[("02:00", "05:00")], &[]);
+    assert_eq!(schedule.until_scan_allowed(at(2, 0)), Duration::ZERO);
+    assert_eq!(schedule.until_scan_allowed(at(4, 59)), Duration::ZERO);
+    assert_eq!(schedule.until_scan_allowed(at(5, 0)), hours(21));
+    assert_eq!(schedule.until_scan_allowed(at(1, 0)), hours(1));
+}
+
+#[test]
+fn windows_wrap_around_midnight() {
+    let schedule = schedule(&[("22:00", "04:00")], &[]);
+    assert_eq!(schedule.until_scan_allowed(at(23, 0)), Duration::ZERO);
+    assert_eq!(schedule.until_scan_allowed(at(3, 0)), Duration::ZERO);
+    assert_eq!(schedule.until_scan_allowed(at(4, 0)), hours(18));
+    assert_eq!(schedule.until_scan_allowed(at(21, 0)), hours(1));
+}
+
+#[test]
+fn the_next_window_to_open_applies() {
+    let schedule = schedule(&[("02:00", "03:00"), ("14:00", "15:00")], &[]);
+    assert_eq!(schedule.until_scan_allowed(at(12, 0)), hours(2));
+    assert_eq!(schedule.until_scan_allowed(at(16, 0)), hours(10));
+}
+
+#[test]
+fn blackouts_delay_scans_within_windows() {
+    let now = at(2, 0);
+    let utc = now.with_timezone(&Utc);
+    let blackout = (
+        utc - chrono::Duration::hours(1),
+        utc + chrono::Duration::hours(2),
+    );
+    let schedule = schedule(&[("01:00", "06:00")], &[blackout]);
+    assert!(schedule.in_blackout(now));
+    assert_eq!(schedule.until_scan_allowed(now), hours(2));
+    assert!(!schedule.in_blackout(at(4, 0)));
+    assert_eq!(schedule.until_scan_allowed(at(4, 0)), Duration::ZERO);
+}
@@ -0,0 +1,81 @@
+use serde_json::json;
+use software_supply_chain_exporter::validate::validate_sbom;
+
+#[test]
+fn syft_sboms_are_valid_spdx() {
+    let sbom = serde_json::from_str(
+        &std::fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/syft.json"
+        ))
+        .unwrap(),
+    )
+    .unwrap();
+    let validation = validate_sbom(&sbom);
+    assert!(validation.format.starts_with("SPDX-2."));
+    assert!(validation.errors.is_empty(), "{:?}", validation.errors);
+    assert!(validation.packages > 0);
+}
+
+#[test]
+fn spdx_problems_are_reported() {
+    let validation = validate_sbom(&json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": "example",
+        "creationInfo": { "created": "2024-01-01T00:00:00Z" },
+        "packages": [
+            { "SPDXID": "SPDXRef-a", "name": "a", "downloadLocation": "NOASSERTION" },
+            { "name": "b", "versionInfo": "1.0" },
+        ],
+    }));
+    assert_eq!(validation.format, "SPDX-2.3");
+    assert_eq!(validation.packages, 2);
+    assert_eq!(
+        validation.errors,
+        [
+            "document: missing documentNamespace",
+            "creationInfo: missing creators",
+            "package b: missing SPDXID",
+            "package b: missing downloadLocation",
+        ]
+    );
+    assert_eq!(validation.warnings.len(), 3);
+    assert!(validation.warnings[0].starts_with("package a: missing versionInfo"));
+    assert!(validation.warnings[1].starts_with("package a: missing purl"));
+    assert!(validation.warnings[2].starts_with("package b: missing purl"));
+}
+
+#[test]
+fn cyclonedx_problems_are_reported() {
+    let validation = validate_sbom(&json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "components": [
+            {
+                "type": "library",
+                "name": "a",
+                "version": "1.0",
+                "purl": "pkg:npm/a@1.0",
+            },
+            { "name": "b" },
+        ],
+    }));
+    assert_eq!(validation.format, "CycloneDX 1.5");
+    assert_eq!(validation.packages, 2);
+    assert_eq!(validation.errors, ["package b: missing type"]);
+    assert_eq!(validation.warnings.len(), 2);
+
+    let validation = validate_sbom(&json!({ "bomFormat": "SPDX" }));
+    assert!(validation
+        .errors
+        .contains(&"document: bomFormat must be CycloneDX".to_owned()));
+}
+
+#[test]
+fn other_documents_are_rejected() {
+    let validation = validate_sbom(&json!({ "packages": [] }));
+    assert_eq!(validation.format, "unknown");
+    assert_eq!(validation.errors.len(), 1);
+}